@@ -2,5 +2,5 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
 /// Wrapper around `time::OffsetDateTime` which serializes according to RFC3339.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DateTime(#[serde(with = "time::serde::rfc3339")] pub OffsetDateTime);
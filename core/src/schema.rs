@@ -74,6 +74,39 @@ pub struct Attribute {
 }
 
 impl Attribute {
+    /// The attributes which are part of every resource, but which are not defined by any
+    /// schema: `id`, `externalId` and `meta`.
+    pub fn common() -> Vec<Attribute> {
+        vec![
+            Attribute::new("id".into(), Type::String)
+                .case_exact()
+                .read_only()
+                .always_returned()
+                .unique(),
+            Attribute::new("externalId".into(), Type::String).case_exact(),
+            Attribute::new("meta".into(), Type::Complex)
+                .read_only()
+                .sub_attributes(vec![
+                    Attribute::new("resourceType".into(), Type::String)
+                        .case_exact()
+                        .read_only(),
+                    Attribute::new("created".into(), Type::DateTime).read_only(),
+                    Attribute::new("lastModified".into(), Type::DateTime).read_only(),
+                    Attribute::new("location".into(), Type::Reference)
+                        .case_exact()
+                        .read_only(),
+                    Attribute::new("version".into(), Type::String)
+                        .case_exact()
+                        .read_only(),
+                ]),
+        ]
+    }
+    /// Find an attribute in a list by name, ignoring case.
+    pub fn find<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+        attributes
+            .iter()
+            .find(|attr| attr.name.eq_ignore_ascii_case(name))
+    }
     /// Construct a new attribute.
     pub fn new(name: String, type_: Type) -> Self {
         Self {
@@ -156,16 +189,29 @@ impl Attribute {
         self.reference_types = Some(reference_types);
         self
     }
+    /// Find a sub-attribute by name, ignoring case.
+    pub fn sub_attribute(&self, name: &str) -> Option<&Attribute> {
+        Self::find(self.sub_attributes.as_deref()?, name)
+    }
+    /// The attribute which a filter compares when it compares this attribute with a value. A
+    /// complex attribute is compared using its `value` sub-attribute, if it has one.
+    pub fn comparison_attribute(&self) -> Option<&Attribute> {
+        match self.type_ {
+            Type::Complex => self.sub_attribute("value"),
+            _ => Some(self),
+        }
+    }
 }
 
 /// The mutability of an attribute.
 #[allow(unused)]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum Mutability {
     /// Clients can only read this attribute.
     ReadOnly,
     /// Clients can read and write this attribute (default).
+    #[default]
     ReadWrite,
     /// Clients can set this value on creation, but not update it.
     Immutable,
@@ -173,15 +219,9 @@ pub enum Mutability {
     WriteOnly,
 }
 
-impl Default for Mutability {
-    fn default() -> Self {
-        Self::ReadWrite
-    }
-}
-
 /// When an attribute is returned.
 #[allow(unused)]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum Returned {
     /// The attribute is always returned.
@@ -189,23 +229,19 @@ pub enum Returned {
     /// The attribute is never returned.
     Never,
     /// The attribute is returned by default (default).
+    #[default]
     Default,
     /// The attribute is returned on request.
     Request,
 }
 
-impl Default for Returned {
-    fn default() -> Self {
-        Self::Default
-    }
-}
-
 /// The uniqueness of an attribute.
 #[allow(unused)]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum Uniqueness {
     /// The attribute is not unique (default).
+    #[default]
     None,
     /// The attribute is unique on this server.
     Server,
@@ -213,18 +249,13 @@ pub enum Uniqueness {
     Global,
 }
 
-impl Default for Uniqueness {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 /// The data type of an attribute.
 #[allow(unused)]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum Type {
     /// A string.
+    #[default]
     String,
     /// A boolean.
     Boolean,
@@ -241,9 +272,3 @@ pub enum Type {
     /// A complex type with sub-attributes.
     Complex,
 }
-
-impl Default for Type {
-    fn default() -> Self {
-        Self::String
-    }
-}
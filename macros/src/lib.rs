@@ -14,12 +14,12 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
 use serde::de::DeserializeOwned;
 use syn::{
-    bracketed,
+    braced, bracketed,
     parse::{Parse, ParseStream},
     parse_macro_input,
     punctuated::Punctuated,
     token::Bracket,
-    Ident, Token,
+    Ident, LitStr, Token,
 };
 
 #[allow(unused)]
@@ -29,21 +29,60 @@ struct DeclareResource {
     name: Ident,
     bracket_token: Bracket,
     schemas: Punctuated<ReferencedSchema, Token![,]>,
+    filters: Vec<DeclaredFilter>,
 }
 
 struct ReferencedSchema {
     path: String,
 }
 
+// A supported filter attribute, such as `"emails.value": eq, sw`.
+struct DeclaredFilter {
+    path: LitStr,
+    operators: Punctuated<Ident, Token![,]>,
+}
+
 impl Parse for DeclareResource {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let content;
+        let path = input.parse::<LitStr>()?.value();
+        let as_ = input.parse::<Token![as]>()?;
+        let name = input.parse::<Ident>()?;
+        let bracket_token = bracketed!(content in input);
+        let schemas = Punctuated::parse_terminated(&content)?;
+        let mut filters = Vec::new();
+        if !input.is_empty() {
+            let keyword = input.parse::<Ident>()?;
+            if keyword != "filter" {
+                return Err(syn::Error::new(keyword.span(), "expected `filter`"));
+            }
+            let content;
+            braced!(content in input);
+            while !content.is_empty() {
+                filters.push(content.parse()?);
+                if !content.is_empty() {
+                    content.parse::<Token![;]>()?;
+                }
+            }
+        }
         Ok(Self {
-            path: input.parse::<syn::LitStr>()?.value(),
-            as_: input.parse::<Token![as]>()?,
-            name: input.parse::<Ident>()?,
-            bracket_token: bracketed!(content in input),
-            schemas: Punctuated::parse_terminated(&content)?,
+            path,
+            as_,
+            name,
+            bracket_token,
+            schemas,
+            filters,
+        })
+    }
+}
+
+impl Parse for DeclaredFilter {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        input.parse::<Token![:]>()?;
+        Ok(Self {
+            path,
+            operators: Punctuated::parse_separated_nonempty(input)?,
         })
     }
 }
@@ -100,6 +139,7 @@ fn declare_manager_trait(
     create_ty: Ident,
     resource_type_str: &str,
     schemas: &HashMap<String, (Schema, String)>,
+    filter_capabilities: TokenStream2,
 ) -> TokenStream2 {
     let adapter = format_ident!("{}Adapter", manager);
     let schema_arms = schemas.iter().map(|(schema_id, (_, schema_str))| {
@@ -135,17 +175,26 @@ fn declare_manager_trait(
             async fn replace(
                 &self,
                 parts: &'async_trait ::cream::hidden::axum::http::request::Parts,
-                id: &'async_trait str, resource: #create_ty
+                id: &'async_trait str, resource: #create_ty,
+                version: ::std::option::Option<&'async_trait str>
             ) -> Result<(), ::cream::Error>;
             async fn delete(
                 &self,
                 parts: &'async_trait ::cream::hidden::axum::http::request::Parts,
-                id: &'async_trait str
+                id: &'async_trait str,
+                version: ::std::option::Option<&'async_trait str>
             ) -> ::std::result::Result<(), ::cream::Error>;
 
             fn default_page_size(&self) -> usize {
                 50
             }
+            fn max_page_size(&self) -> usize {
+                usize::MAX
+            }
+            fn can_push_down(&self, filter: &::cream::filter::FilterRef) -> bool {
+                let _ = filter;
+                true
+            }
         }
 
         #[derive(Debug)]
@@ -166,6 +215,8 @@ fn declare_manager_trait(
                     }).collect(),
                     total_count: result.total_count,
                     items_per_page: result.items_per_page,
+                    next_cursor: result.next_cursor,
+                    previous_cursor: result.previous_cursor,
                 })
             }
 
@@ -200,22 +251,31 @@ fn declare_manager_trait(
                 &self,
                 parts: &'async_trait ::cream::hidden::axum::http::request::Parts,
                 id: &str,
-                resource: ::cream::hidden::ijson::IObject
+                resource: ::cream::hidden::ijson::IObject,
+                version: ::std::option::Option<&'async_trait str>
             ) -> ::std::result::Result<(), ::cream::Error> {
                 let create_resource = #create_ty::from_object(&resource)?;
-                self.0.replace(parts, id, create_resource).await
+                self.0.replace(parts, id, create_resource, version).await
             }
 
             async fn delete(
                 &self,
                 parts: &'async_trait ::cream::hidden::axum::http::request::Parts,
-                id: &str
+                id: &str,
+                version: ::std::option::Option<&'async_trait str>
             ) -> ::std::result::Result<(), ::cream::Error> {
-                self.0.delete(parts, id).await
+                self.0.delete(parts, id, version).await
             }
             fn default_page_size(&self) -> usize {
                 self.0.default_page_size()
             }
+            fn max_page_size(&self) -> usize {
+                self.0.max_page_size()
+            }
+            fn can_push_down(&self, filter: &::cream::filter::FilterRef) -> bool {
+                self.0.can_push_down(filter)
+            }
+            #filter_capabilities
 
             fn load_resource_type(&self) -> ::cream::ResourceType {
                 ::cream::hidden::serde_json::from_str(#resource_type_str).expect(concat!("Failed to deserialize resource type"))
@@ -556,6 +616,276 @@ fn declare_schema_struct(
     }
 }
 
+// An attribute path in a filter declaration, resolved against the schemas. Names are spelled
+// as in the schema, and the URN is omitted for core attributes.
+struct FilterPath {
+    urn: Option<String>,
+    name: String,
+    sub_attr: Option<String>,
+    attr: Attribute,
+}
+
+fn resolve_filter_path(
+    path: &str,
+    resource_type: &ResourceType,
+    schemas: &HashMap<String, (Schema, String)>,
+) -> Option<FilterPath> {
+    let schema_ids = std::iter::once(&resource_type.schema).chain(
+        resource_type
+            .schema_extensions
+            .iter()
+            .map(|ext| &ext.schema),
+    );
+    let (schema_id, rest) = match schema_ids.clone().find(|id| {
+        path.len() > id.len()
+            && path.is_char_boundary(id.len())
+            && path[..id.len()].eq_ignore_ascii_case(id)
+            && path[id.len()..].starts_with(':')
+    }) {
+        Some(id) => (id, &path[id.len() + 1..]),
+        None if path.to_ascii_lowercase().starts_with("urn:") => return None,
+        None => (&resource_type.schema, path),
+    };
+    let is_core = *schema_id == resource_type.schema;
+    let (name, sub_attr) = match rest.split_once('.') {
+        Some((name, sub_attr)) => (name, Some(sub_attr)),
+        None => (rest, None),
+    };
+    let common = Attribute::common();
+    let attr = Attribute::find(&schemas[schema_id].0.attributes, name)
+        .or_else(|| Attribute::find(&common, name).filter(|_| is_core))?;
+    let name = attr.name.clone();
+    let attr = match sub_attr {
+        Some(sub_attr) => attr.sub_attribute(sub_attr)?,
+        None => attr,
+    };
+    Some(FilterPath {
+        urn: (!is_core).then(|| schema_id.clone()),
+        sub_attr: sub_attr.map(|_| attr.name.clone()),
+        name,
+        attr: attr.clone(),
+    })
+}
+
+fn operator_supports_type(operator: &str, type_: Option<&Type>) -> bool {
+    match (operator, type_) {
+        ("pr", _) => true,
+        (_, None) => false,
+        ("eq" | "ne", _) => true,
+        ("co" | "sw" | "ew", Some(type_)) => matches!(type_, Type::String | Type::Reference),
+        ("gt" | "ge" | "lt" | "le", Some(type_)) => matches!(
+            type_,
+            Type::String | Type::Reference | Type::Integer | Type::Decimal | Type::DateTime
+        ),
+        _ => false,
+    }
+}
+
+struct FilterClauses {
+    declaration: TokenStream2,
+    capabilities: TokenStream2,
+}
+
+// Generates an enum with a variant for each supported filter clause, along with the
+// `filter_capabilities` method of the manager adapter.
+fn declare_filter_clauses(
+    name: &Ident,
+    filters: &[DeclaredFilter],
+    resource_type: &ResourceType,
+    schemas: &HashMap<String, (Schema, String)>,
+) -> syn::Result<FilterClauses> {
+    if filters.is_empty() {
+        return Ok(FilterClauses {
+            declaration: TokenStream2::new(),
+            capabilities: TokenStream2::new(),
+        });
+    }
+    let enum_name = format_ident!("{}FilterClause", name);
+    let mut has_lifetime = false;
+    let mut variants = Vec::new();
+    let mut arms = Vec::new();
+    let mut attributes = Vec::new();
+    for filter in filters {
+        let path = resolve_filter_path(&filter.path.value(), resource_type, schemas)
+            .ok_or_else(|| syn::Error::new(filter.path.span(), "Unknown attribute"))?;
+        let value_type = path.attr.comparison_attribute().map(|attr| &attr.type_);
+        let display_path = match &path.sub_attr {
+            Some(sub_attr) => format!("{}.{}", path.name, sub_attr),
+            None => path.name.clone(),
+        };
+        let prefix = sanitize_name(&display_path.replace('.', "_"), Case::Pascal);
+        let (value_ty, pattern, convert) = match value_type {
+            Some(Type::String | Type::Reference | Type::Binary) => (
+                quote! { &'a str },
+                quote! { Str(value) },
+                quote! { Some(value) },
+            ),
+            Some(Type::Boolean) => (
+                quote! { bool },
+                quote! { Bool(value) },
+                quote! { Some(value) },
+            ),
+            Some(Type::Integer) => (
+                quote! { i64 },
+                quote! { Num(value) },
+                quote! { value.to_i64() },
+            ),
+            Some(Type::Decimal) => (
+                quote! { f64 },
+                quote! { Num(value) },
+                quote! { Some(value.to_f64_lossy()) },
+            ),
+            Some(Type::DateTime) => (
+                quote! { ::cream::DateTime },
+                quote! { DateTime(value) },
+                quote! { Some(value) },
+            ),
+            Some(Type::Complex) | None => (quote! {}, quote! {}, quote! {}),
+        };
+
+        let mut operator_names = Vec::new();
+        let mut clause_arms = Vec::new();
+        for operator in &filter.operators {
+            let operator_name = operator.to_string();
+            if !operator_supports_type(&operator_name, value_type) {
+                let message = if matches!(
+                    operator_name.as_str(),
+                    "pr" | "eq" | "ne" | "co" | "sw" | "ew" | "gt" | "ge" | "lt" | "le"
+                ) {
+                    format!(
+                        "`{}` is not supported for `{}`",
+                        operator_name, display_path
+                    )
+                } else {
+                    "expected `pr` or a comparison operator".to_string()
+                };
+                return Err(syn::Error::new(operator.span(), message));
+            }
+            let variant = format_ident!("{}{}", prefix, operator_name.to_case(Case::Pascal));
+            if operator_name == "pr" {
+                // Comparisons with `null` are presence tests
+                let absent = format_ident!("{}Absent", prefix);
+                let present_doc = format!("`{} pr`, or `{} ne null`", display_path, display_path);
+                let absent_doc = format!("`{} eq null`", display_path);
+                variants.push(quote! {
+                    #[doc = #present_doc]
+                    #variant,
+                    #[doc = #absent_doc]
+                    #absent,
+                });
+                clause_arms.push(quote! {
+                    (None, _) | (Some(::cream::filter::CompareOp::NotEqual), ::cream::filter::CompValueRef::Null) => Some(#enum_name::#variant),
+                    (Some(::cream::filter::CompareOp::Equal), ::cream::filter::CompValueRef::Null) => Some(#enum_name::#absent),
+                });
+            } else {
+                has_lifetime |= matches!(
+                    value_type,
+                    Some(Type::String | Type::Reference | Type::Binary)
+                );
+                // A negated `eq` comparison is normalized to `ne`, so supporting `eq` implies
+                // supporting `ne`.
+                let mut implied = vec![operator_name.as_str()];
+                if operator_name == "eq" && !filter.operators.iter().any(|op| op == "ne") {
+                    implied.push("ne");
+                }
+                for operator_name in implied {
+                    let variant =
+                        format_ident!("{}{}", prefix, operator_name.to_case(Case::Pascal));
+                    let op = format_ident!(
+                        "{}",
+                        match operator_name {
+                            "eq" => "Equal",
+                            "ne" => "NotEqual",
+                            "co" => "Contains",
+                            "sw" => "StartsWith",
+                            "ew" => "EndsWith",
+                            "gt" => "GreaterThan",
+                            "ge" => "GreaterThanOrEqual",
+                            "lt" => "LessThan",
+                            _ => "LessThanOrEqual",
+                        }
+                    );
+                    let doc = format!("`{} {} <value>`", display_path, operator_name);
+                    variants.push(quote! {
+                        #[doc = #doc]
+                        #variant(#value_ty),
+                    });
+                    clause_arms.push(quote! {
+                        (Some(::cream::filter::CompareOp::#op), ::cream::filter::CompValueRef::#pattern) => #convert.map(#enum_name::#variant),
+                    });
+                }
+            }
+            operator_names.push(operator_name);
+        }
+
+        let urn = match &path.urn {
+            Some(urn) => quote! { Some(#urn) },
+            None => quote! { None },
+        };
+        let attr_name = &path.name;
+        let sub_attr = match &path.sub_attr {
+            Some(sub_attr) => quote! { Some(#sub_attr) },
+            None => quote! { None },
+        };
+        arms.push(quote! {
+            if path.eq_ignore_ascii_case(&::cream::AttrPathRef {
+                urn: #urn,
+                name: #attr_name,
+                sub_attr: #sub_attr,
+            }) {
+                return match (op, value) {
+                    #(#clause_arms)*
+                    _ => None,
+                };
+            }
+        });
+        let full_path = match &path.urn {
+            Some(urn) => format!("{}:{}", urn, display_path),
+            None => display_path,
+        };
+        attributes.push(quote! {
+            .attribute(#full_path, &[#(#operator_names),*])
+        });
+    }
+
+    let (ty_generics, impl_generics) = if has_lifetime {
+        (quote! { <'a> }, quote! { <'_> })
+    } else {
+        (quote! {}, quote! {})
+    };
+    let enum_doc = format!("Filter clauses supported for `{}` resources.", name);
+    let declaration = quote! {
+        #[doc = #enum_doc]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum #enum_name #ty_generics {
+            #(#variants)*
+        }
+
+        impl #enum_name #impl_generics {
+            /// Converts a `pr` or comparison clause of a filter into the corresponding variant.
+            /// Returns `None` for other kinds of filter, and for clauses which are not supported.
+            pub fn from_filter<'a>(filter: &::cream::filter::FilterRef<'a>) -> Option<#enum_name #ty_generics> {
+                let (path, op, value) = match *filter {
+                    ::cream::filter::FilterRef::Present(path) => (path, None, ::cream::filter::CompValueRef::Null),
+                    ::cream::filter::FilterRef::Compare(path, op, value) => (path, Some(op), value),
+                    _ => return None,
+                };
+                #(#arms)*
+                None
+            }
+        }
+    };
+    let capabilities = quote! {
+        fn filter_capabilities(&self) -> ::std::option::Option<::cream::filter::FilterCapabilities> {
+            Some(::cream::filter::FilterCapabilities::new() #(#attributes)*)
+        }
+    };
+    Ok(FilterClauses {
+        declaration,
+        capabilities,
+    })
+}
+
 /// Generate support code for a resource type.
 ///
 /// Syntax:
@@ -563,14 +893,27 @@ fn declare_schema_struct(
 /// declare_resource!("<path/to/resource_type.json>" as <ResourceName> [
 ///     "<path/to/core_schema.json>",
 ///     ...<optional extension schemas>,
-/// ]);
+/// ] filter {
+///     "<attribute path>": <operator>, ...;
+///     ...
+/// });
 /// ```
+///
+/// The optional `filter` block declares which filters the manager supports, such as
+/// `"userName": eq, sw;` or `"emails.value": pr, eq;`. Cream rejects any other filter before
+/// calling `list`, and publishes the supported filters on the resource type. An enum named
+/// `<ResourceName>FilterClause` is generated with a variant for each supported operator, such
+/// as `UserNameSw(&str)`, whose `from_filter` method converts the clauses of a filter so that
+/// they can be matched exhaustively. Since `not (userName eq "x")` is normalized to
+/// `userName ne "x"`, declaring `eq` also generates an `ne` variant. Without a `filter` block,
+/// any filter may be passed to the manager.
 #[proc_macro]
 pub fn declare_resource(input: TokenStream) -> TokenStream {
     let DeclareResource {
         path,
         name,
         schemas: ref_schemas,
+        filters,
         ..
     } = parse_macro_input!(input as DeclareResource);
 
@@ -587,6 +930,11 @@ pub fn declare_resource(input: TokenStream) -> TokenStream {
 
     let manager = format_ident!("{}Manager", name);
 
+    let filter_clauses = match declare_filter_clauses(&name, &filters, &resource_type, &schemas) {
+        Ok(filter_clauses) => filter_clauses,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     let SchemaStruct {
         ty,
         create_ty,
@@ -605,12 +953,14 @@ pub fn declare_resource(input: TokenStream) -> TokenStream {
     let mut result = TokenStream2::new();
     result.extend(referenced_files_hack);
     result.extend(declaration);
+    result.extend(filter_clauses.declaration);
     result.extend(declare_manager_trait(
         manager,
         ty,
         create_ty,
         &serde_json::to_string(&resource_type).unwrap(),
         &schemas,
+        filter_clauses.capabilities,
     ));
     result.into()
 }
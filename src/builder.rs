@@ -4,8 +4,12 @@ use cream_core::Schema;
 
 use crate::{
     config::ServiceProviderConfig,
+    filter::FilterLimits,
     manager::GenericResourceManager,
+    mapping::{AttributeMapping, MappedManager},
+    schema::SchemaRegistry,
     state::{Cream, InnerState, ResourceTypeState},
+    subject::SubjectResolver,
 };
 
 /// Builder for constructing a `Cream` instance.
@@ -14,6 +18,9 @@ pub struct CreamBuilder {
     config: ServiceProviderConfig,
     schemas: BTreeMap<String, Schema>,
     resource_types: BTreeMap<String, ResourceTypeState>,
+    subject_resolver: Option<Box<dyn SubjectResolver>>,
+    filter_limits: FilterLimits,
+    max_residual_scan: usize,
 }
 
 impl CreamBuilder {
@@ -24,6 +31,9 @@ impl CreamBuilder {
             config,
             schemas: BTreeMap::new(),
             resource_types: BTreeMap::new(),
+            subject_resolver: None,
+            filter_limits: FilterLimits::default(),
+            max_residual_scan: 10_000,
         }
     }
     fn load_schema(&mut self, id: &str, manager: &impl GenericResourceManager) {
@@ -40,16 +50,52 @@ impl CreamBuilder {
             self.load_schema(&ext.schema, &manager);
         }
 
+        let schemas = SchemaRegistry::new(&resource_type, |id| &self.schemas[id]);
+        let filter_capabilities = manager.filter_capabilities();
         self.resource_types.insert(
             resource_type.name.clone(),
             ResourceTypeState {
                 resource_type,
+                schemas,
                 manager: Box::new(manager),
+                filter_capabilities,
             },
         );
         self
     }
 
+    /// Add a new resource type to be handled by cream, rewriting the attribute paths passed to
+    /// its manager into backend fields using `mapping`.
+    pub fn resource_type_with_mapping(
+        self,
+        manager: impl GenericResourceManager,
+        mapping: AttributeMapping,
+    ) -> Self {
+        self.resource_type(MappedManager::new(manager, mapping))
+    }
+
+    /// Enable the `/Me` endpoint, using the given resolver to determine which resource
+    /// each request is authenticated as.
+    pub fn subject_resolver(mut self, resolver: impl SubjectResolver) -> Self {
+        self.subject_resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Set the limits on the complexity of filters sent by clients. Defaults to
+    /// `FilterLimits::default()`.
+    pub fn filter_limits(mut self, limits: FilterLimits) -> Self {
+        self.filter_limits = limits;
+        self
+    }
+
+    /// Set the maximum number of resources which may be fetched from a manager to evaluate the
+    /// part of a filter which it cannot evaluate itself. Requests which would need to fetch more
+    /// are rejected with a `tooMany` error. Defaults to 10,000.
+    pub fn max_residual_scan(mut self, max: usize) -> Self {
+        self.max_residual_scan = max;
+        self
+    }
+
     /// Build the `Cream` instance.
     pub fn build(self) -> Cream {
         Cream(Arc::new(InnerState {
@@ -57,6 +103,9 @@ impl CreamBuilder {
             config: self.config,
             schemas: self.schemas,
             resource_types: self.resource_types,
+            subject_resolver: self.subject_resolver,
+            filter_limits: self.filter_limits,
+            max_residual_scan: self.max_residual_scan,
         }))
     }
 }
@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use axum::http::StatusCode;
+use cream_core::{declare_schema, Reference};
+use ijson::IValue;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::error::Error;
+
+declare_schema!(BulkResponseSchema = "urn:ietf:params:scim:api:messages:2.0:BulkResponse");
+
+/// A request to perform several operations at once.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkRequest {
+    /// The number of errors after which the remaining operations are skipped.
+    #[serde(default)]
+    pub fail_on_errors: Option<usize>,
+    /// The operations to perform.
+    #[serde(rename = "Operations")]
+    pub operations: Vec<BulkOperation>,
+}
+
+/// A single operation within a bulk request.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkOperation {
+    /// The HTTP method of the operation.
+    pub method: BulkMethod,
+    /// A transient identifier for a resource created by a POST operation, which other
+    /// operations may refer to as `bulkId:<id>`. Required for POST operations.
+    #[serde(default)]
+    pub bulk_id: Option<String>,
+    /// The version of the resource which the operation expects, like an `If-Match` header.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The path of the resource or endpoint, such as `/Users/123`.
+    pub path: String,
+    /// The body of the operation, if any.
+    #[serde(default)]
+    pub data: Option<IValue>,
+}
+
+/// The HTTP method of a bulk operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkMethod {
+    /// Create a resource.
+    Post,
+    /// Replace a resource.
+    Put,
+    /// Modify a resource.
+    Patch,
+    /// Delete a resource.
+    Delete,
+}
+
+impl BulkMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+        }
+    }
+}
+
+impl FromStr for BulkMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("post") {
+            Ok(Self::Post)
+        } else if s.eq_ignore_ascii_case("put") {
+            Ok(Self::Put)
+        } else if s.eq_ignore_ascii_case("patch") {
+            Ok(Self::Patch)
+        } else if s.eq_ignore_ascii_case("delete") {
+            Ok(Self::Delete)
+        } else {
+            Err(Error::expected("Bulk Method"))
+        }
+    }
+}
+
+impl Serialize for BulkMethod {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+serde_plain::derive_deserialize_from_fromstr!(BulkMethod, "Bulk Method");
+
+/// The results of a bulk request.
+#[derive(Serialize, Debug)]
+pub struct BulkResponse {
+    /// ["urn:ietf:params:scim:api:messages:2.0:BulkResponse"]
+    pub schemas: [BulkResponseSchema; 1],
+    /// The result of each operation which was performed, in the order they were performed.
+    #[serde(rename = "Operations")]
+    pub operations: Vec<BulkOperationResponse>,
+}
+
+/// The result of a single operation within a bulk request.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkOperationResponse {
+    /// The HTTP method of the operation.
+    pub method: BulkMethod,
+    /// The `bulkId` of the operation, if it had one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bulk_id: Option<String>,
+    /// The version of the resource after the operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The location of the resource which was operated on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Reference>,
+    /// The HTTP status of the operation.
+    #[serde(serialize_with = "Error::serialize_status")]
+    pub status: StatusCode,
+    /// The error, if the operation failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<Error>,
+}
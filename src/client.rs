@@ -0,0 +1,441 @@
+use std::fmt::Display;
+
+use axum::{
+    body::{Body, HttpBody},
+    http::{header, HeaderValue, Method, Request, Response, StatusCode},
+};
+use bytes::Bytes;
+use cream_core::{declare_schema, ResourceType, Schema};
+use futures_util::{stream, Stream, TryStreamExt};
+use ijson::IValue;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tower::{BoxError, Service, ServiceExt};
+
+use crate::{
+    config::ServiceProviderConfig,
+    error::{Error, ErrorType},
+    filter::{AttrPath, Filter},
+    list::ListResponse,
+    manager::SortOrder,
+};
+
+declare_schema!(PatchOpSchema = "urn:ietf:params:scim:api:messages:2.0:PatchOp");
+
+const SCIM_JSON: &str = "application/scim+json";
+
+// Responses larger than this are rejected, to avoid unbounded memory use.
+const MAX_RESPONSE_SIZE: usize = 64 * 1024 * 1024;
+
+/// A client for a SCIM service provider.
+///
+/// Requests are sent via any [`tower::Service`] which accepts HTTP requests, such as an HTTP
+/// client, or the router of a local [`Cream`](crate::Cream) instance. Authentication can be
+/// added by wrapping the service in a layer which sets the `Authorization` header.
+///
+/// Resources can be sent and received as any serializable type, including `IObject` and the
+/// types generated by `declare_resource!`. Errors returned by the service provider are
+/// converted into an [`Error`] with the same status and SCIM error type. Failures to reach the
+/// service provider, or to understand its response, are reported as `502 Bad Gateway`.
+#[derive(Debug, Clone)]
+pub struct Client<S> {
+    service: S,
+    base_url: String,
+}
+
+/// The parameters of a list request.
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery {
+    /// Only return resources matching this filter.
+    pub filter: Option<Filter>,
+    /// Attribute to sort by.
+    pub sort_by: Option<AttrPath>,
+    /// Sort order.
+    pub sort_order: SortOrder,
+    /// The 1-based index of the first resource to return.
+    pub start_index: Option<usize>,
+    /// The number of resources to return per page.
+    pub count: Option<usize>,
+    /// A cursor identifying the page to return. An empty string requests the first page.
+    pub cursor: Option<String>,
+    /// Additional attributes to return.
+    pub attributes: Vec<AttrPath>,
+    /// Attributes which should not be returned.
+    pub excluded_attributes: Vec<AttrPath>,
+}
+
+impl ListQuery {
+    /// Construct a query which returns all resources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only return resources matching a filter.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Sort resources by an attribute.
+    pub fn sort_by(mut self, sort_by: AttrPath, sort_order: SortOrder) -> Self {
+        self.sort_by = Some(sort_by);
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Set the number of resources to return per page.
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Request additional attributes.
+    pub fn attributes(mut self, attributes: impl IntoIterator<Item = AttrPath>) -> Self {
+        self.attributes.extend(attributes);
+        self
+    }
+
+    /// Request that attributes are not returned.
+    pub fn excluded_attributes(mut self, attributes: impl IntoIterator<Item = AttrPath>) -> Self {
+        self.excluded_attributes.extend(attributes);
+        self
+    }
+
+    fn to_query_string(&self) -> String {
+        fn join(paths: &[AttrPath]) -> String {
+            paths
+                .iter()
+                .map(AttrPath::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+
+        let mut params = Vec::new();
+        if let Some(filter) = &self.filter {
+            params.push(("filter", filter.to_string()));
+        }
+        if let Some(sort_by) = &self.sort_by {
+            params.push(("sortBy", sort_by.to_string()));
+            let sort_order = match self.sort_order {
+                SortOrder::Ascending => "ascending",
+                SortOrder::Descending => "descending",
+            };
+            params.push(("sortOrder", sort_order.to_string()));
+        }
+        if let Some(start_index) = self.start_index {
+            params.push(("startIndex", start_index.to_string()));
+        }
+        if let Some(count) = self.count {
+            params.push(("count", count.to_string()));
+        }
+        if let Some(cursor) = &self.cursor {
+            params.push(("cursor", cursor.clone()));
+        }
+        if !self.attributes.is_empty() {
+            params.push(("attributes", join(&self.attributes)));
+        }
+        if !self.excluded_attributes.is_empty() {
+            params.push(("excludedAttributes", join(&self.excluded_attributes)));
+        }
+        serde_urlencoded::to_string(params).expect("Infallible serialization")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PatchOperation {
+    op: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "IValue::is_null")]
+    value: IValue,
+}
+
+/// A PATCH request, consisting of a list of operations which are applied in order.
+///
+/// Paths may be given as strings, or as an [`AttrPath`] or
+/// [`ValuePath`](crate::filter::ValuePath).
+///
+/// ```
+/// use cream::{filter::attr, PatchOp};
+///
+/// let patch = PatchOp::new()
+///     .replace("emails[type eq \"work\"].value", "bjensen@example.com")
+///     .add(attr("title"), "Tour Guide")
+///     .remove("nickName");
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PatchOp {
+    schemas: [PatchOpSchema; 1],
+    #[serde(rename = "Operations")]
+    operations: Vec<PatchOperation>,
+}
+
+impl PatchOp {
+    /// Construct an empty PATCH request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, op: &'static str, path: Option<String>, value: IValue) -> Self {
+        self.operations.push(PatchOperation { op, path, value });
+        self
+    }
+
+    /// Add a value to an attribute. Values are appended to multi-valued attributes.
+    pub fn add(self, path: impl Display, value: impl Into<IValue>) -> Self {
+        self.push("add", Some(path.to_string()), value.into())
+    }
+
+    /// Add each attribute of an object to the resource.
+    pub fn add_attributes(self, value: impl Into<IValue>) -> Self {
+        self.push("add", None, value.into())
+    }
+
+    /// Replace the value of an attribute.
+    pub fn replace(self, path: impl Display, value: impl Into<IValue>) -> Self {
+        self.push("replace", Some(path.to_string()), value.into())
+    }
+
+    /// Replace each attribute of an object within the resource.
+    pub fn replace_attributes(self, value: impl Into<IValue>) -> Self {
+        self.push("replace", None, value.into())
+    }
+
+    /// Remove an attribute, or the values matching a value filter.
+    pub fn remove(self, path: impl Display) -> Self {
+        self.push("remove", Some(path.to_string()), IValue::NULL)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorBody {
+    scim_type: Option<String>,
+    detail: Option<String>,
+}
+
+fn bad_gateway(detail: impl Display) -> Error {
+    Error::new(StatusCode::BAD_GATEWAY, None, detail.to_string())
+}
+
+fn parse_json<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(body).map_err(|e| bad_gateway(format!("Invalid response: {}", e)))
+}
+
+fn parse_error(status: StatusCode, body: &[u8]) -> Error {
+    let body: Option<ErrorBody> = serde_json::from_slice(body).ok();
+    let scim_type = body
+        .as_ref()
+        .and_then(|body| body.scim_type.as_deref())
+        .and_then(|scim_type| scim_type.parse::<ErrorType>().ok());
+    let detail = body
+        .and_then(|body| body.detail)
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or_default().to_string());
+    Error::new(status, scim_type, detail)
+}
+
+// The position of the next page when paginating through a list.
+enum Position {
+    Index(usize),
+    Cursor(String),
+}
+
+impl<S, B> Client<S>
+where
+    S: Service<Request<Body>, Response = Response<B>> + Clone,
+    S::Error: Into<BoxError>,
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    /// Construct a client for the service provider at `base_url`, such as
+    /// `https://example.com/scim/v2`.
+    pub fn new(service: S, base_url: impl Into<String>) -> Self {
+        Self {
+            service,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<String>,
+        body: Option<Vec<u8>>,
+    ) -> Result<(StatusCode, Bytes), Error> {
+        let mut uri = format!("{}{}", self.base_url, path);
+        if let Some(query) = query.filter(|query| !query.is_empty()) {
+            uri.push('?');
+            uri.push_str(&query);
+        }
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::ACCEPT, HeaderValue::from_static(SCIM_JSON));
+        if body.is_some() {
+            builder = builder.header(header::CONTENT_TYPE, HeaderValue::from_static(SCIM_JSON));
+        }
+        let request = builder
+            .body(body.map_or_else(Body::empty, Body::from))
+            .map_err(bad_gateway)?;
+        let response = self
+            .service
+            .clone()
+            .oneshot(request)
+            .await
+            .map_err(|e| bad_gateway(e.into()))?;
+        let status = response.status();
+        let body = axum::body::to_bytes(Body::new(response.into_body()), MAX_RESPONSE_SIZE)
+            .await
+            .map_err(bad_gateway)?;
+        if status.is_success() {
+            Ok((status, body))
+        } else {
+            Err(parse_error(status, &body))
+        }
+    }
+
+    async fn request<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<String>,
+        body: Option<&(impl Serialize + ?Sized)>,
+    ) -> Result<R, Error> {
+        let body = body
+            .map(serde_json::to_vec)
+            .transpose()
+            .map_err(|e| Error::new(StatusCode::BAD_REQUEST, None, e.to_string()))?;
+        let (_, body) = self.send(method, path, query, body).await?;
+        parse_json(&body)
+    }
+
+    /// Fetch the service provider configuration.
+    pub async fn service_provider_config(&self) -> Result<ServiceProviderConfig, Error> {
+        self.request(Method::GET, "/ServiceProviderConfig", None, None::<&()>)
+            .await
+    }
+
+    /// Fetch the schemas supported by the service provider.
+    pub async fn schemas(&self) -> Result<Vec<Schema>, Error> {
+        let response: ListResponse<Schema> = self
+            .request(Method::GET, "/Schemas", None, None::<&()>)
+            .await?;
+        Ok(response.resources)
+    }
+
+    /// Fetch the resource types supported by the service provider.
+    pub async fn resource_types(&self) -> Result<Vec<ResourceType>, Error> {
+        let response: ListResponse<ResourceType> = self
+            .request(Method::GET, "/ResourceTypes", None, None::<&()>)
+            .await?;
+        Ok(response.resources)
+    }
+
+    /// Fetch a resource. `endpoint` is the endpoint of the resource type, such as `/Users`.
+    pub async fn get<R: DeserializeOwned>(&self, endpoint: &str, id: &str) -> Result<R, Error> {
+        let path = format!("{}/{}", endpoint, id);
+        self.request(Method::GET, &path, None, None::<&()>).await
+    }
+
+    /// Create a resource, returning the resource as stored by the service provider.
+    pub async fn create<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        resource: &(impl Serialize + ?Sized),
+    ) -> Result<R, Error> {
+        self.request(Method::POST, endpoint, None, Some(resource))
+            .await
+    }
+
+    /// Replace a resource, returning the resource as stored by the service provider.
+    pub async fn replace<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        id: &str,
+        resource: &(impl Serialize + ?Sized),
+    ) -> Result<R, Error> {
+        let path = format!("{}/{}", endpoint, id);
+        self.request(Method::PUT, &path, None, Some(resource)).await
+    }
+
+    /// Update a resource, returning the updated resource. If the service provider does not
+    /// return the resource, it is fetched with a separate request.
+    pub async fn patch<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        id: &str,
+        patch: &PatchOp,
+    ) -> Result<R, Error> {
+        let path = format!("{}/{}", endpoint, id);
+        let body = serde_json::to_vec(patch).expect("Infallible serialization");
+        let (status, body) = self.send(Method::PATCH, &path, None, Some(body)).await?;
+        if status == StatusCode::NO_CONTENT {
+            self.get(endpoint, id).await
+        } else {
+            parse_json(&body)
+        }
+    }
+
+    /// Delete a resource.
+    pub async fn delete(&self, endpoint: &str, id: &str) -> Result<(), Error> {
+        let path = format!("{}/{}", endpoint, id);
+        self.send(Method::DELETE, &path, None, None).await?;
+        Ok(())
+    }
+
+    /// Fetch a single page of resources.
+    pub async fn list_page<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &ListQuery,
+    ) -> Result<ListResponse<R>, Error> {
+        self.request(
+            Method::GET,
+            endpoint,
+            Some(query.to_query_string()),
+            None::<&()>,
+        )
+        .await
+    }
+
+    /// List all resources matching a query, fetching further pages as the stream is consumed.
+    ///
+    /// The service provider's default pagination method is used for the first page, and
+    /// subsequent pages are requested by cursor or by index accordingly.
+    pub fn list<'a, R: DeserializeOwned + 'a>(
+        &'a self,
+        endpoint: &'a str,
+        query: ListQuery,
+    ) -> impl Stream<Item = Result<R, Error>> + 'a {
+        stream::try_unfold(Some(query), move |query| async move {
+            let Some(mut query) = query else {
+                return Ok(None);
+            };
+            let page: ListResponse<R> = self.list_page(endpoint, &query).await?;
+            let next = match page.next_cursor {
+                _ if page.resources.is_empty() => None,
+                Some(cursor) => Some(Position::Cursor(cursor)),
+                // The last page of a cursor-based list has no next cursor.
+                None if query.cursor.is_some() => None,
+                None => {
+                    let start_index = page.start_index.or(query.start_index).unwrap_or(1);
+                    let next_index = start_index + page.resources.len();
+                    (next_index <= page.total_results).then_some(Position::Index(next_index))
+                }
+            };
+            let next = next.map(|position| {
+                match position {
+                    Position::Index(start_index) => query.start_index = Some(start_index),
+                    Position::Cursor(cursor) => query.cursor = Some(cursor),
+                }
+                query
+            });
+            Ok::<_, Error>(Some((page.resources, next)))
+        })
+        .map_ok(|resources| stream::iter(resources.into_iter().map(Ok)))
+        .try_flatten()
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests;
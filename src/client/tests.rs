@@ -0,0 +1,173 @@
+use axum::{http::StatusCode, Router};
+use cream_core::load_static_json;
+use futures_util::TryStreamExt;
+use ijson::{ijson, IObject, IValue};
+
+use crate::{builder::CreamBuilder, error::ErrorType, filter::attr, memory::MemoryStore};
+
+use super::{Client, ListQuery, PatchOp};
+
+fn client() -> Client<Router> {
+    let users = MemoryStore::new(
+        load_static_json!("../../examples/user_type.json"),
+        [load_static_json!("../../examples/user_schema.json")],
+    );
+    let groups = MemoryStore::new(
+        load_static_json!("../../examples/group_type.json"),
+        [load_static_json!("../../examples/group_schema.json")],
+    );
+    let cream = CreamBuilder::new(
+        "http://localhost",
+        load_static_json!("../../examples/smoke_config.json"),
+    )
+    .resource_type(users)
+    .resource_type(groups)
+    .build();
+    Client::new(cream.router(), "http://localhost")
+}
+
+fn user(user_name: &str, display_name: &str) -> IValue {
+    ijson!({
+        "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+        "userName": user_name,
+        "displayName": display_name,
+    })
+}
+
+fn id(resource: &IObject) -> &str {
+    resource["id"].as_string().unwrap()
+}
+
+#[tokio::test]
+async fn test_discovery() {
+    let client = client();
+
+    let config = client.service_provider_config().await.unwrap();
+    assert!(config.patch.supported);
+
+    let schemas = client.schemas().await.unwrap();
+    assert!(schemas
+        .iter()
+        .any(|schema| schema.id == "urn:ietf:params:scim:schemas:core:2.0:User"));
+
+    let resource_types = client.resource_types().await.unwrap();
+    let mut endpoints: Vec<_> = resource_types
+        .iter()
+        .map(|resource_type| resource_type.endpoint.as_str())
+        .collect();
+    endpoints.sort();
+    assert_eq!(endpoints, ["/Groups", "/Users"]);
+}
+
+#[tokio::test]
+async fn test_crud() {
+    let client = client();
+
+    let created: IObject = client
+        .create("/Users", &user("bjensen", "Tour Guide"))
+        .await
+        .unwrap();
+    let id = id(&created);
+    assert_eq!(created["userName"], ijson!("bjensen"));
+
+    let fetched: IObject = client.get("/Users", id).await.unwrap();
+    assert_eq!(fetched["displayName"], ijson!("Tour Guide"));
+
+    let replaced: IObject = client
+        .replace("/Users", id, &user("bjensen", "Manager"))
+        .await
+        .unwrap();
+    assert_eq!(replaced["displayName"], ijson!("Manager"));
+
+    let patch = PatchOp::new()
+        .add("name.givenName", "Barbara")
+        .add(
+            "emails",
+            ijson!([{ "value": "bjensen@example.com", "type": "work" }]),
+        )
+        .replace("emails[type eq \"work\"].value", "babs@example.com")
+        .remove("displayName");
+    let patched: IObject = client.patch("/Users", id, &patch).await.unwrap();
+    assert_eq!(patched["name"], ijson!({ "givenName": "Barbara" }));
+    assert_eq!(
+        patched["emails"],
+        ijson!([{ "value": "babs@example.com", "type": "work" }])
+    );
+    assert!(patched.get("displayName").is_none());
+
+    client.delete("/Users", id).await.unwrap();
+    let err = client.get::<IObject>("/Users", id).await.unwrap_err();
+    assert_eq!(err.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_errors() {
+    let client = client();
+
+    client
+        .create::<IObject>("/Users", &user("bjensen", "Tour Guide"))
+        .await
+        .unwrap();
+    let err = client
+        .create::<IObject>("/Users", &user("bjensen", "Manager"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::CONFLICT);
+    assert!(matches!(err.scim_type(), Some(ErrorType::Uniqueness)));
+
+    let query = ListQuery::new().filter(attr("userName").eq("bjensen"));
+    let err = client
+        .list_page::<IObject>("/Unknown", &query)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list() {
+    let client = client();
+
+    for (user_name, display_name) in [
+        ("alice", "Engineer"),
+        ("bob", "Manager"),
+        ("carol", "Engineer"),
+        ("dave", "Engineer"),
+    ] {
+        client
+            .create::<IObject>("/Users", &user(user_name, display_name))
+            .await
+            .unwrap();
+    }
+
+    let query = ListQuery::new()
+        .filter(attr("displayName").eq("engineer"))
+        .sort_by(attr("userName"), Default::default())
+        .count(1);
+    let page = client.list_page::<IObject>("/Users", &query).await.unwrap();
+    assert_eq!(page.total_results, 3);
+    assert_eq!(page.resources.len(), 1);
+
+    let users: Vec<IObject> = client.list("/Users", query).try_collect().await.unwrap();
+    let user_names: Vec<_> = users
+        .iter()
+        .map(|user| user["userName"].as_string().unwrap().as_str())
+        .collect();
+    assert_eq!(user_names, ["alice", "carol", "dave"]);
+}
+
+#[test]
+fn test_patch_op() {
+    let patch = PatchOp::new()
+        .add_attributes(ijson!({ "displayName": "Manager" }))
+        .remove(attr("emails").has(attr("type").eq("work")).to_string());
+    assert_eq!(
+        serde_json::to_value(&patch).unwrap(),
+        serde_json::json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [
+                { "op": "add", "value": { "displayName": "Manager" } },
+                { "op": "remove", "path": "emails[type eq \"work\"]" }
+            ]
+        })
+    );
+}
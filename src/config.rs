@@ -6,74 +6,142 @@ declare_schema!(
 );
 declare_resource_type!(ServiceProviderConfigResourceType = "ServiceProviderConfig");
 
+/// The service provider configuration, describing which optional SCIM features are supported.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfig {
+    /// ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"]
     #[serde(skip_deserializing)]
     pub schemas: [ServiceProviderConfigSchema; 1],
+    /// An HTTP-addressable URL pointing to the service provider's human-consumable help documentation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub documentation_uri: Option<String>,
+    /// PATCH configuration options.
     pub patch: ServiceProviderConfigPatch,
+    /// Bulk configuration options.
     pub bulk: ServiceProviderConfigBulk,
+    /// Filter configuration options.
     pub filter: ServiceProviderConfigFilter,
+    /// Configuration options related to changing a password.
     pub change_password: ServiceProviderConfigChangePassword,
+    /// Sort configuration options.
     pub sort: ServiceProviderConfigSort,
+    /// ETag configuration options.
     pub etag: ServiceProviderConfigEtag,
+    /// Pagination configuration options. Index-based pagination is assumed if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<ServiceProviderConfigPagination>,
+    /// The authentication schemes supported by the service provider.
     pub authentication_schemes: Vec<ServiceProviderConfigAuthenticationScheme>,
+    /// Metadata about the configuration.
     #[serde(skip_deserializing)]
     pub meta: Meta<ServiceProviderConfigResourceType>,
 }
 
+/// PATCH configuration options.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigPatch {
+    /// Whether PATCH is supported.
     pub supported: bool,
 }
 
+/// Bulk configuration options.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigBulk {
+    /// Whether bulk operations are supported.
     pub supported: bool,
+    /// The maximum number of operations in a bulk request.
     pub max_operations: i32,
+    /// The maximum payload size of a bulk request, in bytes.
     pub max_payload_size: i32,
 }
 
+/// Filter configuration options.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigFilter {
+    /// Whether filtering is supported.
     pub supported: bool,
+    /// The maximum number of resources returned in a response.
     pub max_results: i32,
 }
 
+/// Configuration options related to changing a password.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigChangePassword {
+    /// Whether changing a password is supported.
     pub supported: bool,
 }
 
+/// Sort configuration options.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigSort {
+    /// Whether sorting is supported.
     pub supported: bool,
 }
 
+/// ETag configuration options.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigEtag {
+    /// Whether ETags are supported.
     pub supported: bool,
 }
 
+/// Pagination configuration options (RFC 9865).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceProviderConfigPagination {
+    /// Whether cursor-based pagination is supported.
+    pub cursor: bool,
+    /// Whether index-based pagination is supported.
+    pub index: bool,
+    /// The pagination method used when the client does not specify one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_pagination_method: Option<PaginationMethod>,
+    /// The number of results returned per page when the client does not specify a count.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_page_size: Option<i32>,
+    /// The maximum number of results returned per page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_page_size: Option<i32>,
+    /// The minimum number of seconds for which a cursor remains valid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_timeout: Option<i32>,
+}
+
+/// A method of paginating list responses.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PaginationMethod {
+    /// Pagination using an opaque cursor.
+    Cursor,
+    /// Pagination using a start index.
+    Index,
+}
+
+/// An authentication scheme supported by the service provider.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceProviderConfigAuthenticationScheme {
+    /// The common authentication scheme name, e.g. HTTP Basic.
     pub name: String,
+    /// A description of the authentication scheme.
     pub description: String,
+    /// An HTTP-addressable URL pointing to the authentication scheme's specification.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub spec_uri: Option<String>,
+    /// An HTTP-addressable URL pointing to the authentication scheme's usage documentation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub documentation_uri: Option<String>,
+    /// The type of the authentication scheme, e.g. `oauthbearertoken` or `httpbasic`.
     #[serde(rename = "type")]
     pub type_: String,
+    /// Whether this is the primary authentication scheme.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub primary: Option<bool>,
 }
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse};
 use cream_core::declare_schema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::json::Json;
 
@@ -10,6 +10,7 @@ declare_schema!(ErrorSchema = "urn:ietf:params:scim:api:messages:2.0:Error");
 
 /// SCIM error response.
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Error {
     /// ["urn:ietf:params:scim:api:messages:2.0:Error"]
     pub(crate) schemas: [ErrorSchema; 1],
@@ -36,7 +37,7 @@ impl Display for Error {
 impl std::error::Error for Error {}
 
 impl Error {
-    fn serialize_status<S>(status: &StatusCode, serializer: S) -> Result<S::Ok, S::Error>
+    pub(crate) fn serialize_status<S>(status: &StatusCode, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
@@ -53,11 +54,36 @@ impl Error {
         }
     }
 
+    /// The HTTP status code of the error.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The SCIM error type, if any.
+    pub fn scim_type(&self) -> Option<&ErrorType> {
+        self.scim_type.as_ref()
+    }
+
+    /// A human-readable description of the error.
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+
     /// Create a new 404 error response.
     pub fn not_found() -> Self {
         Self::new(StatusCode::NOT_FOUND, None, "Not Found".to_string())
     }
 
+    /// Create an error response indicating that the resource has changed since the version
+    /// specified by the client.
+    pub fn precondition_failed() -> Self {
+        Self::new(
+            StatusCode::PRECONDITION_FAILED,
+            None,
+            "Resource version does not match".to_string(),
+        )
+    }
+
     /// Create an error response indicating that a filter is invalid or not supported.
     pub fn invalid_filter() -> Self {
         Self::new(
@@ -76,6 +102,15 @@ impl Error {
         )
     }
 
+    /// Create an error response indicating that a PATCH path did not match any values.
+    pub fn no_target() -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::NoTarget),
+            "No Target".to_string(),
+        )
+    }
+
     /// Create an error response indicating that a different type of value was expected.
     pub fn expected(expected: &str) -> Self {
         Self::new(
@@ -85,6 +120,42 @@ impl Error {
         )
     }
 
+    /// Create an error response indicating that a pagination cursor is invalid or not supported.
+    pub fn invalid_cursor() -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::InvalidCursor),
+            "Invalid Cursor".to_string(),
+        )
+    }
+
+    /// Create an error response indicating that a pagination cursor has expired.
+    pub fn expired_cursor() -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::ExpiredCursor),
+            "Expired Cursor".to_string(),
+        )
+    }
+
+    /// Create an error response indicating that the value at the given JSON path is invalid.
+    pub fn invalid_value(path: &str, expected: &str) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::InvalidValue),
+            format!("Invalid value for `{}`: expected {}", path, expected),
+        )
+    }
+
+    /// Create an error response indicating that an attribute cannot be modified.
+    pub fn mutability(attribute: &str) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::Mutability),
+            format!("Attribute `{}` cannot be modified", attribute),
+        )
+    }
+
     /// Create an error response indicating that a different type of value was expected.
     pub fn uniqueness(attribute: &str) -> Self {
         Self::new(
@@ -97,7 +168,7 @@ impl Error {
 
 /// SCIM error type.
 #[allow(unused)]
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum ErrorType {
     /// The filter is invalid or not supported.
@@ -120,9 +191,14 @@ pub enum ErrorType {
     InvalidVers,
     /// The attribute is sensitive and cannot be read.
     Sensitive,
+    /// The pagination cursor is invalid or not supported.
+    InvalidCursor,
+    /// The pagination cursor has expired.
+    ExpiredCursor,
 }
 
 serde_plain::derive_display_from_serialize!(ErrorType);
+serde_plain::derive_fromstr_from_deserialize!(ErrorType);
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
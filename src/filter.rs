@@ -1,27 +1,52 @@
-use std::str::FromStr;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
 
-use axum::http::StatusCode;
 use bumpalo::Bump;
+use cream_core::DateTime;
 use ijson::INumber;
-use nom::Finish;
+use serde::{Deserialize, Serialize};
 
-use crate::{
-    error::{Error, ErrorType},
-    META_RESOURCE_TYPE,
-};
+use crate::META_RESOURCE_TYPE;
 
+mod builder;
+mod capabilities;
+mod coerce;
+mod display;
+mod error;
+mod eval;
+mod limits;
+mod normalize;
 mod parse;
+mod split;
+
+pub use builder::attr;
+pub use capabilities::{FilterCapabilities, FILTER_CAPABILITIES_SCHEMA};
+pub use error::{Expected, ParseError, ParseKind};
+pub use limits::FilterLimits;
+pub use split::SplitFilter;
 
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum Filter {
+/// An owned filter expression. Use [`Filter::as_ref`] to obtain a [`FilterRef`], which is
+/// more convenient for pattern matching.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Filter {
+    /// Whether an attribute is present.
     Present(AttrPath),
+    /// A comparison between an attribute and a value.
     Compare(AttrPath, CompareOp, CompValue),
+    /// Whether a value in a multi-valued attribute matches a filter. Paths within the filter
+    /// are sub-attributes of the multi-valued attribute.
     Has(AttrPath, Box<Self>),
+    /// A logical AND operation between filters.
     And(Vec<Self>),
+    /// A logical OR operation between filters.
     Or(Vec<Self>),
+    /// A logical NOT operation on a filter.
     Not(Box<Self>),
 }
 
@@ -78,8 +103,22 @@ pub mod prelude {
     pub use super::{CompValueRef::*, CompareOp::*, FilterRef::*, ValuePathRef::*};
 }
 
+// Rewrites the paths within a value filter to be sub-attributes of the parent attribute.
+pub(crate) struct AttrPathPrefixer<'a> {
+    pub parent: &'a AttrPath,
+}
+
+impl Visitor for AttrPathPrefixer<'_> {
+    fn visit_attr_path(&mut self, attr_path: &mut AttrPath) {
+        attr_path.sub_attr = Some(attr_path.name.clone());
+        attr_path.name = self.parent.name.clone();
+        attr_path.urn = self.parent.urn.clone();
+    }
+}
+
 impl Filter {
-    pub(crate) fn as_ref<'a>(&'a self, scope: &'a Bump) -> FilterRef<'a> {
+    /// Borrow this filter as a [`FilterRef`], allocating any slices in `scope`.
+    pub fn as_ref<'a>(&'a self, scope: &'a Bump) -> FilterRef<'a> {
         match self {
             Self::Present(attr_path) => FilterRef::Present(attr_path.as_ref()),
             Self::Compare(attr_path, op, value) => {
@@ -177,7 +216,28 @@ pub enum FilterRef<'a> {
     Not(&'a Self),
 }
 
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let scope = Bump::new();
+        self.as_ref(&scope).fmt(f)
+    }
+}
+
 impl FilterRef<'_> {
+    /// Convert this filter into an owned [`Filter`].
+    pub fn to_owned(&self) -> Filter {
+        match *self {
+            Self::Present(path) => Filter::Present(path.to_owned()),
+            Self::Compare(path, op, value) => {
+                Filter::Compare(path.to_owned(), op, value.to_owned())
+            }
+            Self::Has(path, filter) => Filter::Has(path.to_owned(), Box::new(filter.to_owned())),
+            Self::And(filters) => Filter::And(filters.iter().map(Self::to_owned).collect()),
+            Self::Or(filters) => Filter::Or(filters.iter().map(Self::to_owned).collect()),
+            Self::Not(filter) => Filter::Not(Box::new(filter.to_owned())),
+        }
+    }
+
     /// Iterates over individual filter expressions which are combined with logical AND.
     pub fn iter_cnf(&self) -> impl Iterator<Item = Self> {
         let items: Vec<_> = match self {
@@ -186,16 +246,31 @@ impl FilterRef<'_> {
         };
         items.into_iter()
     }
+
+    /// Iterates over individual filter expressions which are combined with logical OR, such as
+    /// the alternatives of `userName eq "a" or userName eq "b"`.
+    pub fn iter_dnf(&self) -> impl Iterator<Item = Self> {
+        let items: Vec<_> = match self {
+            Self::Or(filters) => filters.iter().flat_map(Self::iter_dnf).collect(),
+            _ => vec![*self],
+        };
+        items.into_iter()
+    }
 }
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum ValuePath {
+/// An owned attribute or filter expression which can be the target of an update.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValuePath {
+    /// A simple attribute path.
     Attr(AttrPath),
+    /// An attribute path with a filter applied.
     Filtered(AttrPath, Filter),
 }
 
 impl ValuePath {
-    pub(crate) fn as_ref<'a>(&'a self, scope: &'a Bump) -> ValuePathRef<'a> {
+    /// Borrow this path as a [`ValuePathRef`], allocating any slices in `scope`.
+    pub fn as_ref<'a>(&'a self, scope: &'a Bump) -> ValuePathRef<'a> {
         match self {
             Self::Attr(attr_path) => ValuePathRef::Attr(attr_path.as_ref()),
             Self::Filtered(attr_path, filter) => {
@@ -205,6 +280,13 @@ impl ValuePath {
     }
 }
 
+impl Display for ValuePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let scope = Bump::new();
+        self.as_ref(&scope).fmt(f)
+    }
+}
+
 /// A attribute or filter expression which can be the target of an update.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum ValuePathRef<'a> {
@@ -214,15 +296,33 @@ pub enum ValuePathRef<'a> {
     Filtered(AttrPathRef<'a>, FilterRef<'a>),
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub(crate) struct AttrPath {
+impl ValuePathRef<'_> {
+    /// Convert this path into an owned [`ValuePath`].
+    pub fn to_owned(&self) -> ValuePath {
+        match *self {
+            Self::Attr(path) => ValuePath::Attr(path.to_owned()),
+            Self::Filtered(path, filter) => ValuePath::Filtered(path.to_owned(), filter.to_owned()),
+        }
+    }
+}
+
+/// An owned attribute reference.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttrPath {
+    /// The schema ID to which this attribute belongs. Omitted for core schema attributes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub urn: Option<String>,
+    /// The name of the attribute.
     pub name: String,
+    /// The name of a sub-attribute, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sub_attr: Option<String>,
 }
 
 impl AttrPath {
-    pub(crate) fn as_ref(&self) -> AttrPathRef {
+    /// Borrow this path as an [`AttrPathRef`].
+    pub fn as_ref(&self) -> AttrPathRef<'_> {
         AttrPathRef {
             urn: self.urn.as_deref(),
             name: self.name.as_str(),
@@ -231,6 +331,12 @@ impl AttrPath {
     }
 }
 
+impl Display for AttrPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+
 /// A single attribute reference.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct AttrPathRef<'a> {
@@ -242,8 +348,34 @@ pub struct AttrPathRef<'a> {
     pub sub_attr: Option<&'a str>,
 }
 
+impl AttrPathRef<'_> {
+    /// Whether this path refers to the same attribute as another, ignoring case.
+    pub fn eq_ignore_ascii_case(&self, other: &AttrPathRef) -> bool {
+        fn eq(a: Option<&str>, b: Option<&str>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                (None, None) => true,
+                _ => false,
+            }
+        }
+        eq(self.urn, other.urn)
+            && self.name.eq_ignore_ascii_case(other.name)
+            && eq(self.sub_attr, other.sub_attr)
+    }
+
+    /// Convert this path into an owned [`AttrPath`].
+    pub fn to_owned(&self) -> AttrPath {
+        AttrPath {
+            urn: self.urn.map(Into::into),
+            name: self.name.into(),
+            sub_attr: self.sub_attr.map(Into::into),
+        }
+    }
+}
+
 /// Supported comparison operators.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum CompareOp {
     /// Equal to.
     Equal,
@@ -284,22 +416,32 @@ impl FromStr for CompareOp {
     }
 }
 
+/// An owned literal value in a filter.
 // https://datatracker.ietf.org/doc/html/rfc7159
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) enum CompValue {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompValue {
+    /// The null value.
     Null,
+    /// A boolean value.
     Bool(bool),
+    /// A numeric value.
     Num(INumber),
+    /// A string value.
     Str(String),
+    /// A date and time, coerced from a string compared with a `dateTime` attribute.
+    DateTime(DateTime),
 }
 
 impl CompValue {
-    pub(crate) fn as_ref(&self) -> CompValueRef {
+    /// Borrow this value as a [`CompValueRef`].
+    pub fn as_ref(&self) -> CompValueRef<'_> {
         match self {
             Self::Null => CompValueRef::Null,
             Self::Bool(b) => CompValueRef::Bool(*b),
             Self::Num(n) => CompValueRef::Num(n),
             Self::Str(s) => CompValueRef::Str(s.as_str()),
+            Self::DateTime(dt) => CompValueRef::DateTime(*dt),
         }
     }
 }
@@ -315,66 +457,49 @@ pub enum CompValueRef<'a> {
     Num(&'a INumber),
     /// A string value.
     Str(&'a str),
+    /// A date and time, coerced from a string compared with a `dateTime` attribute.
+    DateTime(DateTime),
 }
 
-pub(crate) fn parse_filter(input: &str) -> Result<Filter, Error> {
-    let (remain, expression) = parse::filter(input)
-        .map_err(|e| e.to_owned())
-        .finish()
-        .map_err(|e| Error {
-            status: StatusCode::BAD_REQUEST,
-            schemas: Default::default(),
-            scim_type: Some(ErrorType::InvalidFilter),
-            detail: format!("Invalid filter: {:?}", e.input),
-        })?;
-    if !remain.is_empty() {
-        return Err(Error {
-            status: StatusCode::BAD_REQUEST,
-            schemas: Default::default(),
-            scim_type: Some(ErrorType::InvalidFilter),
-            detail: format!("Invalid filter: unexpected {:?}", remain),
-        });
+impl CompValueRef<'_> {
+    /// Convert this value into an owned [`CompValue`].
+    pub fn to_owned(&self) -> CompValue {
+        match *self {
+            Self::Null => CompValue::Null,
+            Self::Bool(b) => CompValue::Bool(b),
+            Self::Num(n) => CompValue::Num(n.clone()),
+            Self::Str(s) => CompValue::Str(s.into()),
+            Self::DateTime(dt) => CompValue::DateTime(dt),
+        }
     }
-    Ok(expression)
 }
 
-pub(crate) fn parse_value_path(input: &str) -> Result<ValuePath, Error> {
-    let (remain, expression) = parse::value_path(input)
-        .map_err(|e| e.to_owned())
-        .finish()
-        .map_err(|e| Error {
-            status: StatusCode::BAD_REQUEST,
-            schemas: Default::default(),
-            scim_type: Some(ErrorType::InvalidPath),
-            detail: format!("Invalid path: {:?}", e.input),
-        })?;
-    if !remain.is_empty() {
-        return Err(Error {
-            status: StatusCode::BAD_REQUEST,
-            schemas: Default::default(),
-            scim_type: Some(ErrorType::InvalidPath),
-            detail: format!("Invalid path: unexpected {:?}", remain),
-        });
-    }
-    Ok(expression)
+/// Parse a filter expression, as found in the `filter` query parameter.
+pub fn parse_filter(input: &str) -> Result<Filter, ParseError> {
+    parse::parse_complete(
+        input,
+        ParseKind::Filter,
+        parse::filter,
+        &[Expected::LogicalOperator, Expected::End],
+    )
 }
-pub(crate) fn parse_attr_path(input: &str) -> Result<AttrPath, Error> {
-    let (remain, expression) = parse::attr_path(input)
-        .map_err(|e| e.to_owned())
-        .finish()
-        .map_err(|e| Error {
-            status: StatusCode::BAD_REQUEST,
-            schemas: Default::default(),
-            scim_type: Some(ErrorType::InvalidPath),
-            detail: format!("Invalid attribute path: {:?}", e.input),
-        })?;
-    if !remain.is_empty() {
-        return Err(Error {
-            status: StatusCode::BAD_REQUEST,
-            schemas: Default::default(),
-            scim_type: Some(ErrorType::InvalidPath),
-            detail: format!("Invalid attribute path: unexpected {:?}", remain),
-        });
-    }
-    Ok(expression)
+
+/// Parse the path of a PATCH operation, which may contain a value filter.
+pub fn parse_value_path(input: &str) -> Result<ValuePath, ParseError> {
+    parse::parse_complete(
+        input,
+        ParseKind::ValuePath,
+        parse::value_path,
+        &[Expected::End],
+    )
+}
+
+/// Parse an attribute path, as found in the `attributes` and `sortBy` query parameters.
+pub fn parse_attr_path(input: &str) -> Result<AttrPath, ParseError> {
+    parse::parse_complete(
+        input,
+        ParseKind::AttrPath,
+        parse::attr_path,
+        &[Expected::End],
+    )
 }
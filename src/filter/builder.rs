@@ -0,0 +1,166 @@
+use std::ops::Not;
+
+use cream_core::DateTime;
+use ijson::INumber;
+
+use super::{parse_attr_path, AttrPath, AttrPathPrefixer, CompValue, CompareOp, Filter, Visitor};
+
+/// Start building a filter on an attribute, such as `attr("userName").eq("bjensen")`.
+///
+/// The path may refer to a sub-attribute or an extension attribute, such as `name.familyName`
+/// or `urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:employeeNumber`.
+///
+/// # Panics
+///
+/// Panics if `path` is not a valid attribute path. Use [`parse_attr_path`] to handle invalid
+/// paths.
+#[track_caller]
+pub fn attr(path: &str) -> AttrPath {
+    match parse_attr_path(path) {
+        Ok(path) => path,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+impl AttrPath {
+    fn compare(self, op: CompareOp, value: impl Into<CompValue>) -> Filter {
+        Filter::Compare(self, op, value.into())
+    }
+
+    /// Whether the attribute is present.
+    pub fn pr(self) -> Filter {
+        Filter::Present(self)
+    }
+    /// Whether the attribute is equal to a value.
+    pub fn eq(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::Equal, value)
+    }
+    /// Whether the attribute is not equal to a value.
+    pub fn ne(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::NotEqual, value)
+    }
+    /// Whether the attribute contains a value.
+    pub fn co(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::Contains, value)
+    }
+    /// Whether the attribute starts with a value.
+    pub fn sw(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::StartsWith, value)
+    }
+    /// Whether the attribute ends with a value.
+    pub fn ew(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::EndsWith, value)
+    }
+    /// Whether the attribute is greater than a value.
+    pub fn gt(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::GreaterThan, value)
+    }
+    /// Whether the attribute is greater than or equal to a value.
+    pub fn ge(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::GreaterThanOrEqual, value)
+    }
+    /// Whether the attribute is less than a value.
+    pub fn lt(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::LessThan, value)
+    }
+    /// Whether the attribute is less than or equal to a value.
+    pub fn le(self, value: impl Into<CompValue>) -> Filter {
+        self.compare(CompareOp::LessThanOrEqual, value)
+    }
+    /// Whether any value of a multi-valued attribute matches a filter, as in
+    /// `emails[type eq "work"]`. Paths within the filter are relative to this attribute, so
+    /// that `attr("emails").has(attr("type").eq("work"))` produces the example above.
+    pub fn has(self, mut filter: Filter) -> Filter {
+        AttrPathPrefixer { parent: &self }.visit_filter(&mut filter);
+        Filter::Has(self, Box::new(filter))
+    }
+}
+
+impl Filter {
+    /// Combine two filters with a logical AND.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            _ => Self::And(vec![self, other]),
+        }
+    }
+    /// Combine two filters with a logical OR.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            _ => Self::Or(vec![self, other]),
+        }
+    }
+}
+
+/// Negates a filter, as in `!attr("title").pr()`.
+impl Not for Filter {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
+
+impl From<&str> for CompValue {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<String> for CompValue {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<bool> for CompValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<DateTime> for CompValue {
+    fn from(value: DateTime) -> Self {
+        Self::DateTime(value)
+    }
+}
+
+impl From<INumber> for CompValue {
+    fn from(value: INumber) -> Self {
+        Self::Num(value)
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for CompValue {
+                fn from(value: $ty) -> Self {
+                    Self::Num(value.into())
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i32, i64, u32, u64, usize);
+
+impl From<f64> for CompValue {
+    /// Non-finite numbers, which cannot be represented in a filter, are converted to null.
+    fn from(value: f64) -> Self {
+        INumber::try_from(value).map_or(Self::Null, Self::Num)
+    }
+}
+
+impl<T: Into<CompValue>> From<Option<T>> for CompValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
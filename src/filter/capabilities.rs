@@ -0,0 +1,223 @@
+use axum::http::StatusCode;
+use cream_core::Type;
+use serde::Serialize;
+
+use crate::{
+    error::{Error, ErrorType},
+    schema::{describe_type, SchemaRegistry},
+};
+
+use super::{attr, AttrPath, AttrPathRef, CompValueRef, CompareOp, FilterRef};
+
+/// The filters which a resource manager supports.
+///
+/// Capabilities are declared per attribute, as the operators which may be used with it: `pr`
+/// or any of the comparison operators. Comparisons with `null` are presence tests, so they
+/// are allowed wherever `pr` is. Comparison values must match the type of the attribute,
+/// after [coercion](super::Filter::coerce), so that a manager which supports
+/// `loginCount: eq` only ever receives integers. Value filters such as
+/// `emails[type eq "work"]` are supported if the comparisons inside them are, and `and`, `or`
+/// and `not` are always supported.
+///
+/// Filters with unsupported clauses are rejected with an `invalidFilter` error before they
+/// reach the manager. The capabilities are also published on the resource type, under the
+/// [`FILTER_CAPABILITIES_SCHEMA`] extension.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterCapabilities {
+    attributes: Vec<SupportedAttribute>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SupportedAttribute {
+    path: AttrPath,
+    present: bool,
+    operators: Vec<CompareOp>,
+}
+
+/// The ID of the extension schema under which [`FilterCapabilities`] are published on
+/// resource types.
+pub const FILTER_CAPABILITIES_SCHEMA: &str =
+    "urn:cream:params:scim:schemas:extension:2.0:FilterCapabilities";
+
+#[derive(Serialize)]
+pub(crate) struct FilterCapabilitiesExtension {
+    attributes: Vec<AttributeCapabilities>,
+}
+
+#[derive(Serialize)]
+struct AttributeCapabilities {
+    path: String,
+    operators: Vec<String>,
+}
+
+fn has_type(type_: &Type, value: CompValueRef) -> bool {
+    match (type_, value) {
+        (Type::String | Type::Reference | Type::Binary, CompValueRef::Str(_)) => true,
+        (Type::Boolean, CompValueRef::Bool(_)) => true,
+        (Type::Integer, CompValueRef::Num(n)) => !n.has_decimal_point() && n.to_i64().is_some(),
+        (Type::Decimal, CompValueRef::Num(_)) => true,
+        (Type::DateTime, CompValueRef::DateTime(_)) => true,
+        _ => false,
+    }
+}
+
+impl SupportedAttribute {
+    fn operator_names(&self) -> Vec<String> {
+        self.present
+            .then(|| "pr".to_string())
+            .into_iter()
+            .chain(self.operators.iter().map(ToString::to_string))
+            .collect()
+    }
+}
+
+// The core schema may be spelled out, but is omitted from the paths in filters.
+fn without_core_urn<'a>(schemas: &SchemaRegistry, mut path: AttrPathRef<'a>) -> AttrPathRef<'a> {
+    if path
+        .urn
+        .is_some_and(|urn| urn.eq_ignore_ascii_case(&schemas.core().id))
+    {
+        path.urn = None;
+    }
+    path
+}
+
+fn unsupported(detail: String) -> Error {
+    Error::new(
+        StatusCode::BAD_REQUEST,
+        Some(ErrorType::InvalidFilter),
+        detail,
+    )
+}
+
+impl FilterCapabilities {
+    /// Create an empty set of capabilities, which supports no filters at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Support filtering on an attribute with the given operators, such as
+    /// `.attribute("emails.value", &["eq", "sw"])`.
+    ///
+    /// Supporting `eq` implies supporting `ne`, since a negated `eq` comparison is normalized to
+    /// `ne` before it reaches the manager.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is not a valid attribute path, or an operator is not `pr` or a
+    /// comparison operator.
+    pub fn attribute(mut self, path: &str, operators: &[&str]) -> Self {
+        let mut supported = SupportedAttribute {
+            path: attr(path),
+            present: false,
+            operators: Vec::new(),
+        };
+        for operator in operators {
+            if operator.eq_ignore_ascii_case("pr") {
+                supported.present = true;
+            } else {
+                match operator.parse() {
+                    Ok(op) => supported.operators.push(op),
+                    Err(e) => panic!("{}", e),
+                }
+            }
+        }
+        self.attributes.push(supported);
+        self
+    }
+
+    fn find(&self, schemas: &SchemaRegistry, path: AttrPathRef) -> Option<&SupportedAttribute> {
+        let path = without_core_urn(schemas, path);
+        self.attributes.iter().find(|supported| {
+            without_core_urn(schemas, supported.path.as_ref()).eq_ignore_ascii_case(&path)
+        })
+    }
+
+    fn check_clause(
+        &self,
+        schemas: &SchemaRegistry,
+        path: AttrPathRef,
+        op: Option<CompareOp>,
+        value: CompValueRef,
+    ) -> Result<(), Error> {
+        let supported = self
+            .find(schemas, path)
+            .ok_or_else(|| unsupported(format!("Filtering on `{}` is not supported", path)))?;
+        let is_presence_test = match op {
+            None => true,
+            Some(CompareOp::Equal | CompareOp::NotEqual) => matches!(value, CompValueRef::Null),
+            Some(_) => false,
+        };
+        // Filters are normalized before they are checked, so `not (userName eq "x")` arrives
+        // as `userName ne "x"`.
+        let is_supported = match op {
+            _ if is_presence_test => supported.present,
+            Some(CompareOp::NotEqual) => supported
+                .operators
+                .iter()
+                .any(|op| matches!(op, CompareOp::Equal | CompareOp::NotEqual)),
+            Some(op) => supported.operators.contains(&op),
+            None => false,
+        };
+        if !is_supported {
+            let operator = match op {
+                Some(op) if !is_presence_test => format!("`{}`", op),
+                Some(op) => format!("`{} null`", op),
+                None => "`pr`".into(),
+            };
+            return Err(unsupported(format!(
+                "Filtering on `{}` with {} is not supported. Supported operators are: {}",
+                path,
+                operator,
+                supported.operator_names().join(", ")
+            )));
+        }
+        if is_presence_test {
+            return Ok(());
+        }
+        if let Some((_, attr)) = schemas.comparison_attribute(path) {
+            let type_ = &attr.type_;
+            if !has_type(type_, value) {
+                return Err(unsupported(format!(
+                    "Filtering on `{}` requires {} value, not {}",
+                    path,
+                    describe_type(type_),
+                    value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a filter is supported, returning an `invalidFilter` error describing the
+    /// first unsupported clause if not. Comparison values are checked against the types of
+    /// their attributes in `schemas`.
+    pub fn check(&self, schemas: &SchemaRegistry, filter: &FilterRef) -> Result<(), Error> {
+        match *filter {
+            FilterRef::Present(path) => self.check_clause(schemas, path, None, CompValueRef::Null),
+            FilterRef::Compare(path, op, value) => {
+                self.check_clause(schemas, path, Some(op), value)
+            }
+            FilterRef::Has(_, filter) | FilterRef::Not(filter) => self.check(schemas, filter),
+            FilterRef::And(filters) | FilterRef::Or(filters) => filters
+                .iter()
+                .try_for_each(|filter| self.check(schemas, filter)),
+        }
+    }
+
+    pub(crate) fn to_extension(&self) -> FilterCapabilitiesExtension {
+        FilterCapabilitiesExtension {
+            attributes: self
+                .attributes
+                .iter()
+                .map(|supported| AttributeCapabilities {
+                    path: supported.path.to_string(),
+                    operators: supported.operator_names(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,64 @@
+use bumpalo::Bump;
+
+use crate::{filter::parse_filter, schema::test_registry};
+
+use super::FilterCapabilities;
+
+#[test]
+fn test_check() {
+    let registry = test_registry();
+    let capabilities = FilterCapabilities::new()
+        .attribute(
+            "urn:ietf:params:scim:schemas:core:2.0:User:userName",
+            &["eq"],
+        )
+        .attribute("loginCount", &["pr", "gt", "lt"])
+        .attribute("emails.type", &["eq"]);
+    let check = |input: &str| {
+        let scope = Bump::new();
+        let filter = parse_filter(input).unwrap();
+        capabilities
+            .check(&registry, &filter.as_ref(&scope))
+            .map_err(|e| e.detail)
+    };
+
+    assert_eq!(check(r#"userName eq "bjensen""#), Ok(()));
+    assert_eq!(check(r#"userName ne "bjensen""#), Ok(()));
+    assert_eq!(
+        check(r#"loginCount gt 1 and loginCount lt 5 or loginCount eq null"#),
+        Ok(())
+    );
+    assert_eq!(check(r#"not (emails[type eq "work"])"#), Ok(()));
+    assert_eq!(
+        check(r#"emails[value eq "x"]"#),
+        Err("Filtering on `emails.value` is not supported".to_string())
+    );
+    assert_eq!(
+        check("loginCount ge 1"),
+        Err(
+            "Filtering on `loginCount` with `ge` is not supported. Supported operators are: pr, gt, lt"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        check("loginCount ne 1"),
+        Err(
+            "Filtering on `loginCount` with `ne` is not supported. Supported operators are: pr, gt, lt"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        check("loginCount gt 1.5"),
+        Err("Filtering on `loginCount` requires an integer value, not 1.5".to_string())
+    );
+    assert_eq!(
+        check(r#"loginCount gt "1""#),
+        Err(r#"Filtering on `loginCount` requires an integer value, not "1""#.to_string())
+    );
+}
+
+#[test]
+#[should_panic(expected = "xx is not a valid operator")]
+fn test_invalid_operator() {
+    FilterCapabilities::new().attribute("userName", &["xx"]);
+}
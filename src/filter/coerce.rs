@@ -0,0 +1,132 @@
+use axum::http::StatusCode;
+use cream_core::{DateTime, Type};
+use ijson::INumber;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    error::{Error, ErrorType},
+    schema::SchemaRegistry,
+};
+
+use super::{AttrPath, CompValue, CompareOp, Filter, ValuePath};
+
+impl Filter {
+    /// Convert the values in comparisons to the types of the attributes they are compared with,
+    /// as defined by `schemas`:
+    ///
+    /// - Strings compared with `dateTime` attributes become [`CompValue::DateTime`].
+    /// - Numeric strings compared with `integer` or `decimal` attributes become numbers.
+    /// - The strings `"true"` and `"false"`, in any case, compared with `boolean` attributes become booleans.
+    ///
+    /// A comparison with a complex attribute uses the type of its `value` sub-attribute.
+    /// Comparisons with `null`, substring comparisons and comparisons with unknown attributes
+    /// are left unchanged. Values which cannot be converted are rejected with an
+    /// `invalidFilter` error.
+    pub fn coerce(&mut self, schemas: &SchemaRegistry) -> Result<(), Error> {
+        coerce_filter(self, schemas, &ErrorType::InvalidFilter)
+    }
+}
+
+impl ValuePath {
+    /// Convert the values in the value filter of this path, if any, as described by
+    /// [`Filter::coerce`]. Values which cannot be converted are rejected with an `invalidPath`
+    /// error.
+    pub fn coerce(&mut self, schemas: &SchemaRegistry) -> Result<(), Error> {
+        match self {
+            Self::Attr(_) => Ok(()),
+            Self::Filtered(_, filter) => coerce_filter(filter, schemas, &ErrorType::InvalidPath),
+        }
+    }
+}
+
+fn coerce_filter(
+    filter: &mut Filter,
+    schemas: &SchemaRegistry,
+    scim_type: &ErrorType,
+) -> Result<(), Error> {
+    match filter {
+        Filter::Present(_) => Ok(()),
+        Filter::Compare(path, op, value) => {
+            if matches!(
+                op,
+                CompareOp::Contains | CompareOp::StartsWith | CompareOp::EndsWith
+            ) {
+                return Ok(());
+            }
+            match schemas.comparison_attribute(path.as_ref()) {
+                Some((_, attr)) => coerce_value(path, &attr.type_, value, scim_type),
+                None => Ok(()),
+            }
+        }
+        Filter::Has(_, filter) | Filter::Not(filter) => coerce_filter(filter, schemas, scim_type),
+        Filter::And(filters) | Filter::Or(filters) => filters
+            .iter_mut()
+            .try_for_each(|filter| coerce_filter(filter, schemas, scim_type)),
+    }
+}
+
+fn parse_number(s: &str, integer: bool) -> Option<INumber> {
+    let number: INumber = serde_json::from_str(s).ok()?;
+    (!integer || !number.has_decimal_point()).then_some(number)
+}
+
+fn coerce_value(
+    path: &AttrPath,
+    type_: &Type,
+    value: &mut CompValue,
+    scim_type: &ErrorType,
+) -> Result<(), Error> {
+    let (coerced, expected) = match (type_, &*value) {
+        (_, CompValue::Null) => return Ok(()),
+        (Type::DateTime, CompValue::Str(s)) => (
+            OffsetDateTime::parse(s, &Rfc3339)
+                .ok()
+                .map(|dt| CompValue::DateTime(DateTime(dt))),
+            "an RFC 3339 date and time",
+        ),
+        (Type::DateTime, CompValue::DateTime(_)) => return Ok(()),
+        (Type::DateTime, _) => (None, "an RFC 3339 date and time"),
+        (Type::Integer, CompValue::Num(n)) if !n.has_decimal_point() => return Ok(()),
+        (Type::Integer, CompValue::Str(s)) => {
+            (parse_number(s, true).map(CompValue::Num), "an integer")
+        }
+        (Type::Integer, _) => (None, "an integer"),
+        (Type::Decimal, CompValue::Num(_)) => return Ok(()),
+        (Type::Decimal, CompValue::Str(s)) => {
+            (parse_number(s, false).map(CompValue::Num), "a number")
+        }
+        (Type::Decimal, _) => (None, "a number"),
+        (Type::Boolean, CompValue::Bool(_)) => return Ok(()),
+        (Type::Boolean, CompValue::Str(s)) => (
+            if s.eq_ignore_ascii_case("true") {
+                Some(CompValue::Bool(true))
+            } else if s.eq_ignore_ascii_case("false") {
+                Some(CompValue::Bool(false))
+            } else {
+                None
+            },
+            "a boolean",
+        ),
+        (Type::Boolean, _) => (None, "a boolean"),
+        _ => return Ok(()),
+    };
+    match coerced {
+        Some(coerced) => {
+            *value = coerced;
+            Ok(())
+        }
+        None => Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            Some(scim_type.clone()),
+            format!(
+                "Cannot compare `{}` with {}: expected {}",
+                path,
+                value.as_ref(),
+                expected
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+use cream_core::DateTime;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    error::ErrorType,
+    filter::{parse_filter, parse_value_path, CompValue, Filter, ValuePath},
+    schema::test_registry,
+};
+
+fn date_time(s: &str) -> CompValue {
+    CompValue::DateTime(DateTime(OffsetDateTime::parse(s, &Rfc3339).unwrap()))
+}
+
+fn coerce(input: &str) -> Result<CompValue, String> {
+    let mut filter = parse_filter(input).unwrap();
+    filter.coerce(&test_registry()).map_err(|e| {
+        assert!(matches!(e.scim_type, Some(ErrorType::InvalidFilter)));
+        e.detail
+    })?;
+    match filter {
+        Filter::Compare(_, _, value) => Ok(value),
+        _ => panic!("expected a comparison"),
+    }
+}
+
+#[test]
+fn test_coerce() {
+    let cases = [
+        (
+            r#"meta.lastModified gt "2011-05-13T04:42:34Z""#,
+            date_time("2011-05-13T04:42:34Z"),
+        ),
+        (
+            r#"meta.created le "2011-05-13T06:42:34+02:00""#,
+            date_time("2011-05-13T04:42:34Z"),
+        ),
+        (r#"loginCount eq "12""#, CompValue::from(12)),
+        (r#"active eq "true""#, CompValue::Bool(true)),
+        (r#"active ne "FALSE""#, CompValue::Bool(false)),
+        ("active eq true", CompValue::Bool(true)),
+        ("loginCount eq null", CompValue::Null),
+        (r#"userName eq "12""#, CompValue::from("12")),
+        (r#"nickName eq "true""#, CompValue::from("true")),
+        (r#"meta.created co "2011""#, CompValue::from("2011")),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(coerce(input), Ok(expected), "{}", input);
+    }
+}
+
+#[test]
+fn test_coerce_errors() {
+    let cases = [
+        (
+            r#"meta.lastModified gt "yesterday""#,
+            r#"Cannot compare `meta.lastModified` with "yesterday": expected an RFC 3339 date and time"#,
+        ),
+        (
+            "meta.lastModified gt 2011",
+            "Cannot compare `meta.lastModified` with 2011: expected an RFC 3339 date and time",
+        ),
+        (
+            r#"loginCount eq "1.5""#,
+            r#"Cannot compare `loginCount` with "1.5": expected an integer"#,
+        ),
+        (
+            "loginCount gt 1.5",
+            "Cannot compare `loginCount` with 1.5: expected an integer",
+        ),
+        (
+            "loginCount eq true",
+            "Cannot compare `loginCount` with true: expected an integer",
+        ),
+        (
+            r#"active eq "yes""#,
+            r#"Cannot compare `active` with "yes": expected a boolean"#,
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(coerce(input), Err(expected.to_string()), "{}", input);
+    }
+}
+
+#[test]
+fn test_coerce_value_path() {
+    let mut path = parse_value_path(r#"emails[primary eq "yes"].value"#).unwrap();
+    let err = path.coerce(&test_registry()).unwrap_err();
+    assert!(matches!(err.scim_type, Some(ErrorType::InvalidPath)));
+
+    let mut path = parse_value_path(r#"emails[primary eq "true"]"#).unwrap();
+    path.coerce(&test_registry()).unwrap();
+    let ValuePath::Filtered(_, Filter::Compare(_, _, value)) = path else {
+        panic!("expected a value filter");
+    };
+    assert_eq!(value, CompValue::Bool(true));
+}
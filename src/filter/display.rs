@@ -0,0 +1,144 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::{AttrPathRef, CompValueRef, CompareOp, FilterRef, ValuePathRef};
+
+impl Display for AttrPathRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(urn) = self.urn {
+            write!(f, "{}:", urn)?;
+        }
+        f.write_str(self.name)?;
+        if let Some(sub_attr) = self.sub_attr {
+            write!(f, ".{}", sub_attr)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Equal => "eq",
+            Self::NotEqual => "ne",
+            Self::Contains => "co",
+            Self::StartsWith => "sw",
+            Self::EndsWith => "ew",
+            Self::GreaterThan => "gt",
+            Self::GreaterThanOrEqual => "ge",
+            Self::LessThan => "lt",
+            Self::LessThanOrEqual => "le",
+        })
+    }
+}
+
+impl Display for CompValueRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Literals use JSON syntax, including the escaping of strings.
+        let json = match self {
+            Self::Null => return f.write_str("null"),
+            Self::Bool(b) => return write!(f, "{}", b),
+            Self::Num(n) => serde_json::to_string(n),
+            Self::Str(s) => serde_json::to_string(s),
+            Self::DateTime(dt) => serde_json::to_string(dt),
+        };
+        f.write_str(&json.map_err(|_| fmt::Error)?)
+    }
+}
+
+// Paths within a value filter are stored as sub-attributes of the parent, but are written
+// relative to it.
+fn relative_path<'a>(parent: Option<AttrPathRef<'a>>, path: AttrPathRef<'a>) -> AttrPathRef<'a> {
+    match (parent, path.sub_attr) {
+        (Some(parent), Some(sub_attr)) if parent.urn == path.urn && parent.name == path.name => {
+            AttrPathRef {
+                urn: None,
+                name: sub_attr,
+                sub_attr: None,
+            }
+        }
+        _ => path,
+    }
+}
+
+// How tightly an expression binds, used to decide where parentheses are needed.
+fn precedence(filter: &FilterRef) -> u8 {
+    match filter {
+        FilterRef::Or(_) => 0,
+        FilterRef::And(_) => 1,
+        _ => 2,
+    }
+}
+
+fn write_filter(
+    f: &mut Formatter<'_>,
+    filter: &FilterRef,
+    parent: Option<AttrPathRef>,
+) -> fmt::Result {
+    match *filter {
+        FilterRef::Present(path) => write!(f, "{} pr", relative_path(parent, path)),
+        FilterRef::Compare(path, op, value) => {
+            write!(f, "{} {} {}", relative_path(parent, path), op, value)
+        }
+        FilterRef::Has(path, inner) => {
+            write!(f, "{}[", relative_path(parent, path))?;
+            write_filter(f, inner, Some(path))?;
+            f.write_str("]")
+        }
+        FilterRef::And(filters) | FilterRef::Or(filters) => {
+            let sep = match filter {
+                FilterRef::And(_) => " and ",
+                _ => " or ",
+            };
+            for (i, item) in filters.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(sep)?;
+                }
+                // Nested expressions of the same kind are parenthesised so that the structure
+                // of the filter is preserved.
+                if precedence(item) <= precedence(filter) {
+                    f.write_str("(")?;
+                    write_filter(f, item, parent)?;
+                    f.write_str(")")?;
+                } else {
+                    write_filter(f, item, parent)?;
+                }
+            }
+            Ok(())
+        }
+        FilterRef::Not(inner) => {
+            f.write_str("not (")?;
+            write_filter(f, inner, parent)?;
+            f.write_str(")")
+        }
+    }
+}
+
+impl Display for FilterRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_filter(f, self, None)
+    }
+}
+
+impl Display for ValuePathRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Attr(path) => write!(f, "{}", path),
+            Self::Filtered(path, filter) => {
+                let parent = AttrPathRef {
+                    sub_attr: None,
+                    ..*path
+                };
+                write!(f, "{}[", parent)?;
+                write_filter(f, filter, Some(parent))?;
+                f.write_str("]")?;
+                if let Some(sub_attr) = path.sub_attr {
+                    write!(f, ".{}", sub_attr)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
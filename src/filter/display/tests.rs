@@ -0,0 +1,183 @@
+use bumpalo::Bump;
+use ijson::INumber;
+use proptest::prelude::*;
+
+use crate::filter::{
+    parse_filter, parse_value_path, AttrPath, CompValue, CompareOp, Filter, ValuePath,
+};
+
+fn render_filter(input: &str) -> String {
+    let scope = Bump::new();
+    parse_filter(input).unwrap().as_ref(&scope).to_string()
+}
+
+fn render_value_path(input: &str) -> String {
+    let scope = Bump::new();
+    parse_value_path(input).unwrap().as_ref(&scope).to_string()
+}
+
+#[test]
+fn test_display() {
+    let cases = [
+        ("userName Eq \"bjensen\"", "userName eq \"bjensen\""),
+        ("title pr", "title pr"),
+        ("name.familyName co \"O'Malley\"", "name.familyName co \"O'Malley\""),
+        ("displayName eq \"a \\\"b\\\" \\\\ c\"", "displayName eq \"a \\\"b\\\" \\\\ c\""),
+        ("meta.lastModified gt \"2011-05-13T04:42:34Z\"", "meta.lastModified gt \"2011-05-13T04:42:34Z\""),
+        ("loginCount ge 3.5", "loginCount ge 3.5"),
+        ("active eq true and manager eq null", "active eq true and manager eq null"),
+        (
+            "title pr and (userType eq \"Employee\" or userType eq \"Intern\")",
+            "title pr and (userType eq \"Employee\" or userType eq \"Intern\")",
+        ),
+        (
+            "(title pr and userType eq \"Employee\") or emails pr",
+            "title pr and userType eq \"Employee\" or emails pr",
+        ),
+        ("not (title pr)", "not (title pr)"),
+        (
+            "emails[type eq \"work\" and value co \"@example.com\"]",
+            "emails[type eq \"work\" and value co \"@example.com\"]",
+        ),
+        (
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.displayName sw \"J\"",
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.displayName sw \"J\"",
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(render_filter(input), expected);
+    }
+
+    assert_eq!(render_value_path("emails"), "emails");
+    assert_eq!(
+        render_value_path("emails[type eq \"work\"].value"),
+        "emails[type eq \"work\"].value"
+    );
+    assert_eq!(
+        render_value_path(
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value"
+        ),
+        "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value"
+    );
+}
+
+fn name() -> impl Strategy<Value = String> {
+    "[a-zA-Z][a-zA-Z0-9_-]{0,8}"
+}
+
+fn attr_path() -> impl Strategy<Value = AttrPath> {
+    (
+        proptest::option::weighted(0.2, "[a-z0-9.-]{1,6}(:[a-zA-Z0-9.-]{1,6}){0,4}"),
+        name(),
+        proptest::option::of(name()),
+    )
+        .prop_map(|(urn, name, sub_attr)| AttrPath {
+            urn,
+            name,
+            sub_attr,
+        })
+}
+
+fn comp_value() -> impl Strategy<Value = CompValue> {
+    prop_oneof![
+        Just(CompValue::Null),
+        any::<bool>().prop_map(CompValue::Bool),
+        any::<i64>().prop_map(|n| CompValue::Num(n.into())),
+        (-100_000i32..100_000)
+            .prop_map(|n| CompValue::Num(INumber::try_from(f64::from(n) / 8.0).unwrap())),
+        any::<String>().prop_map(CompValue::Str),
+    ]
+}
+
+fn compare_op() -> impl Strategy<Value = CompareOp> {
+    prop_oneof![
+        Just(CompareOp::Equal),
+        Just(CompareOp::NotEqual),
+        Just(CompareOp::Contains),
+        Just(CompareOp::StartsWith),
+        Just(CompareOp::EndsWith),
+        Just(CompareOp::GreaterThan),
+        Just(CompareOp::GreaterThanOrEqual),
+        Just(CompareOp::LessThan),
+        Just(CompareOp::LessThanOrEqual),
+    ]
+}
+
+// Filters made of attribute expressions, where every path is a sub-attribute of `parent` when
+// one is given, as in a value filter.
+fn logical_filter(parent: Option<AttrPath>) -> impl Strategy<Value = Filter> {
+    let path = attr_path()
+        .prop_map(move |path| match &parent {
+            Some(parent) => AttrPath {
+                urn: parent.urn.clone(),
+                name: parent.name.clone(),
+                sub_attr: Some(path.name),
+            },
+            None => path,
+        })
+        .boxed();
+    let leaf = prop_oneof![
+        path.clone().prop_map(Filter::Present),
+        (path, compare_op(), comp_value())
+            .prop_map(|(path, op, value)| Filter::Compare(path, op, value)),
+    ];
+    leaf.prop_recursive(4, 16, 4, |inner| {
+        prop_oneof![
+            proptest::collection::vec(inner.clone(), 2..4).prop_map(Filter::And),
+            proptest::collection::vec(inner.clone(), 2..4).prop_map(Filter::Or),
+            inner.prop_map(|filter| Filter::Not(Box::new(filter))),
+        ]
+    })
+}
+
+fn filter() -> impl Strategy<Value = Filter> {
+    let has = attr_path()
+        .prop_map(|path| AttrPath {
+            sub_attr: None,
+            ..path
+        })
+        .prop_flat_map(|path| {
+            logical_filter(Some(path.clone()))
+                .prop_map(move |filter| Filter::Has(path.clone(), Box::new(filter)))
+        });
+    let leaf = prop_oneof![3 => logical_filter(None), 1 => has];
+    leaf.prop_recursive(2, 8, 3, |inner| {
+        prop_oneof![
+            proptest::collection::vec(inner.clone(), 2..4).prop_map(Filter::And),
+            proptest::collection::vec(inner.clone(), 2..4).prop_map(Filter::Or),
+            inner.prop_map(|filter| Filter::Not(Box::new(filter))),
+        ]
+    })
+}
+
+fn value_path() -> impl Strategy<Value = ValuePath> {
+    let filtered = attr_path().prop_flat_map(|path| {
+        let parent = AttrPath {
+            sub_attr: None,
+            ..path.clone()
+        };
+        logical_filter(Some(parent))
+            .prop_map(move |filter| ValuePath::Filtered(path.clone(), filter))
+    });
+    prop_oneof![attr_path().prop_map(ValuePath::Attr), filtered]
+}
+
+proptest! {
+    #[test]
+    fn test_filter_round_trip(filter in filter()) {
+        let scope = Bump::new();
+        let rendered = filter.as_ref(&scope).to_string();
+        let parsed = parse_filter(&rendered);
+        prop_assert!(parsed.is_ok(), "Failed to parse {}", rendered);
+        prop_assert_eq!(parsed.unwrap(), filter, "{}", rendered);
+    }
+
+    #[test]
+    fn test_value_path_round_trip(value_path in value_path()) {
+        let scope = Bump::new();
+        let rendered = value_path.as_ref(&scope).to_string();
+        let parsed = parse_value_path(&rendered);
+        prop_assert!(parsed.is_ok(), "Failed to parse {}", rendered);
+        prop_assert_eq!(parsed.unwrap(), value_path, "{}", rendered);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::{self, Display, Formatter};
+
+use axum::http::StatusCode;
+
+use crate::error::{Error, ErrorType};
+
+use super::parse::MAX_NESTING;
+
+/// Something which the parser expected to find at the position of a syntax error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Expected {
+    /// The start of a filter expression: an attribute path, `not` or `(`.
+    Filter,
+    /// An attribute name.
+    AttrName,
+    /// `pr` or a comparison operator such as `eq`.
+    Operator,
+    /// A comparison value: a string, number, `true`, `false` or `null`.
+    Value,
+    /// The `and` or `or` logical operators.
+    LogicalOperator,
+    /// A closing parenthesis.
+    CloseParen,
+    /// A closing bracket, ending a value filter.
+    CloseBracket,
+    /// A closing quote, ending a string.
+    CloseQuote,
+    /// The end of the input.
+    End,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Filter => "an attribute path, `not` or `(`",
+            Self::AttrName => "an attribute name",
+            Self::Operator => "`pr` or a comparison operator",
+            Self::Value => "a string, number, `true`, `false` or `null`",
+            Self::LogicalOperator => "`and`, `or`",
+            Self::CloseParen => "`)`",
+            Self::CloseBracket => "`]`",
+            Self::CloseQuote => "a closing `\"`",
+            Self::End => "the end of the input",
+        })
+    }
+}
+
+/// What was being parsed when a [`ParseError`] occurred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseKind {
+    /// A filter, such as the `filter` query parameter.
+    Filter,
+    /// The path of a PATCH operation, which may contain a value filter.
+    ValuePath,
+    /// An attribute path, such as the `sortBy` query parameter.
+    AttrPath,
+}
+
+/// A syntax error in a filter or attribute path.
+///
+/// The error records where parsing failed, so that the invalid part of the input can be
+/// highlighted. It converts into an [`Error`] with the `invalidFilter` or `invalidPath` type,
+/// whose detail contains a description of the problem followed by the input with a caret
+/// marking the offending position:
+///
+/// ```text
+/// Invalid filter: expected `pr` or a comparison operator at offset 9
+/// userName is "bjensen"
+///          ^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub(super) kind: ParseKind,
+    pub(super) input: String,
+    pub(super) offset: usize,
+    pub(super) expected: Vec<Expected>,
+    pub(super) too_deep: bool,
+}
+
+impl ParseError {
+    /// What was being parsed.
+    pub fn kind(&self) -> ParseKind {
+        self.kind
+    }
+
+    /// The input which failed to parse.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The offset of the error within the input, in characters.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// What the parser expected to find at [`offset`](Self::offset). Empty when the input was
+    /// not valid at this position, but no particular alternative was expected.
+    pub fn expected(&self) -> &[Expected] {
+        &self.expected
+    }
+
+    /// Whether the input was rejected for being nested more deeply than the parser allows,
+    /// rather than for a syntax error.
+    pub fn is_too_deep(&self) -> bool {
+        self.too_deep
+    }
+
+    /// The character found at the position of the error, or `None` if the input ended.
+    pub fn found(&self) -> Option<char> {
+        self.input.chars().nth(self.offset)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.kind {
+            ParseKind::Filter => "Invalid filter: ",
+            ParseKind::ValuePath => "Invalid path: ",
+            ParseKind::AttrPath => "Invalid attribute path: ",
+        })?;
+        match self.expected.split_last() {
+            _ if self.too_deep => write!(f, "nested more than {} levels deep", MAX_NESTING)?,
+            Some((last, [])) => write!(f, "expected {}", last)?,
+            Some((last, rest)) => {
+                f.write_str("expected ")?;
+                for (i, expected) in rest.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", expected)?;
+                }
+                write!(f, " or {}", last)?;
+            }
+            None => match self.found() {
+                Some(c) => write!(f, "unexpected {:?}", c)?,
+                None => f.write_str("unexpected end of input")?,
+            },
+        }
+        writeln!(f, " at offset {}", self.offset)?;
+        writeln!(f, "{}", self.input)?;
+        write!(f, "{:>1$}", "^", self.offset + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        let scim_type = match e.kind {
+            ParseKind::Filter => ErrorType::InvalidFilter,
+            ParseKind::ValuePath | ParseKind::AttrPath => ErrorType::InvalidPath,
+        };
+        Self::new(StatusCode::BAD_REQUEST, Some(scim_type), e.to_string())
+    }
+}
@@ -0,0 +1,247 @@
+use std::cmp::Ordering;
+
+use cream_core::{Attribute, Type};
+use ijson::{IObject, IValue};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{json::get_ignore_case, schema::SchemaRegistry};
+
+use super::{AttrPathRef, CompValueRef, CompareOp, FilterRef};
+
+// The element of a multi-valued attribute which is being matched against the inner filter of a
+// `Has` expression. Paths within that filter refer to sub-attributes of the element.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    parent: AttrPathRef<'a>,
+    element: &'a IValue,
+}
+
+fn same_attribute(a: AttrPathRef, b: AttrPathRef) -> bool {
+    a.name.eq_ignore_ascii_case(b.name)
+        && match (a.urn, b.urn) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (None, None) => true,
+            _ => false,
+        }
+}
+
+// Pushes a value onto `out`, flattening arrays and skipping nulls.
+fn push_values<'a>(value: &'a IValue, out: &mut Vec<&'a IValue>) {
+    if let Some(arr) = value.as_array() {
+        out.extend(arr.iter().filter(|value| !value.is_null()));
+    } else if !value.is_null() {
+        out.push(value);
+    }
+}
+
+// Collects all the values of an attribute within a resource.
+fn resolve<'a>(
+    resource: &'a IObject,
+    scope: Option<Scope<'a>>,
+    path: AttrPathRef,
+) -> Vec<&'a IValue> {
+    let mut values = Vec::new();
+    match scope {
+        Some(scope) if same_attribute(scope.parent, path) => {
+            push_values(scope.element, &mut values)
+        }
+        _ => {
+            let obj = match path.urn {
+                Some(urn) => get_ignore_case(resource, urn).and_then(IValue::as_object),
+                None => Some(resource),
+            };
+            if let Some(value) = obj.and_then(|obj| get_ignore_case(obj, path.name)) {
+                push_values(value, &mut values);
+            }
+        }
+    }
+    if let Some(sub_attr) = path.sub_attr {
+        let mut sub_values = Vec::new();
+        for value in values {
+            if let Some(value) = value
+                .as_object()
+                .and_then(|obj| get_ignore_case(obj, sub_attr))
+            {
+                push_values(value, &mut sub_values);
+            }
+        }
+        values = sub_values;
+    }
+    values
+}
+
+fn is_present(value: &IValue) -> bool {
+    if let Some(s) = value.as_string() {
+        !s.is_empty()
+    } else if let Some(obj) = value.as_object() {
+        obj.values().any(|value| !value.is_null())
+    } else {
+        true
+    }
+}
+
+// Compares two strings, ignoring case unless the attribute is case-exact.
+fn compare_strings(a: &str, b: &str, case_exact: bool) -> Ordering {
+    if case_exact {
+        a.cmp(b)
+    } else {
+        a.chars()
+            .flat_map(char::to_lowercase)
+            .cmp(b.chars().flat_map(char::to_lowercase))
+    }
+}
+
+fn fold_case(s: &str, case_exact: bool) -> String {
+    if case_exact {
+        s.to_string()
+    } else {
+        s.to_lowercase()
+    }
+}
+
+fn compare_date_times(a: &str, b: &str) -> Option<Ordering> {
+    let a = OffsetDateTime::parse(a, &Rfc3339).ok()?;
+    let b = OffsetDateTime::parse(b, &Rfc3339).ok()?;
+    Some(a.cmp(&b))
+}
+
+// Compares an attribute value with a literal from the filter. Returns `None` if the values are
+// not comparable.
+fn compare_value(
+    attr: Option<&Attribute>,
+    value: &IValue,
+    literal: CompValueRef,
+) -> Option<Ordering> {
+    match literal {
+        CompValueRef::Null => None,
+        CompValueRef::Bool(b) => value.to_bool().filter(|v| *v == b).map(|_| Ordering::Equal),
+        CompValueRef::Num(n) => value.as_number().map(|v| v.cmp(n)),
+        CompValueRef::DateTime(dt) => {
+            let value = OffsetDateTime::parse(value.as_string()?, &Rfc3339).ok()?;
+            Some(value.cmp(&dt.0))
+        }
+        CompValueRef::Str(s) => {
+            let value = value.as_string()?.as_str();
+            match attr.map(|attr| &attr.type_) {
+                Some(Type::DateTime) => compare_date_times(value, s),
+                _ => Some(compare_strings(
+                    value,
+                    s,
+                    attr.is_some_and(|attr| attr.case_exact),
+                )),
+            }
+        }
+    }
+}
+
+fn matches_value(
+    attr: Option<&Attribute>,
+    value: &IValue,
+    op: CompareOp,
+    literal: CompValueRef,
+) -> bool {
+    // A complex value is compared using its `value` sub-attribute.
+    let value = match value.as_object() {
+        Some(obj) => match get_ignore_case(obj, "value") {
+            Some(value) => value,
+            None => return false,
+        },
+        None => value,
+    };
+    match op {
+        CompareOp::Equal | CompareOp::NotEqual => {
+            compare_value(attr, value, literal) == Some(Ordering::Equal)
+        }
+        CompareOp::Contains | CompareOp::StartsWith | CompareOp::EndsWith => {
+            let (Some(value), CompValueRef::Str(literal)) = (value.as_string(), literal) else {
+                return false;
+            };
+            let case_exact = attr.is_some_and(|attr| attr.case_exact);
+            let value = fold_case(value, case_exact);
+            let literal = fold_case(literal, case_exact);
+            match op {
+                CompareOp::Contains => value.contains(&literal),
+                CompareOp::StartsWith => value.starts_with(&literal),
+                _ => value.ends_with(&literal),
+            }
+        }
+        CompareOp::GreaterThan => compare_value(attr, value, literal) == Some(Ordering::Greater),
+        CompareOp::GreaterThanOrEqual => matches!(
+            compare_value(attr, value, literal),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        CompareOp::LessThan => compare_value(attr, value, literal) == Some(Ordering::Less),
+        CompareOp::LessThanOrEqual => matches!(
+            compare_value(attr, value, literal),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+    }
+}
+
+fn matches_inner(
+    filter: &FilterRef,
+    resource: &IObject,
+    scope: Option<Scope>,
+    schemas: &SchemaRegistry,
+) -> bool {
+    match *filter {
+        FilterRef::Present(path) => resolve(resource, scope, path).into_iter().any(is_present),
+        FilterRef::Compare(path, op, literal) => {
+            let values = resolve(resource, scope, path);
+            let attr = schemas.comparison_attribute(path).map(|(_, attr)| attr);
+            match (op, literal) {
+                (CompareOp::Equal, CompValueRef::Null) => values.is_empty(),
+                (CompareOp::NotEqual, CompValueRef::Null) => !values.is_empty(),
+                (CompareOp::NotEqual, _) => !values
+                    .into_iter()
+                    .any(|value| matches_value(attr, value, CompareOp::Equal, literal)),
+                _ => values
+                    .into_iter()
+                    .any(|value| matches_value(attr, value, op, literal)),
+            }
+        }
+        FilterRef::Has(path, inner) => resolve(resource, scope, path).into_iter().any(|element| {
+            let scope = Scope {
+                parent: path,
+                element,
+            };
+            matches_inner(inner, resource, Some(scope), schemas)
+        }),
+        FilterRef::And(filters) => filters
+            .iter()
+            .all(|filter| matches_inner(filter, resource, scope, schemas)),
+        FilterRef::Or(filters) => filters
+            .iter()
+            .any(|filter| matches_inner(filter, resource, scope, schemas)),
+        FilterRef::Not(filter) => !matches_inner(filter, resource, scope, schemas),
+    }
+}
+
+impl FilterRef<'_> {
+    /// Determine whether a resource matches this filter, following the semantics of RFC 7644
+    /// Section 3.4.2.2. The schemas of the resource type are used to determine whether string
+    /// comparisons are case-sensitive, and which values should be compared as dates.
+    ///
+    /// Comparisons against multi-valued attributes match if any value matches, and comparisons
+    /// against complex attributes use their `value` sub-attribute. Values which cannot be
+    /// compared, such as a string and a number, never match.
+    pub fn matches(&self, resource: &IObject, schemas: &SchemaRegistry) -> bool {
+        matches_inner(self, resource, None, schemas)
+    }
+
+    /// Determine whether a single value of a multi-valued attribute matches this filter, as
+    /// in the value filter of `emails[type eq "work"]`. Paths within the filter refer to
+    /// sub-attributes of `parent`.
+    pub(crate) fn matches_value(
+        &self,
+        parent: AttrPathRef,
+        element: &IValue,
+        schemas: &SchemaRegistry,
+    ) -> bool {
+        let scope = Scope { parent, element };
+        matches_inner(self, &IObject::new(), Some(scope), schemas)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,96 @@
+use bumpalo::Bump;
+use ijson::{ijson, IObject};
+
+use crate::{filter::parse_filter, schema::test_registry};
+
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+fn resource() -> IObject {
+    ijson!({
+        "id": "2819c223",
+        "userName": "Bjensen",
+        "externalId": "Babs",
+        "active": true,
+        "loginCount": 12,
+        "lastLogin": "2024-03-01T12:00:00+01:00",
+        "name": { "familyName": "Jensen" },
+        "emails": [
+            { "value": "bjensen@example.com", "type": "work", "primary": true },
+            { "value": "babs@jensen.org", "type": "home" }
+        ],
+        "meta": { "resourceType": "User" },
+        ENTERPRISE: { "employeeNumber": "701984" }
+    })
+    .into_object()
+    .unwrap()
+}
+
+fn matches(filter: &str) -> bool {
+    let scope = Bump::new();
+    let filter = parse_filter(filter).unwrap();
+    filter.as_ref(&scope).matches(&resource(), &test_registry())
+}
+
+fn matches_coerced(filter: &str) -> bool {
+    let scope = Bump::new();
+    let registry = test_registry();
+    let mut filter = parse_filter(filter).unwrap();
+    filter.coerce(&registry).unwrap();
+    filter.as_ref(&scope).matches(&resource(), &registry)
+}
+
+#[test]
+fn test_matches() {
+    let cases = [
+        ("userName eq \"bjensen\"", true),
+        ("userName ne \"bjensen\"", false),
+        ("externalId eq \"babs\"", false),
+        ("externalId eq \"Babs\"", true),
+        ("userName sw \"BJ\"", true),
+        ("userName ew \"SEN\"", true),
+        ("userName co \"ense\"", true),
+        ("name.familyName co \"O\"", false),
+        ("active eq true", true),
+        ("active eq false", false),
+        ("loginCount gt 10", true),
+        ("loginCount le 11.5", false),
+        ("lastLogin gt \"2024-03-01T11:30:00Z\"", false),
+        ("lastLogin ge \"2024-03-01T11:00:00Z\"", true),
+        ("title pr", false),
+        ("title eq null", true),
+        ("userName ne null", true),
+        ("emails pr", true),
+        ("emails co \"example.com\"", true),
+        ("emails.type eq \"home\"", true),
+        ("emails[type eq \"work\" and value co \"@example.com\"]", true),
+        ("emails[type eq \"home\" and primary eq true]", false),
+        ("emails.type eq \"home\" and emails.primary eq true", true),
+        ("meta.resourceType eq \"User\"", true),
+        (
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:employeeNumber eq \"701984\"",
+            true,
+        ),
+        ("userName eq \"x\" or not (active eq false)", true),
+        ("loginCount eq \"12\"", false),
+    ];
+    for (filter, expected) in cases {
+        assert_eq!(matches(filter), expected, "{}", filter);
+    }
+}
+
+#[test]
+fn test_matches_coerced() {
+    let cases = [
+        ("loginCount eq \"12\"", true),
+        ("loginCount gt \"12\"", false),
+        ("active eq \"True\"", true),
+        ("emails[primary eq \"true\"]", true),
+        ("lastLogin gt \"2024-03-01T11:30:00Z\"", false),
+        ("lastLogin lt \"2024-03-01T12:30:00+01:00\"", true),
+        ("lastLogin eq \"2024-03-01T11:00:00Z\"", true),
+        ("lastLogin ne null", true),
+    ];
+    for (filter, expected) in cases {
+        assert_eq!(matches_coerced(filter), expected, "{}", filter);
+    }
+}
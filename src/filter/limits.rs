@@ -0,0 +1,145 @@
+use axum::http::StatusCode;
+use bumpalo::Bump;
+
+use crate::error::{Error, ErrorType};
+
+use super::{parse_filter, parse_value_path, Filter, FilterRef, ValuePath, ValuePathRef};
+
+/// Limits on the complexity of filters sent by clients.
+///
+/// Filters which exceed these limits are rejected before they reach a resource manager:
+/// overly long or deeply nested filters with an `invalidFilter` error, and filters with too many
+/// clauses or `or` branches with a `tooMany` error. The limits apply both to the `filter` query
+/// parameter and to the paths of PATCH operations. Set a limit to `usize::MAX` to disable it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterLimits {
+    /// The maximum length of a filter, in bytes. Defaults to 8192.
+    pub max_length: usize,
+    /// The maximum nesting depth of logical operators and value filters, where a single
+    /// attribute expression has a depth of 1. Defaults to 16.
+    ///
+    /// Regardless of this limit, the parser rejects filters with more than 64 levels of
+    /// parentheses and brackets.
+    pub max_depth: usize,
+    /// The maximum number of attribute expressions, such as `userName eq "bjensen"` or
+    /// `title pr`. Defaults to 256.
+    pub max_clauses: usize,
+    /// The maximum total number of operands of `or` operators. Defaults to 128.
+    pub max_or_branches: usize,
+}
+
+impl Default for FilterLimits {
+    fn default() -> Self {
+        Self {
+            max_length: 8192,
+            max_depth: 16,
+            max_clauses: 256,
+            max_or_branches: 128,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Complexity {
+    depth: usize,
+    clauses: usize,
+    or_branches: usize,
+}
+
+impl Complexity {
+    fn measure(filter: &FilterRef, depth: usize, result: &mut Self) {
+        result.depth = result.depth.max(depth);
+        match *filter {
+            FilterRef::Present(_) | FilterRef::Compare(..) => result.clauses += 1,
+            FilterRef::Has(_, filter) | FilterRef::Not(filter) => {
+                Self::measure(filter, depth + 1, result)
+            }
+            FilterRef::And(filters) | FilterRef::Or(filters) => {
+                if let FilterRef::Or(_) = filter {
+                    result.or_branches += filters.len();
+                }
+                for filter in filters {
+                    Self::measure(filter, depth + 1, result);
+                }
+            }
+        }
+    }
+}
+
+impl FilterLimits {
+    /// Parse a filter, checking that it is within these limits.
+    pub fn parse(&self, input: &str) -> Result<Filter, Error> {
+        if input.len() > self.max_length {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::InvalidFilter),
+                format!(
+                    "Filter is longer than the maximum of {} bytes",
+                    self.max_length
+                ),
+            ));
+        }
+        let filter = parse_filter(input)?;
+        let scope = Bump::new();
+        self.check(&filter.as_ref(&scope))?;
+        Ok(filter)
+    }
+
+    /// Parse the path of a PATCH operation, checking that it and its value filter, if any, are
+    /// within these limits.
+    pub fn parse_value_path(&self, input: &str) -> Result<ValuePath, Error> {
+        if input.len() > self.max_length {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::InvalidPath),
+                format!(
+                    "Path is longer than the maximum of {} bytes",
+                    self.max_length
+                ),
+            ));
+        }
+        let path = parse_value_path(input)?;
+        let scope = Bump::new();
+        if let ValuePathRef::Filtered(_, filter) = path.as_ref(&scope) {
+            self.check(&filter)?;
+        }
+        Ok(path)
+    }
+
+    /// Check that a filter is within these limits. The length of the filter is not checked.
+    pub fn check(&self, filter: &FilterRef) -> Result<(), Error> {
+        let mut complexity = Complexity::default();
+        Complexity::measure(filter, 1, &mut complexity);
+        if complexity.depth > self.max_depth {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::InvalidFilter),
+                format!(
+                    "Filter is nested more deeply than the maximum of {} levels",
+                    self.max_depth
+                ),
+            ));
+        }
+        if complexity.clauses > self.max_clauses {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::TooMany),
+                format!(
+                    "Filter has more than the maximum of {} clauses",
+                    self.max_clauses
+                ),
+            ));
+        }
+        if complexity.or_branches > self.max_or_branches {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::TooMany),
+                format!(
+                    "Filter has more than the maximum of {} `or` branches",
+                    self.max_or_branches
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use super::{CompareOp, Filter};
+
+impl Filter {
+    /// Rewrite this filter into a simpler, equivalent form, so that resource managers need to
+    /// handle fewer shapes of filter:
+    ///
+    /// - Nested `and` and `or` expressions are flattened, so that `(a and b) and c` becomes
+    ///   `a and b and c`.
+    /// - `not` is pushed inwards using De Morgan's laws, so that `not (a or b)` becomes
+    ///   `not (a) and not (b)`. Negated `eq` and `ne` comparisons are replaced with the opposite
+    ///   operator, and double negations are removed. Other negated expressions, including value
+    ///   filters such as `emails[type eq "work"]`, are left wrapped in `not`.
+    /// - Duplicate operands of `and` and `or` are removed.
+    pub fn normalize(self) -> Self {
+        normalize(self, false)
+    }
+}
+
+fn normalize(filter: Filter, negate: bool) -> Filter {
+    match filter {
+        Filter::Not(inner) => normalize(*inner, !negate),
+        // `and` becomes `or` when negated, and vice versa.
+        Filter::And(filters) => combine(
+            !negate,
+            filters.into_iter().map(|filter| normalize(filter, negate)),
+        ),
+        Filter::Or(filters) => combine(
+            negate,
+            filters.into_iter().map(|filter| normalize(filter, negate)),
+        ),
+        // A comparison with `ne` matches exactly those resources which the corresponding `eq`
+        // comparison does not, including when the attribute is missing or multi-valued.
+        Filter::Compare(path, CompareOp::Equal, value) if negate => {
+            Filter::Compare(path, CompareOp::NotEqual, value)
+        }
+        Filter::Compare(path, CompareOp::NotEqual, value) if negate => {
+            Filter::Compare(path, CompareOp::Equal, value)
+        }
+        Filter::Has(path, inner) => negated(
+            Filter::Has(path, Box::new(normalize(*inner, false))),
+            negate,
+        ),
+        filter => negated(filter, negate),
+    }
+}
+
+fn negated(filter: Filter, negate: bool) -> Filter {
+    if negate {
+        Filter::Not(Box::new(filter))
+    } else {
+        filter
+    }
+}
+
+// Combines filters with `and` or `or`, flattening operands of the same kind and removing
+// duplicates.
+fn combine(and: bool, filters: impl Iterator<Item = Filter>) -> Filter {
+    let mut operands = Vec::new();
+    let mut push = |filter: Filter| {
+        if !operands.contains(&filter) {
+            operands.push(filter);
+        }
+    };
+    for filter in filters {
+        match filter {
+            Filter::And(inner) if and => inner.into_iter().for_each(&mut push),
+            Filter::Or(inner) if !and => inner.into_iter().for_each(&mut push),
+            filter => push(filter),
+        }
+    }
+    if operands.len() == 1 {
+        operands.pop().expect("Already checked length")
+    } else if and {
+        Filter::And(operands)
+    } else {
+        Filter::Or(operands)
+    }
+}
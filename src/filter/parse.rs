@@ -1,86 +1,268 @@
+use std::iter;
+
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, tag, tag_no_case, take, take_while_m_n};
-use nom::character::complete::{alpha1, alphanumeric1, char, digit1, space0, space1};
-use nom::combinator::{map, map_opt, map_res, opt, recognize, value, verify};
-use nom::multi::{fold_many0, many0_count, many1, separated_list1};
-use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
-use nom::{IResult, Parser};
+use nom::bytes::complete::{is_not, tag, tag_no_case, take, take_while1, take_while_m_n};
+use nom::character::complete::{alpha1, char, digit0, digit1, one_of, satisfy, space0, space1};
+use nom::combinator::{cut, eof, map, map_opt, map_res, not, opt, recognize, value, verify};
+use nom::error::{ErrorKind, FromExternalError, ParseError as NomParseError};
+use nom::multi::{fold_many0, many0, many0_count, many1_count};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::{Finish, IResult, Parser};
 
 use super::*;
 
-pub fn filter(i: &str) -> IResult<&str, Filter> {
-    or_filter(i)
+// The grammar follows the ABNF in RFC 7644 section 3.4.2.2, with two relaxations: whitespace
+// is permitted inside parentheses and brackets, and `not` may be followed by whitespace.
+//
+// Paths within a value filter (`valFilter`) are relative to the multi-valued attribute, so they
+// cannot themselves contain a URN, a sub-attribute or a further value filter.
+//
+// Once the parser has seen enough of an expression to know what it must be, such as an opening
+// parenthesis or a comparison operator, it uses `cut` to stop backtracking, so that errors are
+// reported where the input went wrong rather than at the start of the expression.
+
+type PResult<'a, O> = IResult<&'a str, O, SyntaxError<'a>>;
+
+// The error produced by the parser. When several alternatives fail, the error which got
+// furthest through the input is kept, and the expectations of alternatives which failed at the
+// same position are combined.
+#[derive(Debug)]
+pub struct SyntaxError<'a> {
+    input: &'a str,
+    expected: Vec<Expected>,
 }
 
-pub fn attr_filter(i: &str) -> IResult<&str, Filter> {
-    alt((
-        map(
-            terminated(attr_path, pair(space1, tag_no_case("pr"))),
-            Filter::Present,
-        ),
-        map(
-            tuple((
-                terminated(attr_path, space1),
-                terminated(compare_op, space1),
-                comp_value,
-            )),
-            |(attr_path, compare_op, comp_value)| {
-                Filter::Compare(attr_path, compare_op, comp_value)
-            },
-        ),
-    ))(i)
+impl<'a> NomParseError<&'a str> for SyntaxError<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Self {
+            input,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(mut self, other: Self) -> Self {
+        match self.input.len().cmp(&other.input.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal => {
+                for expected in other.expected {
+                    if !self.expected.contains(&expected) {
+                        self.expected.push(expected);
+                    }
+                }
+                self
+            }
+        }
+    }
 }
 
-pub fn or_filter(i: &str) -> IResult<&str, Filter> {
+impl<'a, E> FromExternalError<&'a str, E> for SyntaxError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: E) -> Self {
+        Self::from_error_kind(input, kind)
+    }
+}
+
+// Records what was expected if `parser` fails without consuming any input.
+fn expect<'a, O>(
+    expected: &'static [Expected],
+    mut parser: impl FnMut(&'a str) -> PResult<'a, O>,
+) -> impl FnMut(&'a str) -> PResult<'a, O> {
+    move |i| {
+        parser(i).map_err(|e| {
+            e.map(|mut e| {
+                if e.input.len() == i.len() {
+                    e.expected = expected.to_vec();
+                }
+                e
+            })
+        })
+    }
+}
+
+/// The maximum nesting of parentheses and brackets. The parser is recursive, so more deeply
+/// nested input is rejected before parsing to avoid exhausting the stack.
+pub const MAX_NESTING: usize = 64;
+
+// Returns the byte offset of the first parenthesis or bracket which exceeds `MAX_NESTING`.
+fn check_nesting(input: &str) -> Result<(), usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, c) in input.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' => {
+                depth += 1;
+                if depth > MAX_NESTING {
+                    return Err(offset);
+                }
+            }
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parse the whole of `input`, which may be followed by one of `follow` where it ends early.
+pub fn parse_complete<'a, O>(
+    input: &'a str,
+    kind: ParseKind,
+    parser: impl FnMut(&'a str) -> PResult<'a, O>,
+    follow: &'static [Expected],
+) -> Result<O, ParseError> {
+    let error = |offset: usize, expected, too_deep| ParseError {
+        kind,
+        input: input.into(),
+        offset: input[..offset].chars().count(),
+        expected,
+        too_deep,
+    };
+    check_nesting(input).map_err(|offset| error(offset, Vec::new(), true))?;
+    terminated(parser, expect(follow, eof))(input)
+        .finish()
+        .map(|(_, output)| output)
+        .map_err(|e| error(input.len() - e.input.len(), e.expected, false))
+}
+
+pub fn filter(i: &str) -> PResult<'_, Filter> {
+    or_filter(i, false)
+}
+
+fn val_filter(i: &str) -> PResult<'_, Filter> {
+    or_filter(i, true)
+}
+
+// A logical operator, which must be followed by another expression.
+fn logical_op<'a>(
+    op: &'static str,
+    operand: impl FnMut(&'a str) -> PResult<'a, Filter>,
+) -> impl FnMut(&'a str) -> PResult<'a, Filter> {
+    preceded(
+        tuple((space1, tag_no_case(op), not(satisfy(is_name_char)))),
+        // At the end of the input, report the missing operand rather than the missing space.
+        cut(preceded(alt((space1, eof)), operand)),
+    )
+}
+
+fn or_filter(i: &str, nested: bool) -> PResult<'_, Filter> {
     map(
-        separated_list1(tuple((space1, tag_no_case("or"), space1)), and_filter),
-        |clauses| {
-            if clauses.len() == 1 {
-                clauses.into_iter().next().expect("Already checked length")
+        pair(
+            |i| and_filter(i, nested),
+            many0(logical_op("or", |i| and_filter(i, nested))),
+        ),
+        |(first, rest)| {
+            if rest.is_empty() {
+                first
             } else {
-                Filter::Or(clauses)
+                Filter::Or(iter::once(first).chain(rest).collect())
             }
         },
     )(i)
 }
 
-pub fn and_filter(i: &str) -> IResult<&str, Filter> {
+fn and_filter(i: &str, nested: bool) -> PResult<'_, Filter> {
     map(
-        separated_list1(tuple((space1, tag_no_case("and"), space1)), group_filter),
-        |clauses| {
-            if clauses.len() == 1 {
-                clauses.into_iter().next().expect("Already checked length")
+        pair(
+            |i| group_filter(i, nested),
+            many0(logical_op("and", |i| group_filter(i, nested))),
+        ),
+        |(first, rest)| {
+            if rest.is_empty() {
+                first
             } else {
-                Filter::And(clauses)
+                Filter::And(iter::once(first).chain(rest).collect())
             }
         },
     )(i)
 }
 
-pub fn group_filter(i: &str) -> IResult<&str, Filter> {
-    alt((
+fn group_filter(i: &str, nested: bool) -> PResult<'_, Filter> {
+    let parenthesised = |i| {
         map(
-            separated_pair(
-                map(opt(tag_no_case("not")), |not| not.is_some()),
-                space0,
-                delimited(char('('), filter, char(')')),
+            pair(
+                opt(terminated(tag_no_case("not"), space0)),
+                preceded(
+                    char('('),
+                    cut(delimited(
+                        space0,
+                        |i| or_filter(i, nested),
+                        preceded(
+                            space0,
+                            expect(
+                                &[Expected::LogicalOperator, Expected::CloseParen],
+                                char(')'),
+                            ),
+                        ),
+                    )),
+                ),
             ),
             |(not, filter)| {
-                if not {
+                if not.is_some() {
                     Filter::Not(Box::new(filter))
                 } else {
                     filter
                 }
             },
-        ),
-        attr_filter,
-        has_filter,
-    ))(i)
+        )(i)
+    };
+    if nested {
+        expect(
+            &[Expected::Filter],
+            alt((parenthesised, |i| attr_filter(i, relative_attr_path))),
+        )(i)
+    } else {
+        expect(
+            &[Expected::Filter],
+            alt((parenthesised, |i| attr_filter(i, attr_path), has_filter)),
+        )(i)
+    }
 }
 
-pub fn has_filter(i: &str) -> IResult<&str, Filter> {
+fn attr_filter<'a>(
+    i: &'a str,
+    path: impl FnMut(&'a str) -> PResult<'a, AttrPath>,
+) -> PResult<'a, Filter> {
+    let (i, attr_path) = terminated(path, expect(&[Expected::Operator], space1))(i)?;
+    let (i, comparison) = cut(expect(
+        &[Expected::Operator],
+        alt((
+            value(
+                None,
+                terminated(tag_no_case("pr"), not(satisfy(is_name_char))),
+            ),
+            map(
+                pair(
+                    compare_op,
+                    preceded(alt((space1, eof)), expect(&[Expected::Value], comp_value)),
+                ),
+                Some,
+            ),
+        )),
+    ))(i)?;
+    let filter = match comparison {
+        None => Filter::Present(attr_path),
+        Some((compare_op, comp_value)) => Filter::Compare(attr_path, compare_op, comp_value),
+    };
+    Ok((i, filter))
+}
+
+fn has_filter(i: &str) -> PResult<'_, Filter> {
     map(
-        pair(attr_path, delimited(char('['), filter, char(']'))),
+        pair(parent_attr_path, value_filter),
         |(attr_path, mut filter)| {
             AttrPathPrefixer { parent: &attr_path }.visit_filter(&mut filter);
             Filter::Has(attr_path, Box::new(filter))
@@ -88,38 +270,39 @@ pub fn has_filter(i: &str) -> IResult<&str, Filter> {
     )(i)
 }
 
-struct AttrPathPrefixer<'a> {
-    parent: &'a AttrPath,
-}
-
-impl Visitor for AttrPathPrefixer<'_> {
-    fn visit_attr_path(&mut self, attr_path: &mut AttrPath) {
-        attr_path.sub_attr = Some(attr_path.name.clone());
-        attr_path.name = self.parent.name.clone();
-        attr_path.urn = self.parent.urn.clone();
-    }
+fn value_filter(i: &str) -> PResult<'_, Filter> {
+    preceded(
+        char('['),
+        cut(delimited(
+            space0,
+            val_filter,
+            preceded(
+                space0,
+                expect(
+                    &[Expected::LogicalOperator, Expected::CloseBracket],
+                    char(']'),
+                ),
+            ),
+        )),
+    )(i)
 }
 
-pub fn value_path(i: &str) -> IResult<&str, ValuePath> {
-    map(
-        pair(
-            attr_path,
-            opt(pair(delimited(char('['), filter, char(']')), opt(sub_attr))),
-        ),
-        |(mut attr_path, filter)| {
-            if let Some((mut filter, sub_attr)) = filter {
+pub fn value_path(i: &str) -> PResult<'_, ValuePath> {
+    alt((
+        map(
+            tuple((parent_attr_path, value_filter, opt(sub_attr))),
+            |(mut attr_path, mut filter, sub_attr)| {
                 // Prefix all attributes inside the filter with the parent attribute
                 AttrPathPrefixer { parent: &attr_path }.visit_filter(&mut filter);
                 attr_path.sub_attr = sub_attr;
                 ValuePath::Filtered(attr_path, filter)
-            } else {
-                ValuePath::Attr(attr_path)
-            }
-        },
-    )(i)
+            },
+        ),
+        map(attr_path, ValuePath::Attr),
+    ))(i)
 }
 
-pub fn attr_path(i: &str) -> IResult<&str, AttrPath> {
+pub fn attr_path(i: &str) -> PResult<'_, AttrPath> {
     map(
         tuple((opt(urn), attr_name, opt(sub_attr))),
         |(urn, name, sub_attr)| AttrPath {
@@ -130,55 +313,87 @@ pub fn attr_path(i: &str) -> IResult<&str, AttrPath> {
     )(i)
 }
 
-pub fn urn(i: &str) -> IResult<&str, String> {
+// The attribute to which a value filter is applied, which cannot have a sub-attribute.
+fn parent_attr_path(i: &str) -> PResult<'_, AttrPath> {
+    map(pair(opt(urn), attr_name), |(urn, name)| AttrPath {
+        urn,
+        name,
+        sub_attr: None,
+    })(i)
+}
+
+fn relative_attr_path(i: &str) -> PResult<'_, AttrPath> {
+    map(attr_name, |name| AttrPath {
+        urn: None,
+        name,
+        sub_attr: None,
+    })(i)
+}
+
+// A URN consists of colon-separated segments, which may contain any of the characters
+// permitted in the namespace-specific string of RFC 8141 other than `/`, `?` and `#`.
+fn urn(i: &str) -> PResult<'_, String> {
     map(
-        many1(terminated(many1(alt((alphanumeric1, tag(".")))), tag(":"))),
-        |namespaces| {
-            let uri: String = namespaces
-                .into_iter()
-                .fold("".to_string(), |acc, namespace| {
-                    acc + &namespace.join("") + ":"
-                });
-
-            uri[0..uri.len() - 1].to_string()
-        },
+        recognize(many1_count(terminated(urn_segment, char(':')))),
+        |urn: &str| urn[..urn.len() - 1].to_string(),
     )(i)
 }
 
-pub fn compare_op(i: &str) -> IResult<&str, CompareOp> {
+fn urn_segment(i: &str) -> PResult<'_, &str> {
+    take_while1(|c: char| c.is_ascii_alphanumeric() || "-._~!$&'*+,;=@%".contains(c))(i)
+}
+
+fn compare_op(i: &str) -> PResult<'_, CompareOp> {
     map_res(take(2usize), CompareOp::from_str)(i)
 }
 
-pub fn comp_value(i: &str) -> IResult<&str, CompValue> {
+fn comp_value(i: &str) -> PResult<'_, CompValue> {
     alt((
         value(CompValue::Null, tag("null")),
         value(CompValue::Bool(false), tag("false")),
         value(CompValue::Bool(true), tag("true")),
-        map(
-            map_res(many1(alt((digit1, tag(".")))), |digit| {
-                serde_json::from_str(&digit.join(""))
-            }),
-            CompValue::Num,
-        ),
+        map(map_res(number, serde_json::from_str), CompValue::Num),
         map(parse_string, CompValue::Str),
     ))(i)
 }
 
-pub fn attr_name(i: &str) -> IResult<&str, String> {
-    map(
-        recognize(pair(
-            alpha1,
-            many0_count(alt((alphanumeric1, tag("_"), tag("-")))),
+// A number in the syntax of RFC 7159 section 6.
+fn number(i: &str) -> PResult<'_, &str> {
+    recognize(tuple((
+        opt(char('-')),
+        alt((
+            tag("0"),
+            recognize(pair(satisfy(|c| matches!(c, '1'..='9')), digit0)),
         )),
-        Into::into,
+        opt(pair(char('.'), digit1)),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(i)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+// Attribute names are an ALPHA followed by letters, digits, `-` or `_`. The `$ref`
+// sub-attribute defined by RFC 7643 is the only exception.
+fn attr_name(i: &str) -> PResult<'_, String> {
+    expect(
+        &[Expected::AttrName],
+        map(
+            alt((
+                recognize(pair(alpha1, many0_count(satisfy(is_name_char)))),
+                tag_no_case("$ref"),
+            )),
+            Into::into,
+        ),
     )(i)
 }
 
-pub fn sub_attr(i: &str) -> IResult<&str, String> {
-    preceded(char('.'), attr_name)(i)
+fn sub_attr(i: &str) -> PResult<'_, String> {
+    preceded(char('.'), cut(attr_name))(i)
 }
 
-fn parse_unicode(input: &str) -> IResult<&str, char>
+fn parse_unicode(input: &str) -> PResult<'_, char>
 where
 {
     let parse_hex = preceded(
@@ -190,7 +405,7 @@ where
 }
 
 /// Parse an escaped character: \n, \t, \r, \u00AC, etc.
-fn parse_escaped_char(input: &str) -> IResult<&str, char> {
+fn parse_escaped_char(input: &str) -> PResult<'_, char> {
     preceded(
         char('\\'),
         alt((
@@ -209,7 +424,7 @@ fn parse_escaped_char(input: &str) -> IResult<&str, char> {
 }
 
 /// Parse a non-empty block of text that doesn't include \ or "
-fn parse_literal(input: &str) -> IResult<&str, &str> {
+fn parse_literal(input: &str) -> PResult<'_, &str> {
     let not_quote_slash = is_not("\"\\");
     verify(not_quote_slash, |s: &str| !s.is_empty()).parse(input)
 }
@@ -225,7 +440,7 @@ enum StringFragment<'a> {
 
 /// Combine parse_literal, parse_escaped_whitespace, and parse_escaped_char
 /// into a StringFragment.
-fn parse_fragment(input: &str) -> IResult<&str, StringFragment> {
+fn parse_fragment(input: &str) -> PResult<'_, StringFragment<'_>> {
     alt((
         map(parse_literal, StringFragment::Literal),
         map(parse_escaped_char, StringFragment::EscapedChar),
@@ -235,7 +450,7 @@ fn parse_fragment(input: &str) -> IResult<&str, StringFragment> {
 
 /// Parse a string. Use a loop of parse_fragment and push all of the fragments
 /// into an output string.
-fn parse_string(input: &str) -> IResult<&str, String> {
+fn parse_string(input: &str) -> PResult<'_, String> {
     let build_string = fold_many0(parse_fragment, String::new, |mut string, fragment| {
         match fragment {
             StringFragment::Literal(s) => string.push_str(s),
@@ -243,5 +458,10 @@ fn parse_string(input: &str) -> IResult<&str, String> {
         }
         string
     });
-    delimited(char('"'), build_string, char('"')).parse(input)
+    delimited(
+        char('"'),
+        build_string,
+        expect(&[Expected::CloseQuote], char('"')),
+    )
+    .parse(input)
 }
@@ -0,0 +1,115 @@
+use bumpalo::Bump;
+
+use super::FilterRef;
+
+/// A filter split into a part which a resource manager can evaluate itself, and a residual
+/// part which must be evaluated afterwards. A resource matches the original filter exactly
+/// when it matches both parts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitFilter<'a> {
+    /// The part of the filter to push down to the store. It matches every resource which the
+    /// original filter matches, and possibly more. `None` if nothing can be pushed down, in
+    /// which case every resource must be considered.
+    pub pushdown: Option<FilterRef<'a>>,
+    /// The part of the filter to apply to the resources matched by `pushdown`. `None` if the
+    /// pushdown part is exact.
+    pub residual: Option<FilterRef<'a>>,
+}
+
+// Combines operands with `and` or `or`, unwrapping a single operand.
+fn combine<'a>(
+    scope: &'a Bump,
+    filters: Vec<FilterRef<'a>>,
+    make: fn(&'a [FilterRef<'a>]) -> FilterRef<'a>,
+) -> Option<FilterRef<'a>> {
+    match filters.len() {
+        0 => None,
+        1 => filters.into_iter().next(),
+        _ => Some(make(scope.alloc_slice_copy(&filters))),
+    }
+}
+
+impl<'a> FilterRef<'a> {
+    /// Split this filter so that as much of it as possible can be pushed down to a store.
+    ///
+    /// `can_push_down` is called with attribute expressions and value filters, such as
+    /// `userName eq "bjensen"` or `emails[type eq "work"]`, and should return whether the store
+    /// can evaluate them. Operands of `and` are split individually. An `or` is pushed down
+    /// as a whole if every branch can be, and otherwise as the `or` of whatever can be pushed
+    /// down from each branch, leaving the whole `or` in the residual. A `not` is only pushed
+    /// down if its operand can be pushed down entirely.
+    pub fn split(
+        &self,
+        scope: &'a Bump,
+        can_push_down: impl Fn(&FilterRef) -> bool,
+    ) -> SplitFilter<'a> {
+        self.split_inner(scope, &can_push_down)
+    }
+
+    fn split_inner(
+        &self,
+        scope: &'a Bump,
+        can_push_down: &dyn Fn(&FilterRef) -> bool,
+    ) -> SplitFilter<'a> {
+        let exact = SplitFilter {
+            pushdown: Some(*self),
+            residual: None,
+        };
+        let residual = SplitFilter {
+            pushdown: None,
+            residual: Some(*self),
+        };
+        match *self {
+            Self::Present(_) | Self::Compare(..) | Self::Has(..) => {
+                if can_push_down(self) {
+                    exact
+                } else {
+                    residual
+                }
+            }
+            Self::Not(filter) => match filter.split_inner(scope, can_push_down) {
+                SplitFilter { residual: None, .. } => exact,
+                _ => residual,
+            },
+            Self::And(filters) => {
+                let mut pushdown = Vec::new();
+                let mut residual = Vec::new();
+                for filter in filters {
+                    let split = filter.split_inner(scope, can_push_down);
+                    pushdown.extend(split.pushdown);
+                    residual.extend(split.residual);
+                }
+                if residual.is_empty() {
+                    return exact;
+                }
+                SplitFilter {
+                    pushdown: combine(scope, pushdown, Self::And),
+                    residual: combine(scope, residual, Self::And),
+                }
+            }
+            Self::Or(filters) => {
+                let mut pushdown = Vec::new();
+                let mut is_exact = true;
+                for filter in filters {
+                    let split = filter.split_inner(scope, can_push_down);
+                    is_exact &= split.residual.is_none();
+                    // A branch with nothing to push down could match any resource.
+                    let Some(filter) = split.pushdown else {
+                        return residual;
+                    };
+                    pushdown.push(filter);
+                }
+                if is_exact {
+                    return exact;
+                }
+                SplitFilter {
+                    pushdown: combine(scope, pushdown, Self::Or),
+                    residual: Some(*self),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,83 @@
+use bumpalo::Bump;
+
+use crate::filter::{parse_filter, FilterRef};
+
+// Everything can be pushed down except filters on `nickName`.
+fn can_push_down(filter: &FilterRef) -> bool {
+    match *filter {
+        FilterRef::Present(path) | FilterRef::Compare(path, _, _) | FilterRef::Has(path, _) => {
+            !path.name.eq_ignore_ascii_case("nickName")
+        }
+        _ => unreachable!("Called with {}", filter),
+    }
+}
+
+fn split(input: &str) -> (Option<String>, Option<String>) {
+    let scope = Bump::new();
+    let filter = parse_filter(input).unwrap();
+    let split = filter.as_ref(&scope).split(&scope, can_push_down);
+    (
+        split.pushdown.map(|filter| filter.to_string()),
+        split.residual.map(|filter| filter.to_string()),
+    )
+}
+
+#[test]
+fn test_split() {
+    let cases = [
+        (
+            r#"userName eq "bjensen""#,
+            Some(r#"userName eq "bjensen""#),
+            None,
+        ),
+        (r#"nickName eq "Babs""#, None, Some(r#"nickName eq "Babs""#)),
+        (
+            r#"userName eq "bjensen" and nickName eq "Babs""#,
+            Some(r#"userName eq "bjensen""#),
+            Some(r#"nickName eq "Babs""#),
+        ),
+        (
+            r#"userName eq "bjensen" and active eq true and nickName pr"#,
+            Some(r#"userName eq "bjensen" and active eq true"#),
+            Some("nickName pr"),
+        ),
+        (
+            r#"userName eq "bjensen" or active eq true"#,
+            Some(r#"userName eq "bjensen" or active eq true"#),
+            None,
+        ),
+        (
+            r#"userName eq "bjensen" or nickName eq "Babs""#,
+            None,
+            Some(r#"userName eq "bjensen" or nickName eq "Babs""#),
+        ),
+        (
+            r#"(userName eq "bjensen" and nickName eq "Babs") or active eq true"#,
+            Some(r#"userName eq "bjensen" or active eq true"#),
+            Some(r#"userName eq "bjensen" and nickName eq "Babs" or active eq true"#),
+        ),
+        (
+            r#"not (userName eq "bjensen")"#,
+            Some(r#"not (userName eq "bjensen")"#),
+            None,
+        ),
+        (
+            r#"not (userName eq "bjensen" and nickName eq "Babs")"#,
+            None,
+            Some(r#"not (userName eq "bjensen" and nickName eq "Babs")"#),
+        ),
+        (
+            r#"emails[type eq "work"] and nickName pr"#,
+            Some(r#"emails[type eq "work"]"#),
+            Some("nickName pr"),
+        ),
+    ];
+    for (input, pushdown, residual) in cases {
+        assert_eq!(
+            split(input),
+            (pushdown.map(Into::into), residual.map(Into::into)),
+            "{}",
+            input
+        );
+    }
+}
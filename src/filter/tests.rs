@@ -1,9 +1,13 @@
 use bumpalo::Bump;
 
-use crate::filter::FilterRef;
+use crate::{
+    error::{Error, ErrorType},
+    filter::FilterRef,
+};
 
 use super::{
-    parse_attr_path, parse_filter, parse_value_path, prelude::*, AttrPathRef, ValuePathRef,
+    attr, parse_attr_path, parse_filter, parse_value_path, prelude::*, AttrPathRef, CompValue,
+    Expected, Filter, FilterLimits, ParseKind, ValuePathRef,
 };
 
 const USER_EMAILS: AttrPathRef = AttrPathRef {
@@ -108,3 +112,458 @@ fn test_value_path_parsing() {
         ),
     );
 }
+
+#[test]
+fn test_builder() {
+    fn validate(filter: Filter, expected: &str) {
+        assert_eq!(filter, parse_filter(expected).unwrap());
+    }
+
+    validate(attr("userName").eq("bjensen"), "userName eq \"bjensen\"");
+    validate(
+        attr("name.formatted").pr().and(attr("loginCount").gt(3)),
+        "name.formatted pr and loginCount gt 3",
+    );
+    validate(
+        attr("title").eq(None::<&str>).or(!attr("active").eq(true)),
+        "title eq null or not (active eq true)",
+    );
+    validate(
+        attr("emails").has(
+            attr("type")
+                .eq("work")
+                .and(attr("value").co("@example.com")),
+        ),
+        "emails[type eq \"work\" and value co \"@example.com\"]",
+    );
+    validate(
+        attr("urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.displayName")
+            .sw("J"),
+        "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.displayName sw \"J\"",
+    );
+    assert_eq!(CompValue::from(f64::NAN), CompValue::Null);
+}
+
+#[test]
+fn test_to_owned() {
+    let scope = Bump::new();
+    let input = "emails[type eq \"work\"] and not (userName sw \"J\" or loginCount le 1.5)";
+    let filter = parse_filter(input).unwrap();
+    assert_eq!(filter.as_ref(&scope).to_owned(), filter);
+
+    let path = parse_value_path("emails[type eq \"work\"].value").unwrap();
+    assert_eq!(path.as_ref(&scope).to_owned(), path);
+
+    let json = serde_json::to_value(&filter).unwrap();
+    assert_eq!(serde_json::from_value::<Filter>(json).unwrap(), filter);
+    assert_eq!(
+        serde_json::to_value(attr("name.formatted").pr()).unwrap(),
+        serde_json::json!({ "present": { "name": "name", "subAttr": "formatted" } })
+    );
+}
+
+#[test]
+fn test_grammar() {
+    // Each accepted filter, and its canonical form.
+    let accepted = [
+        // Examples from RFC 7644 section 3.4.2.2.
+        ("userName Eq \"john\"", "userName eq \"john\""),
+        ("Username eq \"john\"", "Username eq \"john\""),
+        ("userName eq \"bjensen\"", "userName eq \"bjensen\""),
+        ("name.familyName co \"O'Malley\"", "name.familyName co \"O'Malley\""),
+        ("userName sw \"J\"", "userName sw \"J\""),
+        (
+            "urn:ietf:params:scim:schemas:core:2.0:User:userName sw \"J\"",
+            "urn:ietf:params:scim:schemas:core:2.0:User:userName sw \"J\"",
+        ),
+        ("title pr", "title pr"),
+        (
+            "meta.lastModified gt \"2011-05-13T04:42:34Z\"",
+            "meta.lastModified gt \"2011-05-13T04:42:34Z\"",
+        ),
+        (
+            "title pr and userType eq \"Employee\"",
+            "title pr and userType eq \"Employee\"",
+        ),
+        (
+            "title pr or userType eq \"Intern\"",
+            "title pr or userType eq \"Intern\"",
+        ),
+        (
+            "schemas eq \"urn:ietf:params:scim:schemas:extension:enterprise:2.0:User\"",
+            "schemas eq \"urn:ietf:params:scim:schemas:extension:enterprise:2.0:User\"",
+        ),
+        (
+            "userType eq \"Employee\" and (emails co \"example.com\" or emails.value co \"example.org\")",
+            "userType eq \"Employee\" and (emails co \"example.com\" or emails.value co \"example.org\")",
+        ),
+        (
+            "userType ne \"Employee\" and not (emails co \"example.com\" or emails.value co \"example.org\")",
+            "userType ne \"Employee\" and not (emails co \"example.com\" or emails.value co \"example.org\")",
+        ),
+        (
+            "userType eq \"Employee\" and (emails.type eq \"work\")",
+            "userType eq \"Employee\" and emails.type eq \"work\"",
+        ),
+        (
+            "userType eq \"Employee\" and emails[type eq \"work\" and value co \"@example.com\"]",
+            "userType eq \"Employee\" and emails[type eq \"work\" and value co \"@example.com\"]",
+        ),
+        (
+            "emails[type eq \"work\" and value co \"@example.com\"] or ims[type eq \"xmpp\" and value co \"@foo.com\"]",
+            "emails[type eq \"work\" and value co \"@example.com\"] or ims[type eq \"xmpp\" and value co \"@foo.com\"]",
+        ),
+        // Keywords are case-insensitive.
+        ("title PR", "title pr"),
+        ("title pr AND nickName pr Or NOT (active eq true)", "title pr and nickName pr or not (active eq true)"),
+        // Numbers.
+        ("loginCount eq 0", "loginCount eq 0"),
+        ("loginCount gt -3", "loginCount gt -3"),
+        ("score lt -0.5", "score lt -0.5"),
+        ("score ge 1.25e2", "score ge 125.0"),
+        ("score le 5E-1", "score le 0.5"),
+        ("score eq 2e+3", "score eq 2000.0"),
+        // Literals.
+        ("active eq true", "active eq true"),
+        ("active ne false", "active ne false"),
+        ("manager eq null", "manager eq null"),
+        ("displayName eq \"\"", "displayName eq \"\""),
+        ("displayName eq \"tab\\tquote\\\"\\u00e9\"", "displayName eq \"tab\\tquote\\\"é\""),
+        // Attribute names.
+        ("x-custom_attr1 pr", "x-custom_attr1 pr"),
+        ("members[$ref eq \"https://example.com/Users/1\"]", "members[$ref eq \"https://example.com/Users/1\"]"),
+        ("members.$ref pr", "members.$ref pr"),
+        // URNs.
+        ("urn:acme-corp:scim:schemas:ext:1.0:User:costCenter eq \"42\"", "urn:acme-corp:scim:schemas:ext:1.0:User:costCenter eq \"42\""),
+        (
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value eq \"26118915-6090-4610-87e4-49d8ca9f808d\"",
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value eq \"26118915-6090-4610-87e4-49d8ca9f808d\"",
+        ),
+        (
+            "urn:acme:ext:User:badges[name sw \"gold\"]",
+            "urn:acme:ext:User:badges[name sw \"gold\"]",
+        ),
+        // Grouping and whitespace.
+        ("not(title pr)", "not (title pr)"),
+        ("not  (title pr)", "not (title pr)"),
+        ("( title pr )", "title pr"),
+        ("((title pr))", "title pr"),
+        ("title  pr", "title pr"),
+        ("title   eq   \"a\"", "title eq \"a\""),
+        ("title pr  and  nickName pr", "title pr and nickName pr"),
+        ("emails[ type eq \"work\" ]", "emails[type eq \"work\"]"),
+        ("emails[not(type eq \"work\")]", "emails[not (type eq \"work\")]"),
+        ("emails[(type eq \"work\" or type eq \"home\") and primary eq true]", "emails[(type eq \"work\" or type eq \"home\") and primary eq true]"),
+        ("notes pr", "notes pr"),
+        ("not pr", "not pr"),
+        ("order eq 1 or andrew pr", "order eq 1 or andrew pr"),
+    ];
+    for (input, expected) in accepted {
+        let filter = parse_filter(input).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(filter.to_string(), expected, "{}", input);
+    }
+
+    let rejected = [
+        "",
+        "title",
+        "title pr extra",
+        "title eq",
+        "title xx \"a\"",
+        "title eq \"unterminated",
+        "title eq 'single'",
+        "title eq True",
+        "title eq 01",
+        "title eq 1.",
+        "title eq .5",
+        "title eq 1e",
+        "title eq +1",
+        "title eq --1",
+        "title pr and",
+        "and title pr",
+        "title pr andnickName pr",
+        "not title pr",
+        "(title pr",
+        "title pr)",
+        "emails[type eq \"work\"",
+        "emails[]",
+        "emails.value[type eq \"work\"]",
+        "emails[type[value pr]]",
+        "emails[urn:acme:ext:type eq \"work\"]",
+        "emails[type.value eq \"work\"]",
+        "1title pr",
+        "_title pr",
+        "$title pr",
+        "urn:acme:ext:User: pr",
+        "urn::User:title pr",
+        "name..familyName pr",
+        "name.familyName.extra pr",
+    ];
+    for input in rejected {
+        assert!(parse_filter(input).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn test_value_path_grammar() {
+    let accepted = [
+        ("members", "members"),
+        ("name.familyName", "name.familyName"),
+        (
+            "members[value eq \"2819c223\"]",
+            "members[value eq \"2819c223\"]",
+        ),
+        (
+            "members[value eq \"2819c223\"].display",
+            "members[value eq \"2819c223\"].display",
+        ),
+        ("members[$ref pr].$ref", "members[$ref pr].$ref"),
+        (
+            "emails[ type eq \"work\" and not(primary eq false) ].value",
+            "emails[type eq \"work\" and not (primary eq false)].value",
+        ),
+        (
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value",
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value",
+        ),
+        (
+            "urn:acme-corp:ext:User:badges[level ge -1.5e1].name",
+            "urn:acme-corp:ext:User:badges[level ge -15.0].name",
+        ),
+    ];
+    for (input, expected) in accepted {
+        let path = parse_value_path(input).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(path.to_string(), expected, "{}", input);
+    }
+
+    let scope = Bump::new();
+    let path = parse_value_path("members[$ref pr].display").unwrap();
+    assert_eq!(
+        path.as_ref(&scope),
+        Filtered(
+            AttrPathRef {
+                urn: None,
+                name: "members",
+                sub_attr: Some("display"),
+            },
+            Present(AttrPathRef {
+                urn: None,
+                name: "members",
+                sub_attr: Some("$ref"),
+            }),
+        )
+    );
+
+    let rejected = [
+        "",
+        "emails[type eq \"work\"].value.extra",
+        "emails[type eq \"work\"][value pr]",
+        "emails.value[type eq \"work\"]",
+        "emails[type eq \"work\"]value",
+        "title pr",
+    ];
+    for input in rejected {
+        assert!(parse_value_path(input).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    let cases: &[(&str, usize, &[Expected])] = &[
+        ("", 0, &[Expected::Filter]),
+        ("userName is \"bjensen\"", 9, &[Expected::Operator]),
+        ("title", 5, &[Expected::Operator]),
+        ("title eq", 8, &[Expected::Value]),
+        ("title eq True", 9, &[Expected::Value]),
+        ("title eq \"abc", 13, &[Expected::CloseQuote]),
+        ("title pr and", 12, &[Expected::Filter]),
+        (
+            "title pr xyz",
+            8,
+            &[Expected::LogicalOperator, Expected::End],
+        ),
+        (
+            "(title pr xyz)",
+            10,
+            &[Expected::LogicalOperator, Expected::CloseParen],
+        ),
+        (
+            "emails[type eq \"work\"",
+            21,
+            &[Expected::LogicalOperator, Expected::CloseBracket],
+        ),
+        ("emails[type.value eq 1]", 11, &[Expected::Operator]),
+        ("name. pr", 5, &[Expected::AttrName]),
+        ("urn:acme:User: pr", 14, &[Expected::AttrName]),
+        ("title eq -", 10, &[]),
+        (
+            "displayName eq \"é\" xyz",
+            18,
+            &[Expected::LogicalOperator, Expected::End],
+        ),
+    ];
+    for &(input, offset, expected) in cases {
+        let e = parse_filter(input).unwrap_err();
+        assert_eq!((e.offset(), e.expected()), (offset, expected), "{}", input);
+    }
+
+    let e = parse_filter("userName is \"bjensen\"").unwrap_err();
+    assert_eq!(e.input(), "userName is \"bjensen\"");
+    assert_eq!(e.found(), Some('i'));
+    assert!(!e.is_too_deep());
+    let e = Error::from(e);
+    assert!(matches!(e.scim_type(), Some(ErrorType::InvalidFilter)));
+    assert_eq!(
+        e.detail(),
+        "Invalid filter: expected `pr` or a comparison operator at offset 9\n\
+         userName is \"bjensen\"\n\
+         \x20        ^"
+    );
+
+    let e = parse_filter("title pr and").unwrap_err();
+    assert_eq!(e.kind(), ParseKind::Filter);
+    assert_eq!(
+        e.to_string(),
+        "Invalid filter: expected an attribute path, `not` or `(` at offset 12\n\
+         title pr and\n\
+         \x20           ^"
+    );
+
+    let e = parse_value_path("emails[type eq \"work\"]value").unwrap_err();
+    assert_eq!(e.kind(), ParseKind::ValuePath);
+    assert_eq!((e.offset(), e.expected()), (22, &[Expected::End][..]));
+    let e = parse_attr_path("emails..x").unwrap_err();
+    assert_eq!(e.kind(), ParseKind::AttrPath);
+    assert_eq!(
+        e.to_string(),
+        "Invalid attribute path: expected an attribute name at offset 7\nemails..x\n       ^"
+    );
+    let e = Error::from(e);
+    assert!(matches!(e.scim_type(), Some(ErrorType::InvalidPath)));
+}
+
+#[test]
+fn test_limits() {
+    let limits = FilterLimits {
+        max_length: 100,
+        max_depth: 4,
+        max_clauses: 4,
+        max_or_branches: 3,
+    };
+    let check = |input: &str| limits.parse(input).map_err(|e| e.scim_type().cloned());
+
+    assert!(check("title pr and (userType eq \"Employee\" or emails[type eq \"work\"])").is_ok());
+    assert!(check("a pr or b pr or c pr").is_ok());
+    assert!(check("((((a pr))))").is_ok());
+    assert!(matches!(
+        check(&format!("displayName eq \"{}\"", "x".repeat(100))),
+        Err(Some(ErrorType::InvalidFilter))
+    ));
+    assert!(matches!(
+        check("a pr and (b pr or not (c pr and d pr))"),
+        Err(Some(ErrorType::InvalidFilter))
+    ));
+    assert!(matches!(
+        check("a pr and b pr and c pr and d pr and e pr"),
+        Err(Some(ErrorType::TooMany))
+    ));
+    assert!(matches!(
+        check("(a pr or b pr) and (c pr or d pr)"),
+        Err(Some(ErrorType::TooMany))
+    ));
+
+    let check = |input: &str| {
+        limits
+            .parse_value_path(input)
+            .map_err(|e| e.scim_type().cloned())
+    };
+    assert!(check("emails[type eq \"work\" or primary eq true].value").is_ok());
+    assert!(matches!(
+        check(&format!("name.{}", "x".repeat(100))),
+        Err(Some(ErrorType::InvalidPath))
+    ));
+    assert!(matches!(
+        check("emails[a pr or b pr or c pr or d pr]"),
+        Err(Some(ErrorType::TooMany))
+    ));
+    assert!(matches!(
+        check("emails[a pr and (b pr or not (c pr and d pr))]"),
+        Err(Some(ErrorType::InvalidFilter))
+    ));
+
+    // Deeply nested input is rejected without exhausting the stack.
+    let nested = |depth: usize| format!("{}a pr{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(parse_filter(&nested(64)).is_ok());
+    let e = parse_filter(&nested(100_000)).unwrap_err();
+    assert!(e.is_too_deep());
+    assert_eq!(e.offset(), 64);
+    assert!(e
+        .to_string()
+        .starts_with("Invalid filter: nested more than 64 levels deep"));
+    let e = parse_value_path(&format!("emails[{}]", nested(100))).unwrap_err();
+    assert!(e.is_too_deep());
+    assert_eq!(e.offset(), 70);
+    assert!(parse_filter(&format!("a eq \"{}\"", "(".repeat(100))).is_ok());
+}
+
+#[test]
+fn test_normalize() {
+    let cases = [
+        ("title pr", "title pr"),
+        (
+            "(a pr and b pr) and (c pr and (d pr))",
+            "a pr and b pr and c pr and d pr",
+        ),
+        (
+            "a pr or (b pr or c pr) or (d pr and e pr)",
+            "a pr or b pr or c pr or d pr and e pr",
+        ),
+        ("not (a eq 1)", "a ne 1"),
+        ("not (a ne null)", "a eq null"),
+        ("not (not (a gt 1))", "a gt 1"),
+        ("not (a gt 1)", "not (a gt 1)"),
+        ("not (a eq 1 or b pr)", "a ne 1 and not (b pr)"),
+        (
+            "not (a eq 1 and (b eq 2 or not (c eq 3)))",
+            "a ne 1 or b ne 2 and c eq 3",
+        ),
+        (
+            "not ((a pr and b pr) or c pr)",
+            "(not (a pr) or not (b pr)) and not (c pr)",
+        ),
+        ("a pr and b pr and a pr", "a pr and b pr"),
+        ("a eq 1 or not (a ne 1)", "a eq 1"),
+        (
+            "(a pr or b pr) and (b pr or a pr)",
+            "(a pr or b pr) and (b pr or a pr)",
+        ),
+        (
+            "not (emails[not (type eq \"work\") and value pr])",
+            "not (emails[type ne \"work\" and value pr])",
+        ),
+    ];
+    for (input, expected) in cases {
+        let filter = parse_filter(input).unwrap().normalize();
+        assert_eq!(filter.to_string(), expected, "{}", input);
+        assert_eq!(filter, parse_filter(expected).unwrap(), "{}", input);
+    }
+}
+
+#[test]
+fn test_iter_dnf() {
+    let scope = Bump::new();
+    let filter = parse_filter("a eq 1 or (b eq 2 or c eq 3) or (d pr and e pr)")
+        .unwrap()
+        .normalize();
+    let alternatives: Vec<_> = filter
+        .as_ref(&scope)
+        .iter_dnf()
+        .map(|filter| filter.to_string())
+        .collect();
+    assert_eq!(
+        alternatives,
+        ["a eq 1", "b eq 2", "c eq 3", "d pr and e pr"]
+    );
+
+    let filter = parse_filter("a eq 1 and b eq 2").unwrap();
+    assert_eq!(filter.as_ref(&scope).iter_dnf().count(), 1);
+}
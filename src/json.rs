@@ -4,6 +4,7 @@ use axum::{
     response::IntoResponse,
 };
 use bytes::{BufMut, BytesMut};
+use ijson::{IObject, IValue};
 use serde::Serialize;
 
 use crate::error::Error;
@@ -37,3 +38,12 @@ impl<T: Serialize> IntoResponse for Json<T> {
         }
     }
 }
+
+/// Looks up a key in an object, ignoring case. SCIM attribute names are case-insensitive.
+pub(crate) fn get_ignore_case<'a>(obj: &'a IObject, key: &str) -> Option<&'a IValue> {
+    obj.get(key).or_else(|| {
+        obj.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    })
+}
@@ -0,0 +1,301 @@
+//! Translation of SCIM filters into LDAP search filters (RFC 4515).
+//!
+//! Resource managers backed by an LDAP directory, such as Active Directory, can use
+//! [`LdapTranslator`] to turn the filter passed to
+//! [`GenericResourceManager::list`](crate::GenericResourceManager::list) into the filter
+//! string of a search request.
+
+use std::fmt::{self, Display, Formatter, Write};
+
+use axum::http::StatusCode;
+use cream_core::DateTime;
+use time::UtcOffset;
+
+use crate::{
+    error::{Error, ErrorType},
+    filter::{AttrPathRef, CompValueRef, CompareOp, FilterRef},
+    schema::SchemaRegistry,
+};
+
+/// A filter which cannot be translated into an LDAP filter.
+///
+/// It converts into an [`Error`] with the `invalidFilter` type, so that a resource manager
+/// can return it from `list` with `?`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum LdapError {
+    /// The attribute is not mapped to an LDAP attribute.
+    UnmappedAttribute {
+        /// The path of the attribute, as it appeared in the filter.
+        path: String,
+    },
+    /// The comparison has no LDAP equivalent, such as `sw` with a number, or `gt` with a
+    /// boolean.
+    UnsupportedComparison {
+        /// The path of the attribute being compared.
+        path: String,
+        /// The comparison operator.
+        op: CompareOp,
+        /// The comparison value, formatted as in a SCIM filter.
+        value: String,
+    },
+    /// A value filter on a multi-valued attribute refers to more than one of its
+    /// sub-attributes, such as `emails[type eq "work" and value co "@example.com"]`. LDAP
+    /// stores each sub-attribute separately, so there is no way to require that the
+    /// conditions hold for the same value.
+    UncorrelatedValueFilter {
+        /// The path of the multi-valued attribute.
+        path: String,
+    },
+}
+
+impl Display for LdapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnmappedAttribute { path } => {
+                write!(f, "Filtering on `{}` is not supported", path)
+            }
+            Self::UnsupportedComparison { path, op, value } => {
+                write!(f, "Cannot compare `{}` with {} using `{}`", path, value, op)
+            }
+            Self::UncorrelatedValueFilter { path } => write!(
+                f,
+                "Value filters on `{}` may only refer to a single sub-attribute",
+                path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LdapError {}
+
+impl From<LdapError> for Error {
+    fn from(e: LdapError) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::InvalidFilter),
+            e.to_string(),
+        )
+    }
+}
+
+/// Translates filters into LDAP search filters, using a mapping from attributes to LDAP
+/// attribute descriptions.
+///
+/// Attributes are mapped by a function which is called with each attribute path found in the
+/// filter, such as `userName` or `name.familyName`. A complex attribute compared without a
+/// sub-attribute, such as `emails`, is mapped as its `value` sub-attribute. Filters referring
+/// to attributes which are not mapped are rejected with [`LdapError::UnmappedAttribute`].
+///
+/// The comparison operators translate as follows:
+///
+/// | SCIM            | LDAP                                 |
+/// |-----------------|--------------------------------------|
+/// | `attr pr`       | `(attr=*)`                           |
+/// | `attr eq "x"`   | `(attr=x)`                           |
+/// | `attr ne "x"`   | `(!(attr=x))`                        |
+/// | `attr sw "x"`   | `(attr=x*)`                          |
+/// | `attr ew "x"`   | `(attr=*x)`                          |
+/// | `attr co "x"`   | `(attr=*x*)`                         |
+/// | `attr ge "x"`   | `(attr>=x)`                          |
+/// | `attr le "x"`   | `(attr<=x)`                          |
+/// | `attr gt "x"`   | `(&(attr>=x)(!(attr=x)))`            |
+/// | `attr lt "x"`   | `(&(attr<=x)(!(attr=x)))`            |
+///
+/// Comparisons with `null` are presence tests. Booleans are written as `TRUE` and `FALSE`, and
+/// dates as UTC generalized times such as `20110513044234Z`. Case sensitivity and ordering
+/// are determined by the matching rules of the LDAP attributes.
+pub struct LdapTranslator<'a> {
+    schemas: &'a SchemaRegistry,
+    attributes: Box<dyn Fn(AttrPathRef) -> Option<String> + 'a>,
+}
+
+// Escapes the special characters of an assertion value (RFC 4515 Section 3).
+fn escape_value(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '*' | '(' | ')' | '\\' | '\0' => {
+                write!(out, "\\{:02x}", c as u32).expect("Infallible");
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+// Formats a date as a generalized time (RFC 4517 Section 3.3.13), in UTC.
+fn generalized_time(dt: DateTime) -> String {
+    let dt = dt.0.to_offset(UtcOffset::UTC);
+    let mut result = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        dt.year(),
+        u8::from(dt.month()),
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second()
+    );
+    if dt.nanosecond() != 0 {
+        let fraction = format!("{:09}", dt.nanosecond());
+        write!(result, ".{}", fraction.trim_end_matches('0')).expect("Infallible");
+    }
+    result.push('Z');
+    result
+}
+
+// Collects the distinct sub-attributes referred to by the filter of a value filter.
+fn sub_attributes<'a>(filter: &FilterRef<'a>, out: &mut Vec<&'a str>) {
+    match *filter {
+        FilterRef::Present(path) | FilterRef::Compare(path, _, _) => {
+            let sub_attr = path.sub_attr.unwrap_or("value");
+            if !out.iter().any(|name| name.eq_ignore_ascii_case(sub_attr)) {
+                out.push(sub_attr);
+            }
+        }
+        FilterRef::Has(_, filter) | FilterRef::Not(filter) => sub_attributes(filter, out),
+        FilterRef::And(filters) | FilterRef::Or(filters) => {
+            for filter in filters {
+                sub_attributes(filter, out);
+            }
+        }
+    }
+}
+
+impl<'a> LdapTranslator<'a> {
+    /// Construct a translator for resources with the given schemas. `attributes` returns the
+    /// LDAP attribute description for an attribute, such as `sn` for `name.familyName`, or
+    /// `None` if it cannot be filtered on.
+    pub fn new(
+        schemas: &'a SchemaRegistry,
+        attributes: impl Fn(AttrPathRef) -> Option<String> + 'a,
+    ) -> Self {
+        Self {
+            schemas,
+            attributes: Box::new(attributes),
+        }
+    }
+
+    fn resolve(&self, path: AttrPathRef) -> Result<String, LdapError> {
+        let resolved = match self.schemas.comparison_attribute(path) {
+            Some((resolved, _)) => resolved,
+            None => path,
+        };
+        (self.attributes)(resolved).ok_or_else(|| LdapError::UnmappedAttribute {
+            path: path.to_string(),
+        })
+    }
+
+    fn write_compare(
+        &self,
+        out: &mut String,
+        path: AttrPathRef,
+        op: CompareOp,
+        literal: CompValueRef,
+    ) -> Result<(), LdapError> {
+        let attr = self.resolve(path)?;
+        let unsupported = || LdapError::UnsupportedComparison {
+            path: path.to_string(),
+            op,
+            value: literal.to_string(),
+        };
+        let value = match literal {
+            CompValueRef::Null => {
+                match op {
+                    CompareOp::Equal => write!(out, "(!({}=*))", attr),
+                    CompareOp::NotEqual => write!(out, "({}=*)", attr),
+                    _ => return Err(unsupported()),
+                }
+                .expect("Infallible");
+                return Ok(());
+            }
+            CompValueRef::Bool(b) => {
+                if !matches!(op, CompareOp::Equal | CompareOp::NotEqual) {
+                    return Err(unsupported());
+                }
+                (if b { "TRUE" } else { "FALSE" }).to_string()
+            }
+            CompValueRef::Num(_) => literal.to_string(),
+            CompValueRef::Str(s) => s.to_string(),
+            CompValueRef::DateTime(dt) => generalized_time(dt),
+        };
+        let mut escaped = String::with_capacity(value.len());
+        escape_value(&value, &mut escaped);
+        match op {
+            CompareOp::Equal => write!(out, "({}={})", attr, escaped),
+            CompareOp::NotEqual => write!(out, "(!({}={}))", attr, escaped),
+            CompareOp::Contains | CompareOp::StartsWith | CompareOp::EndsWith => {
+                if !matches!(literal, CompValueRef::Str(_)) {
+                    return Err(unsupported());
+                }
+                // Every string starts with, ends with and contains the empty string, but an
+                // empty substring is not valid in LDAP.
+                if escaped.is_empty() {
+                    write!(out, "({}=*)", attr)
+                } else {
+                    match op {
+                        CompareOp::Contains => write!(out, "({}=*{}*)", attr, escaped),
+                        CompareOp::StartsWith => write!(out, "({}={}*)", attr, escaped),
+                        _ => write!(out, "({}=*{})", attr, escaped),
+                    }
+                }
+            }
+            CompareOp::GreaterThanOrEqual => write!(out, "({}>={})", attr, escaped),
+            CompareOp::LessThanOrEqual => write!(out, "({}<={})", attr, escaped),
+            // LDAP has no strict ordering filters, so exclude equal values.
+            CompareOp::GreaterThan => write!(out, "(&({0}>={1})(!({0}={1})))", attr, escaped),
+            CompareOp::LessThan => write!(out, "(&({0}<={1})(!({0}={1})))", attr, escaped),
+        }
+        .expect("Infallible");
+        Ok(())
+    }
+
+    fn write_filter(&self, out: &mut String, filter: &FilterRef) -> Result<(), LdapError> {
+        match *filter {
+            FilterRef::Present(path) => {
+                write!(out, "({}=*)", self.resolve(path)?).expect("Infallible");
+            }
+            FilterRef::Compare(path, op, literal) => self.write_compare(out, path, op, literal)?,
+            FilterRef::Has(path, inner) => {
+                let multi_valued = self
+                    .schemas
+                    .attribute(path)
+                    .is_some_and(|attr| attr.multi_valued);
+                if multi_valued {
+                    let mut names = Vec::new();
+                    sub_attributes(inner, &mut names);
+                    if names.len() > 1 {
+                        return Err(LdapError::UncorrelatedValueFilter {
+                            path: path.to_string(),
+                        });
+                    }
+                }
+                self.write_filter(out, inner)?;
+            }
+            FilterRef::And(filters) | FilterRef::Or(filters) => {
+                out.push_str(match filter {
+                    FilterRef::And(_) => "(&",
+                    _ => "(|",
+                });
+                for filter in filters {
+                    self.write_filter(out, filter)?;
+                }
+                out.push(')');
+            }
+            FilterRef::Not(inner) => {
+                out.push_str("(!");
+                self.write_filter(out, inner)?;
+                out.push(')');
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate a filter into an LDAP search filter.
+    pub fn filter(&self, filter: &FilterRef) -> Result<String, LdapError> {
+        let mut out = String::new();
+        self.write_filter(&mut out, filter)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,140 @@
+use bumpalo::Bump;
+
+use crate::{
+    error::ErrorType,
+    filter::{parse_filter, AttrPathRef, CompareOp},
+    schema::test_registry,
+    Error,
+};
+
+use super::{LdapError, LdapTranslator};
+
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+fn attributes(path: AttrPathRef) -> Option<String> {
+    let attr = match (path.urn, path.name, path.sub_attr) {
+        (None, "userName", None) => "sAMAccountName",
+        (None, "active", None) => "enabled",
+        (None, "loginCount", None) => "logonCount",
+        (None, "meta", Some("created")) => "whenCreated",
+        (None, "name", Some("givenName")) => "givenName",
+        (None, "name", Some("familyName")) => "sn",
+        (None, "emails", Some("value")) => "mail",
+        (None, "emails", Some("type")) => "mailType",
+        (Some(ENTERPRISE), "employeeNumber", None) => "employeeID",
+        _ => return None,
+    };
+    Some(attr.to_string())
+}
+
+fn translate(input: &str) -> Result<String, LdapError> {
+    let scope = Bump::new();
+    let registry = test_registry();
+    let mut filter = parse_filter(input).unwrap();
+    filter.coerce(&registry).unwrap();
+    let translator = LdapTranslator::new(&registry, attributes);
+    translator.filter(&filter.as_ref(&scope))
+}
+
+#[test]
+fn test_filter() {
+    let cases = [
+        ("userName pr", "(sAMAccountName=*)"),
+        (r#"userName eq "bjensen""#, "(sAMAccountName=bjensen)"),
+        (r#"userName ne "bjensen""#, "(!(sAMAccountName=bjensen))"),
+        (r#"userName sw "bj""#, "(sAMAccountName=bj*)"),
+        (r#"userName ew "sen""#, "(sAMAccountName=*sen)"),
+        (r#"userName co "jen""#, "(sAMAccountName=*jen*)"),
+        (r#"userName co """#, "(sAMAccountName=*)"),
+        ("userName eq null", "(!(sAMAccountName=*))"),
+        ("userName ne null", "(sAMAccountName=*)"),
+        ("loginCount ge 5", "(logonCount>=5)"),
+        ("loginCount le 5", "(logonCount<=5)"),
+        ("loginCount gt 5", "(&(logonCount>=5)(!(logonCount=5)))"),
+        ("loginCount lt \"5\"", "(&(logonCount<=5)(!(logonCount=5)))"),
+        ("active eq true", "(enabled=TRUE)"),
+        ("active ne \"false\"", "(!(enabled=FALSE))"),
+        (
+            r#"meta.created ge "2011-05-13T06:42:34.5+02:00""#,
+            "(whenCreated>=20110513044234.5Z)",
+        ),
+        (
+            r#"meta.created lt "2011-05-13T04:42:34Z""#,
+            "(&(whenCreated<=20110513044234Z)(!(whenCreated=20110513044234Z)))",
+        ),
+        (
+            r#"name.familyName eq "O'Brien" and (name.givenName sw "J" or not (active eq true))"#,
+            "(&(sn=O'Brien)(|(givenName=J*)(!(enabled=TRUE))))",
+        ),
+        (r#"emails co "@example.com""#, "(mail=*@example.com*)"),
+        (r#"emails[type eq "work"]"#, "(mailType=work)"),
+        (
+            r#"name[givenName eq "Barbara" and familyName eq "Jensen"]"#,
+            "(&(givenName=Barbara)(sn=Jensen))",
+        ),
+        (
+            r#"urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:employeeNumber eq "701984""#,
+            "(employeeID=701984)",
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(translate(input).unwrap(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(
+        translate(r#"userName eq "a*b(c)d\\e""#).unwrap(),
+        r"(sAMAccountName=a\2ab\28c\29d\5ce)"
+    );
+    assert_eq!(
+        translate(r#"userName sw "*""#).unwrap(),
+        r"(sAMAccountName=\2a*)"
+    );
+    assert_eq!(
+        translate(r#"userName eq "\u0000é""#).unwrap(),
+        "(sAMAccountName=\\00é)"
+    );
+}
+
+#[test]
+fn test_errors() {
+    let cases = [
+        (
+            r#"displayName eq "Babs""#,
+            LdapError::UnmappedAttribute {
+                path: "displayName".to_string(),
+            },
+        ),
+        (
+            "loginCount sw 5",
+            LdapError::UnsupportedComparison {
+                path: "loginCount".to_string(),
+                op: CompareOp::StartsWith,
+                value: "5".to_string(),
+            },
+        ),
+        (
+            "active gt false",
+            LdapError::UnsupportedComparison {
+                path: "active".to_string(),
+                op: CompareOp::GreaterThan,
+                value: "false".to_string(),
+            },
+        ),
+        (
+            r#"emails[type eq "work" and value co "@example.com"]"#,
+            LdapError::UncorrelatedValueFilter {
+                path: "emails".to_string(),
+            },
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(translate(input), Err(expected), "{}", input);
+    }
+
+    let e = Error::from(translate("loginCount sw 5").unwrap_err());
+    assert!(matches!(e.scim_type, Some(ErrorType::InvalidFilter)));
+    assert_eq!(e.detail, "Cannot compare `loginCount` with 5 using `sw`");
+}
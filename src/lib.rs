@@ -23,7 +23,8 @@
 //! SCIM is a complex and underspecified standard, and Cream aims to hide some of this complexity from the user:
 //!
 //! - Many parts of SCIM are case-insensitive, but some are case-sensitive. Cream uses your schema to normalize the casing on
-//!   attributes, schema IDs and filters, so that your application can expect a consistent casing.
+//!   attributes, schema IDs and filters, so that your application can expect a consistent casing. Paths in filters, `sortBy`
+//!   and `PATCH` operations which don't match the schema are rejected with an `invalidFilter` or `invalidPath` error.
 //!
 //! - SCIM provides many ways to do the same thing. For example, you can search for resources of a particular type via a `GET`
 //!   request with query parameters, via a `POST` request with a filter in the body, or by a `POST` to the SCIM base URL with a
@@ -31,48 +32,105 @@
 //!
 //! - SCIM filters are complicated to parse, and may be arbitrarily complicated. Cream handles the parsing and translates them
 //!   into Rust-native types which can be directly pattern-matched. This allows you to abstract away subtle differences in the
-//!   way different SCIM clients may filter for resources.
+//!   way different SCIM clients may filter for resources. Filters are also normalized, flattening nested `and` and `or`
+//!   expressions and pushing `not` inwards, so there are fewer shapes of filter to handle. Comparison values are converted to
+//!   the types of their attributes, so that a date arrives as a `DateTime` and `active eq "true"` as a boolean.
+//!   Resource managers can also declare which filters they support, and Cream rejects any others with a precise error.
+//!   Alternatively, a resource manager can evaluate only the parts of a filter its store supports, leaving Cream to apply
+//!   the rest before paginating the results.
+//!
+//! - Resources are often stored under different names than their SCIM attributes. An `AttributeMapping` lets Cream
+//!   rewrite the paths in filters, sort orders, attribute lists and `PATCH` operations into your backend's field names,
+//!   converting values along the way, so that each resource manager doesn't need its own translation table.
 //!
 //! - SCIM clients can request that some fields be excluded whilst other fields are included. Cream hides this complexity by
 //!   giving you a single list of "optional" fields that are to be included along with the required fields which are always
 //!   present.
 //!
-//! Cream supports all aspects of the SCIM v2 standard, with the exception of these optional endpoints:
+//! - SCIM clients may batch many operations into a single request to the `/Bulk` endpoint. Cream splits these up and
+//!   dispatches them to your resource managers individually, resolving any `bulkId` references between operations.
+//!
+//! - SCIM `PATCH` requests have intricate semantics, particularly for multi-valued attributes and value filters such as
+//!   `emails[type eq "work"]`. Resource managers which store whole resources can apply updates using `apply_update`.
+//!
+//! Cream supports all aspects of the SCIM v2 standard, including these optional endpoints:
+//! - `/Bulk`
+//!
+//!   Enabled when the service provider configuration indicates that bulk operations are supported.
+//!
 //! - `/Me`
-//!    
+//!
 //!   This endpoint only makes sense when the SCIM client authenticates as a specific user, which is not part of the typical
-//!   SCIM use-case.
+//!   SCIM use-case. Enable it by providing a `SubjectResolver` to the `CreamBuilder`.
 //!
-//! - `/Bulk`
+//! # Features
 //!
-//!   This endpoint is not yet implemented, but may be added in future.
+//! - `memory`: Provides `MemoryStore`, a resource manager which keeps resources in memory. Useful for testing, or as a
+//!   reference when implementing your own resource managers.
+//! - `sql`: Provides the `sql` module, which translates filters and sort orders into SQL for resource managers backed by a
+//!   relational database.
+//! - `ldap`: Provides the `ldap` module, which translates filters into LDAP search filters for resource managers backed
+//!   by a directory.
+//! - `opensearch`: Provides the `opensearch` module, which translates filters, sort orders and pagination into the query
+//!   DSL of OpenSearch and Elasticsearch.
+//! - `client`: Provides `Client`, an async SCIM client which can talk to any SCIM service provider via a `tower::Service`,
+//!   including the router of a `Cream` instance.
 //!
 #![deny(missing_docs)]
 
 mod builder;
+mod bulk;
+#[cfg(feature = "client")]
+mod client;
 mod config;
 mod error;
 /// Functionality relating to SCIM filters.
 pub mod filter;
 mod json;
+#[cfg(feature = "ldap")]
+pub mod ldap;
 mod list;
 mod manager;
+mod mapping;
+#[cfg(any(test, feature = "memory"))]
+mod memory;
 mod meta;
+#[cfg(feature = "opensearch")]
+pub mod opensearch;
+mod patch;
 mod router;
 mod schema;
+#[cfg(feature = "sql")]
+pub mod sql;
 mod state;
+mod subject;
 
 pub use builder::CreamBuilder;
+#[cfg(feature = "client")]
+pub use client::{Client, ListQuery, PatchOp};
+pub use config::{
+    PaginationMethod, ServiceProviderConfig, ServiceProviderConfigAuthenticationScheme,
+    ServiceProviderConfigBulk, ServiceProviderConfigChangePassword, ServiceProviderConfigEtag,
+    ServiceProviderConfigFilter, ServiceProviderConfigPagination, ServiceProviderConfigPatch,
+    ServiceProviderConfigSort,
+};
 pub use cream_core::*;
 pub use cream_macros::*;
 pub use error::{Error, ErrorType};
 pub use filter::AttrPathRef;
+pub use list::ListResponse;
 pub use manager::{
     GenericResourceManager, GetResourceArgs, ListResourceArgs, ListResourceResult, SortOrder,
     UpdateOp, UpdateResourceArgs, UpdateResourceItem,
 };
+pub use mapping::AttributeMapping;
+#[cfg(feature = "memory")]
+pub use memory::MemoryStore;
 pub use meta::{META_CREATED, META_LAST_MODIFIED, META_RESOURCE_TYPE, META_VERSION};
+pub use patch::apply_update;
+pub use schema::SchemaRegistry;
 pub use state::Cream;
+pub use subject::{Subject, SubjectResolver};
 
 #[doc(hidden)]
 pub mod hidden {
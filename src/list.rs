@@ -1,17 +1,32 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use cream_core::declare_schema;
 
 declare_schema!(ListResponseSchema = "urn:ietf:params:scim:api:messages:2.0:ListResponse");
 
-#[derive(Serialize, Debug, Clone)]
+/// A page of resources returned from a list or search request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ListResponse<T> {
+    /// ["urn:ietf:params:scim:api:messages:2.0:ListResponse"]
+    #[serde(skip_deserializing)]
     pub schemas: [ListResponseSchema; 1],
+    /// The total number of results matching the query.
     pub total_results: usize,
-    pub start_index: usize,
+    /// The 1-based index of the first result in this page. Omitted for cursor-based pagination.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<usize>,
+    /// The number of results returned in this page.
+    #[serde(default)]
     pub items_per_page: usize,
-    #[serde(rename = "Resources")]
+    /// A cursor identifying the next page of results, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// A cursor identifying the previous page of results, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_cursor: Option<String>,
+    /// The resources in this page.
+    #[serde(rename = "Resources", default = "Vec::new")]
     pub resources: Vec<T>,
 }
 
@@ -20,8 +35,10 @@ impl<T> Default for ListResponse<T> {
         Self {
             schemas: Default::default(),
             total_results: Default::default(),
-            start_index: 1,
+            start_index: Some(1),
             items_per_page: 100,
+            next_cursor: None,
+            previous_cursor: None,
             resources: Default::default(),
         }
     }
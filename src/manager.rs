@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     error::Error,
-    filter::{AttrPathRef, FilterRef, ValuePathRef},
+    filter::{AttrPathRef, FilterCapabilities, FilterRef, ValuePathRef},
 };
 
 /// A trait for managing a generic resource. Implemented automatically by the `define_resource` macro.
@@ -33,19 +33,50 @@ pub trait GenericResourceManager: Debug + Send + Sync + 'static {
         parts: &'async_trait Parts,
         args: UpdateResourceArgs<'async_trait>,
     ) -> Result<(), Error>;
-    /// Replace a resource.
+    /// Replace a resource. If `version` is specified, the resource should only be replaced
+    /// if its current version matches.
     async fn replace(
         &self,
         parts: &'async_trait Parts,
         id: &str,
         resource: IObject,
+        version: Option<&'async_trait str>,
     ) -> Result<(), Error>;
-    /// Delete a resource by ID.
-    async fn delete(&self, parts: &'async_trait Parts, id: &str) -> Result<(), Error>;
-    /// Get the default page size for this resource type.
+    /// Delete a resource by ID. If `version` is specified, the resource should only be deleted
+    /// if its current version matches.
+    async fn delete(
+        &self,
+        parts: &'async_trait Parts,
+        id: &str,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error>;
+    /// Get the default page size for this resource type. Ignored if the service provider
+    /// config specifies a `defaultPageSize`.
     fn default_page_size(&self) -> usize {
         50
     }
+    /// Get the maximum page size for this resource type. Larger requests are capped to this
+    /// size, in addition to the `maxResults` limit from the service provider config.
+    fn max_page_size(&self) -> usize {
+        usize::MAX
+    }
+    /// Get the filters supported by `list`, or `None` if any filter may be passed to it.
+    /// Filters which are not supported are rejected before `list` is called.
+    fn filter_capabilities(&self) -> Option<FilterCapabilities> {
+        None
+    }
+    /// Determine whether `list` can evaluate part of a filter itself. Called with attribute
+    /// expressions and value filters, such as `userName eq "bjensen"`.
+    ///
+    /// Filters are [split](FilterRef::split) into the part which can be pushed down, which is
+    /// passed to `list`, and a residual part, which is applied to the resources it returns.
+    /// In that case `list` is called repeatedly to fetch every matching resource, so that
+    /// pagination and `totalResults` account for the residual part, and the resources must
+    /// include any attributes the residual part refers to.
+    fn can_push_down(&self, filter: &FilterRef) -> bool {
+        let _ = filter;
+        true
+    }
 
     // Reflection
     /// Load the resource type for this manager.
@@ -56,7 +87,7 @@ pub trait GenericResourceManager: Debug + Send + Sync + 'static {
 
 /// Arguments for listing resources.
 #[non_exhaustive]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ListResourceArgs<'a> {
     /// Filter to apply to the resources.
     pub filter: Option<FilterRef<'a>>,
@@ -64,12 +95,16 @@ pub struct ListResourceArgs<'a> {
     pub sort_by: Option<AttrPathRef<'a>>,
     /// Sort order.
     pub sort_order: SortOrder,
-    /// Index of the first resource to return (zero-indexed).
+    /// Index of the first resource to return (zero-indexed). Always zero when `cursor` is set.
     pub start_index: usize,
     /// Number of resources to return.
     pub count: usize,
     /// Additional attributes to include in the response.
     pub optional_attributes: &'a [AttrPathRef<'a>],
+    /// Opaque cursor identifying the page to return, if the client requested cursor-based
+    /// pagination (RFC 9865). An empty string requests the first page. Invalid or expired
+    /// cursors should be rejected with [`Error::invalid_cursor`] or [`Error::expired_cursor`].
+    pub cursor: Option<&'a str>,
 }
 
 /// Arguments for getting a resource by ID.
@@ -83,7 +118,7 @@ pub struct GetResourceArgs<'a> {
 }
 
 /// Result of listing resources.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct ListResourceResult<T> {
     /// The resources.
     pub resources: Vec<T>,
@@ -91,6 +126,24 @@ pub struct ListResourceResult<T> {
     pub items_per_page: usize,
     /// The total number of items matching the filter.
     pub total_count: usize,
+    /// Cursor identifying the next page of results, if there is one. Only used with
+    /// cursor-based pagination.
+    pub next_cursor: Option<String>,
+    /// Cursor identifying the previous page of results, if there is one. Only used with
+    /// cursor-based pagination.
+    pub previous_cursor: Option<String>,
+}
+
+impl<T> Default for ListResourceResult<T> {
+    fn default() -> Self {
+        Self {
+            resources: Vec::new(),
+            items_per_page: 0,
+            total_count: 0,
+            next_cursor: None,
+            previous_cursor: None,
+        }
+    }
 }
 
 /// Arguments for updating a resource.
@@ -101,6 +154,10 @@ pub struct UpdateResourceArgs<'a> {
     pub id: &'a str,
     /// The updates to apply.
     pub items: &'a [UpdateResourceItem<'a>],
+    /// The expected current version of the resource, if the client made the update
+    /// conditional via `If-Match`. The update should fail with
+    /// [`Error::precondition_failed`] if the version does not match.
+    pub version: Option<&'a str>,
 }
 
 /// An update to apply to a resource.
@@ -126,17 +183,12 @@ pub enum UpdateOp<'a> {
 }
 
 /// Sort order for listing resources.
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum SortOrder {
     /// Sort in ascending order (default).
+    #[default]
     Ascending,
     /// Sort in descending order.
     Descending,
 }
-
-impl Default for SortOrder {
-    fn default() -> Self {
-        Self::Ascending
-    }
-}
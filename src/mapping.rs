@@ -0,0 +1,445 @@
+use std::{fmt, sync::Arc};
+
+use axum::http::{request::Parts, StatusCode};
+use bumpalo::Bump;
+use cream_core::{ResourceType, Schema};
+use ijson::{Destructured, IObject, IValue};
+
+use crate::{
+    error::{Error, ErrorType},
+    filter::{
+        self, attr, AttrPath, AttrPathRef, CompValue, Filter, FilterCapabilities, FilterRef,
+        ValuePath, Visitor,
+    },
+    manager::{
+        GenericResourceManager, GetResourceArgs, ListResourceArgs, ListResourceResult, UpdateOp,
+        UpdateResourceArgs, UpdateResourceItem,
+    },
+};
+
+type Converter = Arc<dyn Fn(&IValue) -> Result<IValue, Error> + Send + Sync>;
+
+/// A mapping from SCIM attributes to the fields of a backend, such as the columns of a
+/// database table.
+///
+/// Register a resource type with
+/// [`CreamBuilder::resource_type_with_mapping`](crate::CreamBuilder::resource_type_with_mapping)
+/// to have the paths in filters, `sortBy`, optional attributes and PATCH operations rewritten
+/// into backend fields before they are passed to the resource manager. A path to a mapped
+/// attribute is replaced by a path with no URN or sub-attribute, whose name is the field. A
+/// path to a sub-attribute whose parent is mapped keeps its sub-attribute, so that
+/// `name.givenName` becomes `name_json.givenName` if `name` is mapped to `name_json`. Other
+/// paths are passed to the manager unchanged. In PATCH paths with a value filter, such as
+/// `emails[type eq "work"].value`, the parent attribute and the paths within the filter are
+/// mapped, but the sub-attribute being updated keeps its name.
+///
+/// Fields may also have a value converter, which is applied to comparison values in filters
+/// and to the values of PATCH operations on the field. Comparisons with `null` and null
+/// values are not converted, and the elements of arrays are converted individually.
+///
+/// Resources are always exchanged with the manager in their SCIM representation. Filter
+/// capabilities, and the filters passed to
+/// [`can_push_down`](GenericResourceManager::can_push_down), are also in SCIM terms.
+#[derive(Clone, Default)]
+pub struct AttributeMapping {
+    fields: Vec<MappedField>,
+    core_urn: Option<String>,
+}
+
+#[derive(Clone)]
+struct MappedField {
+    path: AttrPath,
+    field: String,
+    converter: Option<Converter>,
+}
+
+impl fmt::Debug for AttributeMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.fields
+                    .iter()
+                    .map(|mapped| (mapped.path.to_string(), &mapped.field)),
+            )
+            .finish()
+    }
+}
+
+// Converts a comparison value into JSON, so that the same converters apply to filters and
+// PATCH operations.
+fn comp_to_json(value: &CompValue) -> IValue {
+    match value {
+        CompValue::Null => IValue::NULL,
+        CompValue::Bool(b) => (*b).into(),
+        CompValue::Num(n) => n.clone().into(),
+        CompValue::Str(s) => s.as_str().into(),
+        CompValue::DateTime(dt) => ijson::to_value(dt).expect("Dates can be serialized"),
+    }
+}
+
+fn json_to_comp(path: &AttrPath, value: IValue) -> Result<CompValue, Error> {
+    Ok(match value.destructure() {
+        Destructured::Null => CompValue::Null,
+        Destructured::Bool(b) => CompValue::Bool(b),
+        Destructured::Number(n) => CompValue::Num(n),
+        Destructured::String(s) => CompValue::Str(s.as_str().into()),
+        Destructured::Array(_) | Destructured::Object(_) => {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::InvalidFilter),
+                format!(
+                    "Cannot compare `{}`: the converted value is an array or object",
+                    path
+                ),
+            ))
+        }
+    })
+}
+
+impl AttributeMapping {
+    /// Create an empty mapping, under which every path is passed to the manager unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map an attribute to a field, such as `.field("name.familyName", "last_name")`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is not a valid attribute path.
+    pub fn field(self, path: &str, field: &str) -> Self {
+        self.push(path, field, None)
+    }
+
+    /// Map an attribute to a field, converting values with `convert`. For example, a boolean
+    /// attribute stored as a `Y` or `N` flag could be mapped with:
+    ///
+    /// ```
+    /// # use cream::{AttributeMapping, Error};
+    /// let mapping = AttributeMapping::new().field_with("active", "is_active", |value| {
+    ///     match value.to_bool() {
+    ///         Some(active) => Ok((if active { "Y" } else { "N" }).into()),
+    ///         None => Err(Error::expected("a boolean")),
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is not a valid attribute path.
+    pub fn field_with(
+        self,
+        path: &str,
+        field: &str,
+        convert: impl Fn(&IValue) -> Result<IValue, Error> + Send + Sync + 'static,
+    ) -> Self {
+        self.push(path, field, Some(Arc::new(convert)))
+    }
+
+    fn push(mut self, path: &str, field: &str, converter: Option<Converter>) -> Self {
+        self.fields.push(MappedField {
+            path: attr(path),
+            field: field.into(),
+            converter,
+        });
+        self
+    }
+
+    fn is_core_urn(&self, urn: Option<&str>) -> bool {
+        urn.zip(self.core_urn.as_deref())
+            .is_some_and(|(urn, core_urn)| urn.eq_ignore_ascii_case(core_urn))
+    }
+
+    // The core schema may be spelled out, but is omitted from the paths in filters.
+    fn with_core_urn(mut self, core_urn: &str) -> Self {
+        for mapped in &mut self.fields {
+            if mapped
+                .path
+                .urn
+                .as_deref()
+                .is_some_and(|urn| urn.eq_ignore_ascii_case(core_urn))
+            {
+                mapped.path.urn = None;
+            }
+        }
+        self.core_urn = Some(core_urn.into());
+        self
+    }
+
+    fn find(&self, mut path: AttrPathRef) -> Option<&MappedField> {
+        if self.is_core_urn(path.urn) {
+            path.urn = None;
+        }
+        self.fields
+            .iter()
+            .find(|mapped| mapped.path.as_ref().eq_ignore_ascii_case(&path))
+    }
+
+    fn map_path(&self, path: &AttrPath) -> Option<AttrPath> {
+        if let Some(mapped) = self.find(path.as_ref()) {
+            return Some(AttrPath {
+                urn: None,
+                name: mapped.field.clone(),
+                sub_attr: None,
+            });
+        }
+        let sub_attr = path.sub_attr.as_ref()?;
+        let parent = self.find(AttrPathRef {
+            sub_attr: None,
+            ..path.as_ref()
+        })?;
+        Some(AttrPath {
+            urn: None,
+            name: parent.field.clone(),
+            sub_attr: Some(sub_attr.clone()),
+        })
+    }
+
+    // Converts a value, or each element of an array.
+    fn convert(&self, path: AttrPathRef, value: &IValue) -> Result<Option<IValue>, Error> {
+        let Some(converter) = self.find(path).and_then(|mapped| mapped.converter.as_ref()) else {
+            return Ok(None);
+        };
+        if value.is_null() {
+            return Ok(None);
+        }
+        let converted = match value.as_array() {
+            Some(values) => values
+                .iter()
+                .map(|value| converter(value))
+                .collect::<Result<ijson::IArray, _>>()?
+                .into(),
+            None => converter(value)?,
+        };
+        Ok(Some(converted))
+    }
+}
+
+// Rewrites the paths and comparison values of a filter or PATCH path into backend terms.
+struct MappingVisitor<'a> {
+    mapping: &'a AttributeMapping,
+    error: Option<Error>,
+}
+
+impl MappingVisitor<'_> {
+    fn convert_comparison(&self, path: &AttrPath, value: &mut CompValue) -> Result<(), Error> {
+        if let Some(converted) = self.mapping.convert(path.as_ref(), &comp_to_json(value))? {
+            *value = json_to_comp(path, converted)?;
+        }
+        Ok(())
+    }
+}
+
+impl Visitor for MappingVisitor<'_> {
+    fn visit_filter(&mut self, filter: &mut Filter) {
+        if let Filter::Compare(path, _, value) = filter {
+            if let Err(e) = self.convert_comparison(path, value) {
+                self.error.get_or_insert(e);
+            }
+        }
+        filter::default_visit_filter(self, filter);
+    }
+    fn visit_attr_path(&mut self, attr_path: &mut AttrPath) {
+        if let Some(mapped) = self.mapping.map_path(attr_path) {
+            *attr_path = mapped;
+        }
+    }
+    fn visit_value_path(&mut self, value_path: &mut ValuePath) {
+        match value_path {
+            // The parser prefixes the paths within the value filter with the parent attribute
+            // (see `AttrPathPrefixer`), so `visit_filter` maps them like any other filter. Only
+            // the parent of the sub-attribute being updated is mapped, so that the path keeps
+            // the form `parent[filter].sub`.
+            ValuePath::Filtered(attr_path, filter) => {
+                let mut parent = AttrPath {
+                    sub_attr: None,
+                    ..attr_path.clone()
+                };
+                self.visit_attr_path(&mut parent);
+                attr_path.urn = parent.urn;
+                attr_path.name = parent.name;
+                self.visit_filter(filter);
+            }
+            ValuePath::Attr(_) => filter::default_visit_value_path(self, value_path),
+        }
+    }
+}
+
+/// A resource manager whose arguments are rewritten using an [`AttributeMapping`].
+#[derive(Debug)]
+pub(crate) struct MappedManager<M> {
+    manager: M,
+    mapping: AttributeMapping,
+}
+
+impl<M: GenericResourceManager> MappedManager<M> {
+    pub(crate) fn new(manager: M, mapping: AttributeMapping) -> Self {
+        let mapping = mapping.with_core_urn(&manager.load_resource_type().schema);
+        Self { manager, mapping }
+    }
+
+    fn visitor(&self) -> MappingVisitor<'_> {
+        MappingVisitor {
+            mapping: &self.mapping,
+            error: None,
+        }
+    }
+
+    fn map_filter(&self, filter: &FilterRef) -> Result<Filter, Error> {
+        let mut filter = filter.to_owned();
+        let mut visitor = self.visitor();
+        visitor.visit_filter(&mut filter);
+        visitor.error.map_or(Ok(filter), Err)
+    }
+
+    fn map_path(&self, path: AttrPathRef) -> AttrPath {
+        let path = path.to_owned();
+        self.mapping.map_path(&path).unwrap_or(path)
+    }
+
+    fn map_paths(&self, paths: &[AttrPathRef]) -> Vec<AttrPath> {
+        paths.iter().map(|path| self.map_path(*path)).collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: GenericResourceManager> GenericResourceManager for MappedManager<M> {
+    async fn list(
+        &self,
+        parts: &'async_trait Parts,
+        args: ListResourceArgs<'async_trait>,
+    ) -> Result<ListResourceResult<IObject>, Error> {
+        let scope = Bump::new();
+        let filter = args
+            .filter
+            .map(|filter| self.map_filter(&filter))
+            .transpose()?;
+        let sort_by = args.sort_by.map(|path| self.map_path(path));
+        let optional_attributes = self.map_paths(args.optional_attributes);
+        let optional_attributes: Vec<_> =
+            optional_attributes.iter().map(AttrPath::as_ref).collect();
+        let args = ListResourceArgs {
+            filter: filter.as_ref().map(|filter| filter.as_ref(&scope)),
+            sort_by: sort_by.as_ref().map(AttrPath::as_ref),
+            optional_attributes: &optional_attributes,
+            ..args
+        };
+        self.manager.list(parts, args).await
+    }
+
+    async fn get(
+        &self,
+        parts: &'async_trait Parts,
+        args: GetResourceArgs<'async_trait>,
+    ) -> Result<IObject, Error> {
+        let optional_attributes = self.map_paths(args.optional_attributes);
+        let optional_attributes: Vec<_> =
+            optional_attributes.iter().map(AttrPath::as_ref).collect();
+        let args = GetResourceArgs {
+            optional_attributes: &optional_attributes,
+            ..args
+        };
+        self.manager.get(parts, args).await
+    }
+
+    async fn create(&self, parts: &'async_trait Parts, resource: IObject) -> Result<String, Error> {
+        self.manager.create(parts, resource).await
+    }
+
+    async fn update(
+        &self,
+        parts: &'async_trait Parts,
+        args: UpdateResourceArgs<'async_trait>,
+    ) -> Result<(), Error> {
+        let scope = Bump::new();
+        let mut paths = Vec::with_capacity(args.items.len());
+        let mut values = Vec::with_capacity(args.items.len());
+        for item in args.items {
+            let value = match item.op {
+                UpdateOp::Add(value) | UpdateOp::Remove(value) | UpdateOp::Replace(value) => value,
+            };
+            let converted = match item.path {
+                Some(
+                    filter::ValuePathRef::Attr(path) | filter::ValuePathRef::Filtered(path, _),
+                ) => self.mapping.convert(path, value)?,
+                None => None,
+            };
+            values.push(converted);
+
+            let path = item.path.map(|path| path.to_owned());
+            paths.push(match path {
+                Some(mut path) => {
+                    let mut visitor = self.visitor();
+                    visitor.visit_value_path(&mut path);
+                    if let Some(e) = visitor.error {
+                        return Err(e);
+                    }
+                    Some(path)
+                }
+                None => None,
+            });
+        }
+        let items: Vec<_> = args
+            .items
+            .iter()
+            .zip(&paths)
+            .zip(&values)
+            .map(|((item, path), value)| UpdateResourceItem {
+                path: path.as_ref().map(|path: &ValuePath| path.as_ref(&scope)),
+                op: match (item.op, value) {
+                    (UpdateOp::Add(_), Some(value)) => UpdateOp::Add(value),
+                    (UpdateOp::Remove(_), Some(value)) => UpdateOp::Remove(value),
+                    (UpdateOp::Replace(_), Some(value)) => UpdateOp::Replace(value),
+                    (op, None) => op,
+                },
+            })
+            .collect();
+        let args = UpdateResourceArgs {
+            items: &items,
+            ..args
+        };
+        self.manager.update(parts, args).await
+    }
+
+    async fn replace(
+        &self,
+        parts: &'async_trait Parts,
+        id: &str,
+        resource: IObject,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        self.manager.replace(parts, id, resource, version).await
+    }
+
+    async fn delete(
+        &self,
+        parts: &'async_trait Parts,
+        id: &str,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        self.manager.delete(parts, id, version).await
+    }
+
+    fn default_page_size(&self) -> usize {
+        self.manager.default_page_size()
+    }
+    fn max_page_size(&self) -> usize {
+        self.manager.max_page_size()
+    }
+    fn filter_capabilities(&self) -> Option<FilterCapabilities> {
+        self.manager.filter_capabilities()
+    }
+    fn can_push_down(&self, filter: &FilterRef) -> bool {
+        self.manager.can_push_down(filter)
+    }
+
+    fn load_resource_type(&self) -> ResourceType {
+        self.manager.load_resource_type()
+    }
+    fn load_schema(&self, id: &str) -> Schema {
+        self.manager.load_schema(id)
+    }
+}
+
+#[cfg(test)]
+mod tests;
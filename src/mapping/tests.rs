@@ -0,0 +1,274 @@
+use std::sync::Mutex;
+
+use axum::http::{request::Parts, Request};
+use bumpalo::Bump;
+use cream_core::{load_static_json, ResourceType, Schema};
+use ijson::{ijson, IObject, IValue};
+
+use crate::{
+    error::{Error, ErrorType},
+    filter::{parse_filter, parse_value_path, AttrPathRef},
+    manager::{
+        GenericResourceManager, GetResourceArgs, ListResourceArgs, ListResourceResult, UpdateOp,
+        UpdateResourceArgs, UpdateResourceItem,
+    },
+};
+
+use super::{AttributeMapping, MappedManager};
+
+const CORE: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+// Records the arguments it is called with.
+#[derive(Debug, Default)]
+struct Recorder {
+    calls: Mutex<Vec<String>>,
+}
+
+fn paths(paths: &[AttrPathRef]) -> String {
+    paths
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[async_trait::async_trait]
+impl GenericResourceManager for Recorder {
+    async fn list(
+        &self,
+        _parts: &'async_trait Parts,
+        args: ListResourceArgs<'async_trait>,
+    ) -> Result<ListResourceResult<IObject>, Error> {
+        self.calls.lock().unwrap().push(format!(
+            "filter: {}; sort by: {}; attributes: {}",
+            args.filter.unwrap(),
+            args.sort_by.unwrap(),
+            paths(args.optional_attributes)
+        ));
+        Ok(ListResourceResult::default())
+    }
+    async fn get(
+        &self,
+        _parts: &'async_trait Parts,
+        args: GetResourceArgs<'async_trait>,
+    ) -> Result<IObject, Error> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("attributes: {}", paths(args.optional_attributes)));
+        Ok(IObject::new())
+    }
+    async fn create(
+        &self,
+        _parts: &'async_trait Parts,
+        _resource: IObject,
+    ) -> Result<String, Error> {
+        Err(Error::not_found())
+    }
+    async fn update(
+        &self,
+        _parts: &'async_trait Parts,
+        args: UpdateResourceArgs<'async_trait>,
+    ) -> Result<(), Error> {
+        let mut calls = self.calls.lock().unwrap();
+        for item in args.items {
+            let (op, value) = match item.op {
+                UpdateOp::Add(value) => ("add", value),
+                UpdateOp::Remove(value) => ("remove", value),
+                UpdateOp::Replace(value) => ("replace", value),
+            };
+            let path = item.path.map(|path| path.to_string()).unwrap_or_default();
+            calls.push(format!(
+                "{} {}: {}",
+                op,
+                path,
+                serde_json::to_string(value).unwrap()
+            ));
+        }
+        Ok(())
+    }
+    async fn replace(
+        &self,
+        _parts: &'async_trait Parts,
+        _id: &str,
+        _resource: IObject,
+        _version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    async fn delete(
+        &self,
+        _parts: &'async_trait Parts,
+        _id: &str,
+        _version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    fn load_resource_type(&self) -> ResourceType {
+        serde_json::from_value(serde_json::json!({
+            "name": "User",
+            "endpoint": "/Users",
+            "schema": CORE,
+            "schemaExtensions": [{ "schema": ENTERPRISE, "required": false }]
+        }))
+        .unwrap()
+    }
+    fn load_schema(&self, id: &str) -> Schema {
+        if id == ENTERPRISE {
+            load_static_json!("../../examples/enterprise_user_schema.json")
+        } else {
+            load_static_json!("../../examples/user_schema.json")
+        }
+    }
+}
+
+fn manager() -> MappedManager<Recorder> {
+    let mapping = AttributeMapping::new()
+        .field("userName", "login")
+        .field(&format!("{}:displayName", CORE), "display_name")
+        .field("name", "name_json")
+        .field("emails", "user_emails")
+        .field("emails.value", "user_emails.address")
+        .field(&format!("{}:employeeNumber", ENTERPRISE), "employee_no")
+        .field_with("active", "is_active", |value| match value.to_bool() {
+            Some(active) => Ok((if active { "Y" } else { "N" }).into()),
+            None => Err(Error::expected("a boolean")),
+        });
+    MappedManager::new(Recorder::default(), mapping)
+}
+
+fn parts() -> Parts {
+    Request::new(()).into_parts().0
+}
+
+fn take_calls(manager: &MappedManager<Recorder>) -> Vec<String> {
+    std::mem::take(&mut *manager.manager.calls.lock().unwrap())
+}
+
+#[tokio::test]
+async fn test_list() {
+    let manager = manager();
+    let scope = Bump::new();
+    let filter = parse_filter(
+        &format!(
+            r#"userName eq "bjensen" and {}:employeeNumber pr and (active eq true or emails[type eq "work" and value co "@example.com"])"#,
+            ENTERPRISE
+        ),
+    )
+    .unwrap();
+    let sort_by = crate::filter::attr("name.familyName");
+    let attributes = [
+        crate::filter::attr(&format!("{}:displayName", CORE)),
+        crate::filter::attr("title"),
+    ];
+    let attributes: Vec<_> = attributes.iter().map(|path| path.as_ref()).collect();
+    let args = ListResourceArgs {
+        filter: Some(filter.as_ref(&scope)),
+        sort_by: Some(sort_by.as_ref()),
+        optional_attributes: &attributes,
+        count: 10,
+        ..Default::default()
+    };
+    manager.list(&parts(), args).await.unwrap();
+    assert_eq!(
+        take_calls(&manager),
+        [concat!(
+            r#"filter: login eq "bjensen" and employee_no pr and (is_active eq "Y" or "#,
+            r#"user_emails[type eq "work" and user_emails.address co "@example.com"]); "#,
+            "sort by: name_json.familyName; attributes: display_name, title"
+        )]
+    );
+
+    let filter = parse_filter(r#"active eq "maybe""#).unwrap();
+    let args = ListResourceArgs {
+        filter: Some(filter.as_ref(&scope)),
+        ..Default::default()
+    };
+    let e = manager.list(&parts(), args).await.unwrap_err();
+    assert_eq!(e.detail, "Expected a boolean");
+    assert!(take_calls(&manager).is_empty());
+}
+
+#[tokio::test]
+async fn test_list_converted_to_array() {
+    let mapping =
+        AttributeMapping::new().field_with("title", "titles", |value| Ok(ijson!([value])));
+    let manager = MappedManager::new(Recorder::default(), mapping);
+    let scope = Bump::new();
+    let filter = parse_filter(r#"title eq "Tour Guide""#).unwrap();
+    let args = ListResourceArgs {
+        filter: Some(filter.as_ref(&scope)),
+        ..Default::default()
+    };
+    let e = manager.list(&parts(), args).await.unwrap_err();
+    assert!(matches!(e.scim_type, Some(ErrorType::InvalidFilter)));
+    assert_eq!(
+        e.detail,
+        "Cannot compare `title`: the converted value is an array or object"
+    );
+    assert!(take_calls(&manager).is_empty());
+}
+
+#[tokio::test]
+async fn test_get() {
+    let manager = manager();
+    let attributes = [AttrPathRef {
+        urn: None,
+        name: "userName",
+        sub_attr: None,
+    }];
+    let args = GetResourceArgs {
+        id: "1".to_string(),
+        optional_attributes: &attributes,
+    };
+    manager.get(&parts(), args).await.unwrap();
+    assert_eq!(take_calls(&manager), ["attributes: login"]);
+}
+
+#[tokio::test]
+async fn test_update() {
+    let manager = manager();
+    let scope = Bump::new();
+    let active = parse_value_path("active").unwrap();
+    let email = parse_value_path(r#"emails[type eq "work"].value"#).unwrap();
+    let nick_name = parse_value_path("nickName").unwrap();
+    let values: [IValue; 3] = [
+        ijson!([false, true]),
+        ijson!("babs@example.com"),
+        ijson!("Babs"),
+    ];
+    let items = [
+        UpdateResourceItem {
+            path: Some(active.as_ref(&scope)),
+            op: UpdateOp::Replace(&values[0]),
+        },
+        UpdateResourceItem {
+            path: Some(email.as_ref(&scope)),
+            op: UpdateOp::Add(&values[1]),
+        },
+        UpdateResourceItem {
+            path: Some(nick_name.as_ref(&scope)),
+            op: UpdateOp::Remove(&values[2]),
+        },
+        UpdateResourceItem {
+            path: Some(active.as_ref(&scope)),
+            op: UpdateOp::Replace(&IValue::NULL),
+        },
+    ];
+    let args = UpdateResourceArgs {
+        id: "1",
+        items: &items,
+        version: None,
+    };
+    manager.update(&parts(), args).await.unwrap();
+    assert_eq!(
+        take_calls(&manager),
+        [
+            r#"replace is_active: ["N","Y"]"#,
+            r#"add user_emails[type eq "work"].value: "babs@example.com""#,
+            r#"remove nickName: "Babs""#,
+            "replace is_active: null",
+        ]
+    );
+}
@@ -0,0 +1,494 @@
+use std::{cmp::Ordering, collections::BTreeMap, sync::Mutex};
+
+use axum::http::request::Parts;
+use cream_core::{Attribute, Reference, ResourceType, Returned, Schema, Type, Uniqueness};
+use ijson::{IObject, IValue};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    filter::AttrPathRef,
+    json::get_ignore_case,
+    manager::{
+        GenericResourceManager, GetResourceArgs, ListResourceArgs, ListResourceResult, SortOrder,
+        UpdateResourceArgs,
+    },
+    patch::apply_update,
+    schema::SchemaRegistry,
+    Error,
+};
+
+#[derive(Debug, Default)]
+struct MemoryStoreState {
+    resources: BTreeMap<String, IObject>,
+    next_id: u64,
+    next_version: u64,
+}
+
+/// A resource manager which stores resources of a single type in memory.
+///
+/// Supports the full range of SCIM functionality, including filtering, sorting, index and
+/// cursor-based pagination, PATCH and uniqueness constraints. Resources are lost when the
+/// store is dropped, so this is mostly useful for testing and for small deployments.
+#[derive(Debug)]
+pub struct MemoryStore {
+    resource_type: ResourceType,
+    schemas: BTreeMap<String, Schema>,
+    registry: SchemaRegistry,
+    state: Mutex<MemoryStoreState>,
+}
+
+fn now() -> IValue {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .expect("Failed to format timestamp")
+        .into()
+}
+
+fn first_value(value: &IValue) -> Option<&IValue> {
+    match value.as_array() {
+        // Prefer the primary value of a multi-valued attribute.
+        Some(arr) => arr
+            .iter()
+            .find(|item| {
+                item.as_object()
+                    .and_then(|obj| get_ignore_case(obj, "primary"))
+                    .and_then(IValue::to_bool)
+                    .unwrap_or(false)
+            })
+            .or_else(|| arr.first()),
+        None => Some(value),
+    }
+    .filter(|value| !value.is_null())
+}
+
+// Looks up the value used when sorting or checking uniqueness of an attribute.
+fn sort_value<'a>(resource: &'a IObject, path: AttrPathRef) -> Option<&'a IValue> {
+    let obj = match path.urn {
+        Some(urn) => get_ignore_case(resource, urn)?.as_object()?,
+        None => resource,
+    };
+    let value = first_value(get_ignore_case(obj, path.name)?)?;
+    match path.sub_attr {
+        Some(sub_attr) => first_value(get_ignore_case(value.as_object()?, sub_attr)?),
+        None => Some(value),
+    }
+}
+
+fn compare_values(attr: Option<&Attribute>, a: &IValue, b: &IValue) -> Ordering {
+    if let (Some(a), Some(b)) = (a.as_string(), b.as_string()) {
+        if attr.is_some_and(|attr| matches!(attr.type_, Type::DateTime)) {
+            if let (Ok(a), Ok(b)) = (
+                OffsetDateTime::parse(a, &Rfc3339),
+                OffsetDateTime::parse(b, &Rfc3339),
+            ) {
+                return a.cmp(&b);
+            }
+        }
+        if attr.is_some_and(|attr| attr.case_exact) {
+            a.cmp(b)
+        } else {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        }
+    } else {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    }
+}
+
+fn is_listed(optional_attributes: &[AttrPathRef], urn: Option<&str>, name: &str) -> bool {
+    optional_attributes.iter().any(|path| {
+        path.sub_attr.is_none()
+            && path.name.eq_ignore_ascii_case(name)
+            && path.urn.map(str::to_ascii_lowercase) == urn.map(str::to_ascii_lowercase)
+    })
+}
+
+fn listed_sub_attr(
+    optional_attributes: &[AttrPathRef],
+    urn: Option<&str>,
+    name: &str,
+    sub_attr: &str,
+) -> bool {
+    optional_attributes.iter().any(|path| {
+        path.sub_attr
+            .is_some_and(|s| s.eq_ignore_ascii_case(sub_attr))
+            && path.name.eq_ignore_ascii_case(name)
+            && path.urn.map(str::to_ascii_lowercase) == urn.map(str::to_ascii_lowercase)
+    })
+}
+
+// Applies the `returned` characteristic of an attribute to its value.
+fn project_attribute(
+    attr: &Attribute,
+    urn: Option<&str>,
+    value: &IValue,
+    optional_attributes: &[AttrPathRef],
+) -> Option<IValue> {
+    let whole = match attr.returned {
+        Returned::Never => return None,
+        Returned::Always => true,
+        Returned::Default | Returned::Request => is_listed(optional_attributes, urn, &attr.name),
+    };
+    let Some(sub_attributes) = &attr.sub_attributes else {
+        return whole.then(|| value.clone());
+    };
+    let keep = |key: &str| {
+        sub_attributes
+            .iter()
+            .find(|sub_attr| sub_attr.name.eq_ignore_ascii_case(key))
+            .is_none_or(|sub_attr| match sub_attr.returned {
+                Returned::Never => false,
+                Returned::Always => true,
+                _ => whole || listed_sub_attr(optional_attributes, urn, &attr.name, key),
+            })
+    };
+    let project = |value: &IValue| match value.as_object() {
+        Some(obj) => obj
+            .iter()
+            .filter(|(key, _)| keep(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<IObject>()
+            .into(),
+        None => value.clone(),
+    };
+    let result: IValue = match value.as_array() {
+        Some(arr) => arr.iter().map(project).collect::<Vec<_>>().into(),
+        None => project(value),
+    };
+    let empty = match result.as_array() {
+        Some(arr) => arr
+            .iter()
+            .all(|item| item.as_object().is_some_and(|obj| obj.is_empty())),
+        None => result.as_object().is_some_and(|obj| obj.is_empty()),
+    };
+    (whole || !empty).then_some(result)
+}
+
+impl MemoryStore {
+    /// Construct a new store for the given resource type. The core and extension schemas
+    /// referenced by the resource type must be provided.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resource type references a schema which is not provided.
+    pub fn new(resource_type: ResourceType, schemas: impl IntoIterator<Item = Schema>) -> Self {
+        let schemas: BTreeMap<_, _> = schemas
+            .into_iter()
+            .map(|schema| (schema.id.clone(), schema))
+            .collect();
+        let registry = SchemaRegistry::new(&resource_type, |id| {
+            schemas
+                .get(id)
+                .unwrap_or_else(|| panic!("Unknown schema: {}", id))
+        });
+        Self {
+            resource_type,
+            schemas,
+            registry,
+            state: Mutex::default(),
+        }
+    }
+
+    // Applies the `returned` characteristic of each attribute to a stored resource.
+    fn project(&self, resource: &IObject, optional_attributes: &[AttrPathRef]) -> IObject {
+        let mut result = IObject::new();
+        for (key, value) in resource {
+            if let (Some(ext), Some(obj)) = (self.registry.extension(key), value.as_object()) {
+                let projected: IObject = obj
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let attr = ext
+                            .attributes
+                            .iter()
+                            .find(|attr| attr.name.eq_ignore_ascii_case(key))?;
+                        let value =
+                            project_attribute(attr, Some(&ext.id), value, optional_attributes)?;
+                        Some((key.clone(), value))
+                    })
+                    .collect();
+                if !projected.is_empty() {
+                    result.insert(key.clone(), projected);
+                }
+                continue;
+            }
+            let attr = self
+                .registry
+                .core()
+                .attributes
+                .iter()
+                .find(|attr| attr.name.eq_ignore_ascii_case(key));
+            match attr {
+                Some(attr) => {
+                    if let Some(value) = project_attribute(attr, None, value, optional_attributes) {
+                        result.insert(key.clone(), value);
+                    }
+                }
+                // Common attributes, such as `id` and `meta`, are always returned.
+                None => {
+                    result.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        result
+    }
+
+    // Adds the location to a stored resource. This is done on every read, since the base URL
+    // is only known while handling a request.
+    fn locate(&self, resource: &mut IObject, id: &str) {
+        let location = Reference::new_relative(&format!("{}/{}", self.resource_type.endpoint, id));
+        if let Some(meta) = resource.get_mut("meta").and_then(IValue::as_object_mut) {
+            meta.insert(
+                "location",
+                ijson::to_value(location).expect("Failed to serialize location"),
+            );
+        }
+    }
+
+    fn read(&self, resource: &IObject, id: &str, optional_attributes: &[AttrPathRef]) -> IObject {
+        let mut result = self.project(resource, optional_attributes);
+        self.locate(&mut result, id);
+        result
+    }
+
+    // Sets the `schemas` and `meta` attributes of a resource which is being written.
+    fn prepare(
+        &self,
+        state: &mut MemoryStoreState,
+        id: &str,
+        resource: &mut IObject,
+        created: Option<IValue>,
+    ) {
+        let mut schemas = vec![IValue::from(self.resource_type.schema.as_str())];
+        for ext in &self.resource_type.schema_extensions {
+            if get_ignore_case(resource, &ext.schema).is_some() {
+                schemas.push(ext.schema.as_str().into());
+            }
+        }
+        state.next_version += 1;
+        let now = now();
+        let mut meta = IObject::new();
+        meta.insert("resourceType", self.resource_type.name.as_str());
+        meta.insert("created", created.unwrap_or_else(|| now.clone()));
+        meta.insert("lastModified", now);
+        meta.insert("version", format!("\"{}\"", state.next_version));
+
+        resource.insert("id", id);
+        resource.insert("schemas", schemas);
+        resource.insert("meta", meta);
+    }
+
+    // Checks that no other resource has the same value for a unique attribute.
+    fn check_uniqueness(
+        &self,
+        state: &MemoryStoreState,
+        id: &str,
+        resource: &IObject,
+    ) -> Result<(), Error> {
+        let core = self
+            .registry
+            .core()
+            .attributes
+            .iter()
+            .map(|attr| (None, attr));
+        let extensions = self.registry.extensions().flat_map(|(schema, _)| {
+            schema
+                .attributes
+                .iter()
+                .map(|attr| (Some(schema.id.as_str()), attr))
+        });
+        for (urn, attr) in core.chain(extensions) {
+            if matches!(attr.uniqueness, Uniqueness::None) {
+                continue;
+            }
+            let path = AttrPathRef {
+                urn,
+                name: &attr.name,
+                sub_attr: None,
+            };
+            let Some(value) = sort_value(resource, path) else {
+                continue;
+            };
+            let conflict = state.resources.iter().any(|(other_id, other)| {
+                other_id != id
+                    && sort_value(other, path).is_some_and(|other| {
+                        compare_values(Some(attr), value, other) == Ordering::Equal
+                    })
+            });
+            if conflict {
+                return Err(Error::uniqueness(&attr.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_version(current: &IObject, version: Option<&str>) -> Result<(), Error> {
+        let current_version = current
+            .get("meta")
+            .and_then(|meta| meta.get("version"))
+            .and_then(IValue::as_string)
+            .map(|version| version.as_str());
+        match version {
+            Some(version) if current_version != Some(version) => Err(Error::precondition_failed()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl GenericResourceManager for MemoryStore {
+    async fn list(
+        &self,
+        _parts: &'async_trait Parts,
+        args: ListResourceArgs<'async_trait>,
+    ) -> Result<ListResourceResult<IObject>, Error> {
+        let state = self.state.lock().expect("Lock poisoned");
+        let mut matching: Vec<_> = state
+            .resources
+            .iter()
+            .filter(|(_, resource)| {
+                args.filter
+                    .is_none_or(|filter| filter.matches(resource, &self.registry))
+            })
+            .collect();
+
+        if let Some(sort_by) = args.sort_by {
+            let attr = self.registry.attribute(sort_by);
+            // Resources without a value are sorted last, regardless of the sort order.
+            matching.sort_by(|(_, a), (_, b)| {
+                match (sort_value(a, sort_by), sort_value(b, sort_by)) {
+                    (Some(a), Some(b)) => {
+                        let ordering = compare_values(attr, a, b);
+                        match args.sort_order {
+                            SortOrder::Ascending => ordering,
+                            SortOrder::Descending => ordering.reverse(),
+                        }
+                    }
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            });
+        }
+
+        // Cursors are simply the offset of the first result on the page.
+        let offset = match args.cursor {
+            Some("") => 0,
+            Some(cursor) => cursor.parse().map_err(|_| Error::invalid_cursor())?,
+            None => args.start_index,
+        };
+        let total_count = matching.len();
+        let resources = matching
+            .into_iter()
+            .skip(offset)
+            .take(args.count)
+            .map(|(id, resource)| self.read(resource, id, args.optional_attributes))
+            .collect();
+        let (next_cursor, previous_cursor) = match args.cursor {
+            Some(_) => (
+                (offset + args.count < total_count).then(|| (offset + args.count).to_string()),
+                (offset > 0).then(|| offset.saturating_sub(args.count).to_string()),
+            ),
+            None => (None, None),
+        };
+        Ok(ListResourceResult {
+            resources,
+            items_per_page: args.count,
+            total_count,
+            next_cursor,
+            previous_cursor,
+        })
+    }
+
+    async fn get(
+        &self,
+        _parts: &'async_trait Parts,
+        args: GetResourceArgs<'async_trait>,
+    ) -> Result<IObject, Error> {
+        let state = self.state.lock().expect("Lock poisoned");
+        let resource = state.resources.get(&args.id).ok_or_else(Error::not_found)?;
+        Ok(self.read(resource, &args.id, args.optional_attributes))
+    }
+
+    async fn create(
+        &self,
+        _parts: &'async_trait Parts,
+        mut resource: IObject,
+    ) -> Result<String, Error> {
+        let mut state = self.state.lock().expect("Lock poisoned");
+        // Check before taking an id or version, so that a failed create does not use them up
+        let id = (state.next_id + 1).to_string();
+        self.check_uniqueness(&state, &id, &resource)?;
+        state.next_id += 1;
+        self.prepare(&mut state, &id, &mut resource, None);
+        state.resources.insert(id.clone(), resource);
+        Ok(id)
+    }
+
+    async fn update(
+        &self,
+        _parts: &'async_trait Parts,
+        args: UpdateResourceArgs<'async_trait>,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().expect("Lock poisoned");
+        let current = state.resources.get(args.id).ok_or_else(Error::not_found)?;
+        Self::check_version(current, args.version)?;
+        let created = current
+            .get("meta")
+            .and_then(|meta| meta.get("created"))
+            .cloned();
+
+        let mut resource = current.clone();
+        apply_update(&mut resource, args.items, &self.registry)?;
+        self.check_uniqueness(&state, args.id, &resource)?;
+        self.prepare(&mut state, args.id, &mut resource, created);
+        state.resources.insert(args.id.to_string(), resource);
+        Ok(())
+    }
+
+    async fn replace(
+        &self,
+        _parts: &'async_trait Parts,
+        id: &str,
+        mut resource: IObject,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().expect("Lock poisoned");
+        let current = state.resources.get(id).ok_or_else(Error::not_found)?;
+        Self::check_version(current, version)?;
+        let created = current
+            .get("meta")
+            .and_then(|meta| meta.get("created"))
+            .cloned();
+
+        self.check_uniqueness(&state, id, &resource)?;
+        self.prepare(&mut state, id, &mut resource, created);
+        state.resources.insert(id.to_string(), resource);
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        _parts: &'async_trait Parts,
+        id: &str,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().expect("Lock poisoned");
+        let current = state.resources.get(id).ok_or_else(Error::not_found)?;
+        Self::check_version(current, version)?;
+        state.resources.remove(id);
+        Ok(())
+    }
+
+    fn load_resource_type(&self) -> ResourceType {
+        self.resource_type.clone()
+    }
+
+    fn load_schema(&self, id: &str) -> Schema {
+        self.schemas
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| panic!("Unknown schema: {}", id))
+    }
+}
+
+#[cfg(test)]
+mod tests;
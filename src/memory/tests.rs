@@ -0,0 +1,210 @@
+use axum::http::{request::Parts, Request};
+use bumpalo::Bump;
+use cream_core::load_static_json;
+use ijson::{ijson, IObject, IValue};
+
+use crate::{
+    filter::{self, AttrPathRef, ValuePathRef},
+    manager::{
+        GenericResourceManager, GetResourceArgs, ListResourceArgs, SortOrder, UpdateOp,
+        UpdateResourceArgs, UpdateResourceItem,
+    },
+    Error,
+};
+
+use super::MemoryStore;
+
+fn store() -> MemoryStore {
+    MemoryStore::new(
+        load_static_json!("../../examples/user_type.json"),
+        [load_static_json!("../../examples/user_schema.json")],
+    )
+}
+
+fn parts() -> Parts {
+    Request::new(()).into_parts().0
+}
+
+fn object(value: IValue) -> IObject {
+    value.into_object().unwrap()
+}
+
+const DEFAULT_ATTRIBUTES: &[AttrPathRef] = &[
+    AttrPathRef {
+        urn: None,
+        name: "userName",
+        sub_attr: None,
+    },
+    AttrPathRef {
+        urn: None,
+        name: "title",
+        sub_attr: None,
+    },
+    AttrPathRef {
+        urn: None,
+        name: "emails",
+        sub_attr: None,
+    },
+];
+
+async fn list(store: &MemoryStore, filter: Option<&str>, start_index: usize) -> Vec<String> {
+    let scope = Bump::new();
+    let filter = filter.map(|filter| scope.alloc(filter::parse_filter(filter).unwrap()));
+    let args = ListResourceArgs {
+        filter: filter.map(|filter| filter.as_ref(&scope)),
+        sort_by: Some(AttrPathRef {
+            urn: None,
+            name: "userName",
+            sub_attr: None,
+        }),
+        sort_order: SortOrder::Descending,
+        start_index,
+        count: 2,
+        optional_attributes: DEFAULT_ATTRIBUTES,
+        ..Default::default()
+    };
+    let result = store.list(&parts(), args).await.unwrap();
+    result
+        .resources
+        .iter()
+        .map(|resource| resource["userName"].as_string().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_create_and_list() {
+    let store = store();
+    for user_name in ["alice", "bob", "carol"] {
+        store
+            .create(
+                &parts(),
+                object(ijson!({ "userName": user_name, "password": "secret" })),
+            )
+            .await
+            .unwrap();
+    }
+
+    let err = store
+        .create(&parts(), object(ijson!({ "userName": "ALICE" })))
+        .await
+        .unwrap_err();
+    assert_eq!(err.detail, "Attribute `userName` must be unique");
+
+    assert_eq!(list(&store, None, 0).await, ["carol", "bob"]);
+    assert_eq!(list(&store, None, 2).await, ["alice"]);
+    assert_eq!(
+        list(&store, Some("userName lt \"c\""), 0).await,
+        ["bob", "alice"]
+    );
+
+    let resource = store
+        .get(
+            &parts(),
+            GetResourceArgs {
+                id: "1".into(),
+                optional_attributes: DEFAULT_ATTRIBUTES,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resource["id"], "1".into());
+    assert_eq!(resource["meta"]["resourceType"], "User".into());
+    assert_eq!(resource["meta"]["location"], "/Users/1".into());
+    assert!(resource.get("password").is_none());
+}
+
+async fn create(store: &MemoryStore, user_name: &str) -> Result<String, Error> {
+    store
+        .create(&parts(), object(ijson!({ "userName": user_name })))
+        .await
+}
+
+#[tokio::test]
+async fn test_create_not_unique() {
+    let store = store();
+    assert_eq!(create(&store, "alice").await.unwrap(), "1");
+    let err = create(&store, "alice").await.unwrap_err();
+    assert_eq!(err.detail, "Attribute `userName` must be unique");
+
+    // The failed create used up neither an id nor a version.
+    let id = create(&store, "bob").await.unwrap();
+    assert_eq!(id, "2");
+    let resource = store
+        .get(
+            &parts(),
+            GetResourceArgs {
+                id,
+                optional_attributes: DEFAULT_ATTRIBUTES,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resource["meta"]["version"], "\"2\"".into());
+}
+
+#[tokio::test]
+async fn test_update() {
+    let store = store();
+    let id = store
+        .create(
+            &parts(),
+            object(ijson!({
+                "userName": "alice",
+                "title": "Engineer",
+                "emails": [{ "value": "alice@example.com", "type": "work" }]
+            })),
+        )
+        .await
+        .unwrap();
+
+    let new_email = ijson!({ "value": "alice@example.org", "type": "home" });
+    let items = [
+        UpdateResourceItem {
+            path: Some(ValuePathRef::Attr(AttrPathRef {
+                urn: None,
+                name: "emails",
+                sub_attr: None,
+            })),
+            op: UpdateOp::Add(&new_email),
+        },
+        UpdateResourceItem {
+            path: Some(ValuePathRef::Attr(AttrPathRef {
+                urn: None,
+                name: "title",
+                sub_attr: None,
+            })),
+            op: UpdateOp::Remove(&IValue::NULL),
+        },
+    ];
+    let args = |version| UpdateResourceArgs {
+        id: &id,
+        items: &items,
+        version,
+    };
+    let err = store
+        .update(&parts(), args(Some("\"0\"")))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status, 412);
+    store.update(&parts(), args(None)).await.unwrap();
+
+    let resource = store
+        .get(
+            &parts(),
+            GetResourceArgs {
+                id,
+                optional_attributes: DEFAULT_ATTRIBUTES,
+            },
+        )
+        .await
+        .unwrap();
+    assert!(resource.get("title").is_none());
+    assert_eq!(resource["emails"].as_array().unwrap().len(), 2);
+    assert_eq!(resource["meta"]["version"], "\"2\"".into());
+}
+
+#[test]
+#[should_panic(expected = "Unknown schema: urn:ietf:params:scim:schemas:core:2.0:User")]
+fn test_missing_schema() {
+    MemoryStore::new(load_static_json!("../../examples/user_type.json"), []);
+}
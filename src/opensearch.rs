@@ -0,0 +1,362 @@
+//! Translation of SCIM filters and list arguments into OpenSearch queries.
+//!
+//! Resource managers backed by OpenSearch or Elasticsearch can use [`OpenSearchTranslator`] to
+//! turn the arguments passed to
+//! [`GenericResourceManager::list`](crate::GenericResourceManager::list) into the body of a
+//! search request, written in the query DSL.
+
+use axum::http::StatusCode;
+use cream_core::{Attribute, Type};
+use serde_json::{json, Value};
+use time::{format_description::well_known::Rfc3339, UtcOffset};
+
+use crate::{
+    error::{Error, ErrorType},
+    filter::{AttrPathRef, CompValueRef, CompareOp, FilterRef},
+    manager::{ListResourceArgs, SortOrder},
+    schema::SchemaRegistry,
+};
+
+/// The representation of an attribute in a search index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchField {
+    /// A field which can be queried directly, such as `userName`.
+    Field {
+        /// The name of the field. It should be a `keyword` field, or another type of field
+        /// which matches values exactly, such as `date`.
+        name: String,
+        /// The name of a `keyword` field with a lowercase normalizer, used for comparisons
+        /// which ignore case. If `None`, such comparisons use the `case_insensitive` option
+        /// where one exists, and are otherwise case-sensitive.
+        normalized: Option<String>,
+    },
+    /// A multi-valued complex attribute which is indexed with the `nested` type, so that
+    /// value filters such as `emails[type eq "work" and primary eq true]` are evaluated
+    /// against each value separately.
+    ///
+    /// The sub-attributes of the attribute must be mapped to fields of the nested documents,
+    /// such as `emails.type`. A comparison against the attribute itself uses its `value`
+    /// sub-attribute.
+    Nested {
+        /// The path of the nested documents.
+        path: String,
+    },
+}
+
+impl SearchField {
+    /// A field without a normalized counterpart.
+    pub fn field(name: impl Into<String>) -> Self {
+        Self::Field {
+            name: name.into(),
+            normalized: None,
+        }
+    }
+}
+
+/// Translates filters and list arguments into the OpenSearch query DSL, using a mapping from
+/// attributes to fields.
+///
+/// Attributes are mapped by a function which is called with each attribute path found in the
+/// filter. Filters referring to attributes which are not mapped are rejected with an
+/// `invalidFilter` error. The comparison operators translate as follows:
+///
+/// - `pr` into an `exists` query. Comparisons with `null` are also presence tests.
+/// - `eq` into a `term` query, and `ne` into its negation.
+/// - `sw` into a `prefix` query.
+/// - `co` and `ew` into `wildcard` queries.
+/// - `gt`, `ge`, `lt` and `le` into `range` queries.
+///
+/// String comparisons ignore case unless the attribute is `caseExact`, in which case they
+/// use the field itself rather than its normalized counterpart. Value filters and
+/// comparisons on [nested](SearchField::Nested) attributes become `nested` queries.
+pub struct OpenSearchTranslator<'a> {
+    schemas: &'a SchemaRegistry,
+    fields: Box<dyn Fn(AttrPathRef) -> Option<SearchField> + 'a>,
+}
+
+// The resolved field for an attribute path.
+struct Field<'a> {
+    name: String,
+    normalized: Option<String>,
+    nested: Option<String>,
+    attr: Option<&'a Attribute>,
+}
+
+impl Field<'_> {
+    // Whether string comparisons against this field ignore case.
+    fn ignore_case(&self) -> bool {
+        self.attr.is_none_or(|attr| {
+            !attr.case_exact && matches!(attr.type_, Type::String | Type::Reference)
+        })
+    }
+
+    // The field to compare a string against, the string to compare, and whether the query
+    // must ignore case itself.
+    fn string_comparison(&self, s: &str) -> (&str, String, bool) {
+        match &self.normalized {
+            Some(normalized) if self.ignore_case() => (normalized, s.to_lowercase(), false),
+            _ => (&self.name, s.to_string(), self.ignore_case()),
+        }
+    }
+
+    // Wraps a query in a `nested` query if the field is in a nested document.
+    fn wrap(&self, query: Value) -> Value {
+        match &self.nested {
+            Some(path) => json!({ "nested": { "path": path, "query": query } }),
+            None => query,
+        }
+    }
+}
+
+fn same_attribute(a: AttrPathRef, b: AttrPathRef) -> bool {
+    a.name.eq_ignore_ascii_case(b.name)
+        && match (a.urn, b.urn) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (None, None) => true,
+            _ => false,
+        }
+}
+
+// Escapes the special characters of a `wildcard` pattern.
+fn escape_wildcard(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn not(query: Value) -> Value {
+    json!({ "bool": { "must_not": [query] } })
+}
+
+fn unsupported(path: AttrPathRef, op: CompareOp, literal: CompValueRef) -> Error {
+    Error::new(
+        StatusCode::BAD_REQUEST,
+        Some(ErrorType::InvalidFilter),
+        format!("Cannot compare `{}` with {} using `{}`", path, literal, op),
+    )
+}
+
+impl<'a> OpenSearchTranslator<'a> {
+    /// Construct a translator for resources with the given schemas. `fields` returns the
+    /// representation of an attribute in the index, or `None` if it cannot be filtered on.
+    pub fn new(
+        schemas: &'a SchemaRegistry,
+        fields: impl Fn(AttrPathRef) -> Option<SearchField> + 'a,
+    ) -> Self {
+        Self {
+            schemas,
+            fields: Box::new(fields),
+        }
+    }
+
+    fn resolve(&self, path: AttrPathRef, scope: Option<AttrPathRef>) -> Result<Field<'a>, Error> {
+        let parent = AttrPathRef {
+            sub_attr: None,
+            ..path
+        };
+        let nested = match (self.fields)(parent) {
+            Some(SearchField::Nested { path }) => Some(path),
+            _ => None,
+        };
+        // Nested attributes are compared using their `value` sub-attribute.
+        let resolved = match self.schemas.comparison_attribute(path) {
+            Some((resolved, _)) => resolved,
+            None => path,
+        };
+        let resolved = if resolved.sub_attr.is_none() && nested.is_some() {
+            AttrPathRef {
+                sub_attr: Some("value"),
+                ..resolved
+            }
+        } else {
+            resolved
+        };
+        let Some(SearchField::Field { name, normalized }) = (self.fields)(resolved) else {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::InvalidFilter),
+                format!("Filtering on `{}` is not supported", path),
+            ));
+        };
+        Ok(Field {
+            name,
+            normalized,
+            // Within a value filter on the same attribute, the nested query already applies.
+            nested: nested.filter(|_| scope.is_none_or(|scope| !same_attribute(scope, parent))),
+            attr: self.schemas.attribute(resolved),
+        })
+    }
+
+    // Builds the query for a comparison against a single document.
+    fn compare(
+        &self,
+        field: &Field,
+        path: AttrPathRef,
+        op: CompareOp,
+        literal: CompValueRef,
+    ) -> Result<Value, Error> {
+        let exists = json!({ "exists": { "field": field.name } });
+        let (name, value, case_insensitive) = match literal {
+            CompValueRef::Null => {
+                return match op {
+                    CompareOp::Equal => Ok(not(exists)),
+                    CompareOp::NotEqual => Ok(exists),
+                    _ => Err(unsupported(path, op, literal)),
+                }
+            }
+            CompValueRef::Bool(b) => (field.name.as_str(), json!(b), false),
+            CompValueRef::Num(n) => (field.name.as_str(), json!(n), false),
+            CompValueRef::Str(s) => {
+                let (name, s, case_insensitive) = field.string_comparison(s);
+                (name, json!(s), case_insensitive)
+            }
+            CompValueRef::DateTime(dt) => {
+                let dt =
+                    dt.0.to_offset(UtcOffset::UTC)
+                        .format(&Rfc3339)
+                        .expect("Dates can be formatted");
+                (field.name.as_str(), json!(dt), false)
+            }
+        };
+        // Adds the `case_insensitive` option, which term-level queries support
+        let term_level = |kind: &str, value: Value| {
+            let mut options = json!({ "value": value });
+            if case_insensitive {
+                options["case_insensitive"] = json!(true);
+            }
+            json!({ kind: { name: options } })
+        };
+        let query = match op {
+            CompareOp::Equal => term_level("term", value),
+            CompareOp::NotEqual => not(term_level("term", value)),
+            CompareOp::Contains | CompareOp::StartsWith | CompareOp::EndsWith => {
+                let Value::String(s) = &value else {
+                    return Err(unsupported(path, op, literal));
+                };
+                match op {
+                    CompareOp::StartsWith => term_level("prefix", value),
+                    CompareOp::Contains => {
+                        term_level("wildcard", json!(format!("*{}*", escape_wildcard(s))))
+                    }
+                    _ => term_level("wildcard", json!(format!("*{}", escape_wildcard(s)))),
+                }
+            }
+            _ if value.is_boolean() => return Err(unsupported(path, op, literal)),
+            CompareOp::GreaterThan => json!({ "range": { name: { "gt": value } } }),
+            CompareOp::GreaterThanOrEqual => json!({ "range": { name: { "gte": value } } }),
+            CompareOp::LessThan => json!({ "range": { name: { "lt": value } } }),
+            CompareOp::LessThanOrEqual => json!({ "range": { name: { "lte": value } } }),
+        };
+        Ok(query)
+    }
+
+    fn query_inner(&self, filter: &FilterRef, scope: Option<AttrPathRef>) -> Result<Value, Error> {
+        Ok(match *filter {
+            FilterRef::Present(path) => {
+                let field = self.resolve(path, scope)?;
+                field.wrap(json!({ "exists": { "field": field.name } }))
+            }
+            FilterRef::Compare(path, op, literal) => {
+                let field = self.resolve(path, scope)?;
+                if field.nested.is_none() {
+                    return self.compare(&field, path, op, literal);
+                }
+                // A multi-valued attribute matches if any of its values match. Conversely,
+                // `ne` matches if none of the values are equal.
+                let (negate, op) = match (op, literal) {
+                    (CompareOp::Equal, CompValueRef::Null) => (true, CompareOp::NotEqual),
+                    (CompareOp::NotEqual, CompValueRef::Null) => (false, op),
+                    (CompareOp::NotEqual, _) => (true, CompareOp::Equal),
+                    _ => (false, op),
+                };
+                let query = field.wrap(self.compare(&field, path, op, literal)?);
+                if negate {
+                    not(query)
+                } else {
+                    query
+                }
+            }
+            FilterRef::Has(path, inner) => match (self.fields)(path) {
+                Some(SearchField::Nested { path: nested })
+                    if scope.is_none_or(|scope| !same_attribute(scope, path)) =>
+                {
+                    json!({
+                        "nested": {
+                            "path": nested,
+                            "query": self.query_inner(inner, Some(path))?,
+                        }
+                    })
+                }
+                _ => self.query_inner(inner, scope)?,
+            },
+            FilterRef::And(filters) | FilterRef::Or(filters) => {
+                let queries = filters
+                    .iter()
+                    .map(|filter| self.query_inner(filter, scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match filter {
+                    FilterRef::And(_) => json!({ "bool": { "filter": queries } }),
+                    _ => json!({ "bool": { "should": queries, "minimum_should_match": 1 } }),
+                }
+            }
+            FilterRef::Not(inner) => not(self.query_inner(inner, scope)?),
+        })
+    }
+
+    /// Translate a filter into a query.
+    pub fn query(&self, filter: &FilterRef) -> Result<Value, Error> {
+        self.query_inner(filter, None)
+    }
+
+    /// Translate a sort order into a sort clause. Resources which do not have the attribute
+    /// are sorted last. Multi-valued attributes are sorted by their smallest value.
+    pub fn sort(&self, sort_by: AttrPathRef, sort_order: SortOrder) -> Result<Value, Error> {
+        let field = self
+            .resolve(sort_by, None)
+            .map_err(|_| Error::invalid_path())?;
+        let name = match &field.normalized {
+            Some(normalized) if field.ignore_case() => normalized,
+            _ => &field.name,
+        };
+        let order = match sort_order {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        };
+        let mut options = json!({ "order": order, "missing": "_last", "mode": "min" });
+        if let Some(path) = &field.nested {
+            options["nested"] = json!({ "path": path });
+        }
+        Ok(json!({ name: options }))
+    }
+
+    /// Translate the arguments to `list` into the body of a search request, with `query`,
+    /// `sort`, `from` and `size` properties.
+    ///
+    /// Cursors are not translated, since they are specific to the resource manager. The body
+    /// enables `track_total_hits`, so that the `hits.total` property of the response holds
+    /// the exact number of matching resources rather than a lower bound.
+    pub fn search(&self, args: &ListResourceArgs) -> Result<Value, Error> {
+        let query = match &args.filter {
+            Some(filter) => self.query(filter)?,
+            None => json!({ "match_all": {} }),
+        };
+        let mut body = json!({
+            "query": query,
+            "from": args.start_index,
+            "size": args.count,
+            "track_total_hits": true,
+        });
+        if let Some(sort_by) = args.sort_by {
+            body["sort"] = json!([self.sort(sort_by, args.sort_order)?]);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,236 @@
+use bumpalo::Bump;
+use serde_json::{json, Value};
+
+use crate::{
+    error::ErrorType,
+    filter::{parse_filter, AttrPathRef},
+    manager::{ListResourceArgs, SortOrder},
+    schema::test_registry,
+};
+
+use super::{OpenSearchTranslator, SearchField};
+
+fn fields(path: AttrPathRef) -> Option<SearchField> {
+    match (path.urn, path.name, path.sub_attr) {
+        (None, "userName", None) => Some(SearchField::Field {
+            name: "userName".to_string(),
+            normalized: Some("userName.normalized".to_string()),
+        }),
+        (None, "externalId", None) => Some(SearchField::Field {
+            name: "externalId".to_string(),
+            normalized: Some("externalId.normalized".to_string()),
+        }),
+        (None, "title", None) => Some(SearchField::field("title")),
+        (None, "active", None) => Some(SearchField::field("active")),
+        (None, "loginCount", None) => Some(SearchField::field("loginCount")),
+        (None, "lastLogin", None) => Some(SearchField::field("lastLogin")),
+        (None, "emails", None) => Some(SearchField::Nested {
+            path: "emails".to_string(),
+        }),
+        (None, "emails", Some("value")) => Some(SearchField::Field {
+            name: "emails.value".to_string(),
+            normalized: Some("emails.value.normalized".to_string()),
+        }),
+        (None, "emails", Some("type")) => Some(SearchField::field("emails.type")),
+        (None, "emails", Some("primary")) => Some(SearchField::field("emails.primary")),
+        _ => None,
+    }
+}
+
+fn query(input: &str) -> Value {
+    let scope = Bump::new();
+    let registry = test_registry();
+    let mut filter = parse_filter(input).unwrap();
+    filter.coerce(&registry).unwrap();
+    let translator = OpenSearchTranslator::new(&registry, fields);
+    translator.query(&filter.as_ref(&scope)).unwrap()
+}
+
+#[test]
+fn test_query() {
+    let cases = [
+        (
+            r#"userName eq "BJensen""#,
+            json!({ "term": { "userName.normalized": { "value": "bjensen" } } }),
+        ),
+        (
+            r#"externalId eq "Babs""#,
+            json!({ "term": { "externalId": { "value": "Babs" } } }),
+        ),
+        (
+            r#"title eq "Tour Guide""#,
+            json!({ "term": { "title": { "value": "Tour Guide", "case_insensitive": true } } }),
+        ),
+        (
+            r#"userName ne "bjensen""#,
+            json!({ "bool": { "must_not": [
+                { "term": { "userName.normalized": { "value": "bjensen" } } }
+            ] } }),
+        ),
+        (
+            r#"userName sw "BJ""#,
+            json!({ "prefix": { "userName.normalized": { "value": "bj" } } }),
+        ),
+        (
+            r#"title co "a*b?""#,
+            json!({ "wildcard": { "title": { "value": r"*a\*b\?*", "case_insensitive": true } } }),
+        ),
+        (
+            r#"externalId ew "s""#,
+            json!({ "wildcard": { "externalId": { "value": "*s" } } }),
+        ),
+        ("title pr", json!({ "exists": { "field": "title" } })),
+        (
+            "title eq null",
+            json!({ "bool": { "must_not": [{ "exists": { "field": "title" } }] } }),
+        ),
+        (
+            "active eq true",
+            json!({ "term": { "active": { "value": true } } }),
+        ),
+        (
+            r#"loginCount ge "5""#,
+            json!({ "range": { "loginCount": { "gte": 5 } } }),
+        ),
+        (
+            r#"lastLogin lt "2011-05-13T06:42:34+02:00""#,
+            json!({ "range": { "lastLogin": { "lt": "2011-05-13T04:42:34Z" } } }),
+        ),
+        (
+            r#"title pr and (active eq false or not (loginCount gt 3))"#,
+            json!({ "bool": { "filter": [
+                { "exists": { "field": "title" } },
+                { "bool": {
+                    "should": [
+                        { "term": { "active": { "value": false } } },
+                        { "bool": { "must_not": [{ "range": { "loginCount": { "gt": 3 } } }] } }
+                    ],
+                    "minimum_should_match": 1
+                } }
+            ] } }),
+        ),
+        (
+            r#"emails co "@example.com""#,
+            json!({ "nested": {
+                "path": "emails",
+                "query": { "wildcard": { "emails.value.normalized": { "value": "*@example.com*" } } }
+            } }),
+        ),
+        (
+            "emails pr",
+            json!({ "nested": {
+                "path": "emails",
+                "query": { "exists": { "field": "emails.value" } }
+            } }),
+        ),
+        (
+            r#"emails.type ne "work""#,
+            json!({ "bool": { "must_not": [{ "nested": {
+                "path": "emails",
+                "query": { "term": { "emails.type": { "value": "work", "case_insensitive": true } } }
+            } }] } }),
+        ),
+        (
+            r#"emails[type eq "work" and primary eq true]"#,
+            json!({ "nested": {
+                "path": "emails",
+                "query": { "bool": { "filter": [
+                    { "term": { "emails.type": { "value": "work", "case_insensitive": true } } },
+                    { "term": { "emails.primary": { "value": true } } }
+                ] } }
+            } }),
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(query(input), expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_query_errors() {
+    let scope = Bump::new();
+    let registry = test_registry();
+    let translator = OpenSearchTranslator::new(&registry, fields);
+    let cases = [
+        (
+            r#"displayName eq "Babs""#,
+            "Filtering on `displayName` is not supported",
+        ),
+        (
+            "loginCount sw 5",
+            "Cannot compare `loginCount` with 5 using `sw`",
+        ),
+        (
+            "active gt false",
+            "Cannot compare `active` with false using `gt`",
+        ),
+    ];
+    for (input, expected) in cases {
+        let filter = parse_filter(input).unwrap();
+        let e = translator.query(&filter.as_ref(&scope)).unwrap_err();
+        assert!(matches!(e.scim_type, Some(ErrorType::InvalidFilter)));
+        assert_eq!(e.detail, expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_search() {
+    let scope = Bump::new();
+    let registry = test_registry();
+    let translator = OpenSearchTranslator::new(&registry, fields);
+    let path = |name, sub_attr| AttrPathRef {
+        urn: None,
+        name,
+        sub_attr,
+    };
+
+    let filter = parse_filter("title pr").unwrap();
+    let args = ListResourceArgs {
+        filter: Some(filter.as_ref(&scope)),
+        sort_by: Some(path("userName", None)),
+        sort_order: SortOrder::Descending,
+        start_index: 20,
+        count: 10,
+        ..Default::default()
+    };
+    assert_eq!(
+        translator.search(&args).unwrap(),
+        json!({
+            "query": { "exists": { "field": "title" } },
+            "sort": [{ "userName.normalized": { "order": "desc", "missing": "_last", "mode": "min" } }],
+            "from": 20,
+            "size": 10,
+            "track_total_hits": true
+        })
+    );
+
+    let args = ListResourceArgs {
+        count: 50,
+        ..Default::default()
+    };
+    assert_eq!(
+        translator.search(&args).unwrap(),
+        json!({
+            "query": { "match_all": {} },
+            "from": 0,
+            "size": 50,
+            "track_total_hits": true
+        })
+    );
+
+    assert_eq!(
+        translator
+            .sort(path("emails", None), SortOrder::Ascending)
+            .unwrap(),
+        json!({ "emails.value.normalized": {
+            "order": "asc",
+            "missing": "_last",
+            "mode": "min",
+            "nested": { "path": "emails" }
+        } })
+    );
+    let e = translator
+        .sort(path("displayName", None), SortOrder::Ascending)
+        .unwrap_err();
+    assert!(matches!(e.scim_type, Some(ErrorType::InvalidPath)));
+}
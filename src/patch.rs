@@ -0,0 +1,401 @@
+use cream_core::{Attribute, Type};
+use ijson::{IObject, IValue};
+
+use crate::{
+    filter::{AttrPathRef, FilterRef, ValuePathRef},
+    manager::{UpdateOp, UpdateResourceItem},
+    schema::SchemaRegistry,
+    Error,
+};
+
+// Finds the name of an existing key in an object, ignoring case, falling back to `name`.
+fn existing_key(obj: &IObject, name: &str) -> String {
+    obj.keys()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .map(|key| key.to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn is_empty(value: &IValue) -> bool {
+    value.is_null()
+        || value.as_array().is_some_and(|arr| arr.is_empty())
+        || value.as_object().is_some_and(|obj| obj.is_empty())
+}
+
+fn is_primary(value: &IValue) -> bool {
+    value
+        .as_object()
+        .and_then(|obj| {
+            obj.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("primary"))
+        })
+        .and_then(|(_, value)| value.to_bool())
+        .unwrap_or(false)
+}
+
+// At most one value of a multi-valued attribute may be primary. When a new primary value is
+// added, the flag is cleared on the others.
+fn clear_primary(values: &mut [IValue]) {
+    for value in values {
+        if let Some(obj) = value.as_object_mut() {
+            let key = existing_key(obj, "primary");
+            if obj
+                .get(key.as_str())
+                .is_some_and(|primary| primary.to_bool() == Some(true))
+            {
+                obj.insert(key, false);
+            }
+        }
+    }
+}
+
+fn as_values(value: &IValue) -> Vec<IValue> {
+    match value.as_array() {
+        Some(arr) => arr.iter().cloned().collect(),
+        None => vec![value.clone()],
+    }
+}
+
+// Merges the sub-attributes of `value` into a complex value.
+fn merge_object(target: &mut IValue, value: &IValue) -> Result<(), Error> {
+    let value = value.as_object().ok_or_else(|| Error::expected("object"))?;
+    if !target.is_object() {
+        *target = IObject::new().into();
+    }
+    let target = target.as_object_mut().expect("Just checked");
+    for (key, value) in value {
+        let key = existing_key(target, key);
+        target.insert(key, value.clone());
+    }
+    Ok(())
+}
+
+// Adds values to a multi-valued attribute, skipping any which are already present.
+fn add_values(target: &mut IValue, value: &IValue) {
+    let mut values: Vec<IValue> = match target.as_array() {
+        Some(arr) => arr.iter().cloned().collect(),
+        None => Vec::new(),
+    };
+    for value in as_values(value) {
+        if value.is_null() || values.contains(&value) {
+            continue;
+        }
+        if is_primary(&value) {
+            clear_primary(&mut values);
+        }
+        values.push(value);
+    }
+    *target = values.into();
+}
+
+// Whether a value of a multi-valued attribute is one of the values to be removed. Values are
+// matched either exactly, or by their `value` sub-attribute, since clients commonly send only
+// that (eg. `{"value": "<member id>"}`).
+fn is_removed(value: &IValue, removed: &[IValue]) -> bool {
+    let sub_value = |value: &IValue| {
+        value
+            .as_object()
+            .and_then(|obj| {
+                obj.iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("value"))
+            })
+            .map(|(_, value)| value.clone())
+    };
+    removed.iter().any(|removed| {
+        removed == value || sub_value(removed).is_some_and(|v| Some(v) == sub_value(value))
+    })
+}
+
+struct Target<'a> {
+    path: AttrPathRef<'a>,
+    attr: Option<&'a Attribute>,
+    filter: Option<FilterRef<'a>>,
+}
+
+impl Target<'_> {
+    fn multi_valued(&self, current: Option<&IValue>) -> bool {
+        self.attr.map_or_else(
+            || current.is_some_and(IValue::is_array),
+            |attr| attr.multi_valued,
+        )
+    }
+    fn complex(&self, value: &IValue) -> bool {
+        self.attr.map_or_else(
+            || value.is_object(),
+            |attr| matches!(attr.type_, Type::Complex),
+        )
+    }
+}
+
+// Returns the object containing the attributes of the schema which a path refers to, creating
+// it if necessary.
+fn container<'a>(
+    resource: &'a mut IObject,
+    urn: Option<&str>,
+    create: bool,
+) -> Option<&'a mut IObject> {
+    let Some(urn) = urn else {
+        return Some(resource);
+    };
+    let key = existing_key(resource, urn);
+    if create && !resource.get(key.as_str()).is_some_and(IValue::is_object) {
+        resource.insert(key.as_str(), IObject::new());
+    }
+    resource
+        .get_mut(key.as_str())
+        .and_then(IValue::as_object_mut)
+}
+
+fn apply_add(
+    resource: &mut IObject,
+    target: &Target,
+    value: &IValue,
+    schemas: &SchemaRegistry,
+    replace: bool,
+) -> Result<(), Error> {
+    let obj = container(resource, target.path.urn, true).expect("Container was created");
+    let key = existing_key(obj, target.path.name);
+    let current = obj.get(key.as_str());
+    let multi_valued = target.multi_valued(current);
+
+    // A value filter selects values which must already exist.
+    if let Some(filter) = target.filter {
+        let values = obj
+            .get_mut(key.as_str())
+            .and_then(IValue::as_array_mut)
+            .ok_or_else(Error::no_target)?;
+        let mut matched = false;
+        for element in values.iter_mut() {
+            if !filter.matches_value(target.path, element, schemas) {
+                continue;
+            }
+            matched = true;
+            match target.path.sub_attr {
+                Some(sub_attr) => {
+                    let element = element.as_object_mut().ok_or_else(Error::invalid_path)?;
+                    let sub_key = existing_key(element, sub_attr);
+                    element.insert(sub_key, value.clone());
+                }
+                None if replace => *element = value.clone(),
+                None => merge_object(element, value)?,
+            }
+        }
+        return if matched {
+            Ok(())
+        } else {
+            Err(Error::no_target())
+        };
+    }
+
+    let entry = obj.entry(key).or_insert(IValue::NULL);
+    match target.path.sub_attr {
+        // A sub-attribute of a multi-valued attribute is set on every value.
+        Some(sub_attr) if multi_valued => {
+            let values = entry.as_array_mut().ok_or_else(Error::no_target)?;
+            if values.is_empty() {
+                return Err(Error::no_target());
+            }
+            for element in values.iter_mut() {
+                let element = element.as_object_mut().ok_or_else(Error::invalid_path)?;
+                let sub_key = existing_key(element, sub_attr);
+                element.insert(sub_key, value.clone());
+            }
+        }
+        Some(sub_attr) => {
+            if !entry.is_object() {
+                *entry = IObject::new().into();
+            }
+            let element = entry.as_object_mut().expect("Just checked");
+            let sub_key = existing_key(element, sub_attr);
+            element.insert(sub_key, value.clone());
+        }
+        None if multi_valued && replace => {
+            *entry = IValue::from(Vec::<IValue>::new());
+            add_values(entry, value);
+        }
+        None if multi_valued => add_values(entry, value),
+        // Sub-attributes which are not specified are left unchanged.
+        None if target.complex(value) && value.is_object() => merge_object(entry, value)?,
+        None => *entry = value.clone(),
+    }
+    Ok(())
+}
+
+fn apply_remove(
+    resource: &mut IObject,
+    target: &Target,
+    value: &IValue,
+    schemas: &SchemaRegistry,
+) -> Result<(), Error> {
+    let Some(obj) = container(resource, target.path.urn, false) else {
+        return Ok(());
+    };
+    let key = existing_key(obj, target.path.name);
+    let Some(current) = obj.get_mut(key.as_str()) else {
+        return Ok(());
+    };
+
+    match (target.filter, target.path.sub_attr) {
+        (None, None) => {
+            // Removing specific values from a multi-valued attribute is a common extension.
+            let removed = if value.is_null() {
+                Vec::new()
+            } else {
+                as_values(value)
+            };
+            match current.as_array_mut() {
+                Some(values) if !removed.is_empty() => {
+                    *values = values
+                        .iter()
+                        .filter(|value| !is_removed(value, &removed))
+                        .cloned()
+                        .collect();
+                }
+                _ => *current = IValue::NULL,
+            }
+        }
+        (filter, sub_attr) => {
+            let matches = |element: &IValue| {
+                filter.is_none_or(|filter| filter.matches_value(target.path, element, schemas))
+            };
+            if let Some(values) = current.as_array_mut() {
+                if filter.is_some() && !values.iter().any(matches) {
+                    return Err(Error::no_target());
+                }
+                match sub_attr {
+                    Some(sub_attr) => {
+                        for element in values.iter_mut().filter(|element| matches(element)) {
+                            if let Some(element) = element.as_object_mut() {
+                                element.remove(existing_key(element, sub_attr).as_str());
+                            }
+                        }
+                    }
+                    None => {
+                        *values = values
+                            .iter()
+                            .filter(|element| !matches(element))
+                            .cloned()
+                            .collect();
+                    }
+                }
+            } else if let (None, Some(sub_attr), Some(element)) =
+                (filter, sub_attr, current.as_object_mut())
+            {
+                element.remove(existing_key(element, sub_attr).as_str());
+            } else if filter.is_some() {
+                return Err(Error::no_target());
+            }
+        }
+    }
+
+    if is_empty(current) {
+        obj.remove(key.as_str());
+    }
+    // Don't leave behind an empty extension object.
+    if let (Some(urn), true) = (target.path.urn, obj.is_empty()) {
+        let key = existing_key(resource, urn);
+        resource.remove(key.as_str());
+    }
+    Ok(())
+}
+
+fn apply_item(
+    resource: &mut IObject,
+    path: Option<ValuePathRef>,
+    op: UpdateOp,
+    schemas: &SchemaRegistry,
+) -> Result<(), Error> {
+    let Some(path) = path else {
+        // Without a path, the value contains the attributes to add or replace.
+        let value = match op {
+            UpdateOp::Add(value) | UpdateOp::Replace(value) => value,
+            UpdateOp::Remove(_) => return Err(Error::no_target()),
+        };
+        let obj = value.as_object().ok_or_else(|| Error::expected("object"))?;
+        for (key, value) in obj {
+            if let (Some(ext), Some(ext_obj)) = (schemas.extension(key), value.as_object()) {
+                for (key, value) in ext_obj {
+                    let path = AttrPathRef {
+                        urn: Some(&ext.id),
+                        name: key,
+                        sub_attr: None,
+                    };
+                    apply_item(
+                        resource,
+                        Some(ValuePathRef::Attr(path)),
+                        op.with_value(value),
+                        schemas,
+                    )?;
+                }
+            } else {
+                let path = AttrPathRef {
+                    urn: None,
+                    name: key,
+                    sub_attr: None,
+                };
+                apply_item(
+                    resource,
+                    Some(ValuePathRef::Attr(path)),
+                    op.with_value(value),
+                    schemas,
+                )?;
+            }
+        }
+        return Ok(());
+    };
+
+    let (path, filter) = match path {
+        ValuePathRef::Attr(path) => (path, None),
+        ValuePathRef::Filtered(path, filter) => (path, Some(filter)),
+    };
+    let target = Target {
+        path,
+        attr: schemas.attribute(AttrPathRef {
+            sub_attr: None,
+            ..path
+        }),
+        filter,
+    };
+    match op {
+        UpdateOp::Add(value) => apply_add(resource, &target, value, schemas, false),
+        UpdateOp::Replace(value) => apply_add(resource, &target, value, schemas, true),
+        UpdateOp::Remove(value) => apply_remove(resource, &target, value, schemas),
+    }
+}
+
+impl<'a> UpdateOp<'a> {
+    fn with_value(self, value: &'a IValue) -> Self {
+        match self {
+            Self::Add(_) => Self::Add(value),
+            Self::Replace(_) => Self::Replace(value),
+            Self::Remove(_) => Self::Remove(value),
+        }
+    }
+}
+
+/// Applies a list of updates to a resource, following the semantics of PATCH in RFC 7644
+/// Section 3.5.2. The schemas of the resource type determine which attributes are multi-valued.
+///
+/// - Adding to a multi-valued attribute merges the new values into the existing ones, skipping
+///   duplicates. Adding a `primary` value clears the flag on the other values.
+/// - Adding or replacing a complex attribute updates only the sub-attributes which are given.
+/// - Paths with a value filter, such as `emails[type eq "work"].value`, update or remove each
+///   matching value, and fail with a `noTarget` error if nothing matches.
+/// - Removing an attribute with a value removes only the matching values from a multi-valued
+///   attribute, which is a common extension used to remove group members.
+///
+/// Updates are applied in order. If an error is returned, the resource may have been partially
+/// updated, so it should be applied to a copy of the stored resource.
+pub fn apply_update(
+    resource: &mut IObject,
+    items: &[UpdateResourceItem],
+    schemas: &SchemaRegistry,
+) -> Result<(), Error> {
+    for item in items {
+        apply_item(resource, item.path, item.op, schemas)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
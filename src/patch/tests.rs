@@ -0,0 +1,232 @@
+use bumpalo::Bump;
+use ijson::{ijson, IObject, IValue};
+
+use crate::{
+    filter::parse_value_path,
+    manager::{UpdateOp, UpdateResourceItem},
+    schema::test_registry,
+    Error, ErrorType,
+};
+
+use super::apply_update;
+
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+fn resource() -> IObject {
+    ijson!({
+        "userName": "bjensen",
+        "name": { "givenName": "Barbara", "familyName": "Jensen" },
+        "emails": [
+            { "value": "bjensen@example.com", "type": "work", "primary": true },
+            { "value": "babs@jensen.org", "type": "home" }
+        ]
+    })
+    .into_object()
+    .unwrap()
+}
+
+enum Op {
+    Add,
+    Remove,
+    Replace,
+}
+
+// Applies a single operation to the test resource.
+fn apply(op: Op, path: Option<&str>, value: IValue) -> Result<IObject, Error> {
+    let scope = Bump::new();
+    let path = path.map(|path| scope.alloc(parse_value_path(path).unwrap()).as_ref(&scope));
+    let op = match op {
+        Op::Add => UpdateOp::Add(&value),
+        Op::Remove => UpdateOp::Remove(&value),
+        Op::Replace => UpdateOp::Replace(&value),
+    };
+    let mut resource = resource();
+    apply_update(
+        &mut resource,
+        &[UpdateResourceItem { path, op }],
+        &test_registry(),
+    )?;
+    Ok(resource)
+}
+
+#[test]
+fn test_add() {
+    let resource = apply(
+        Op::Add,
+        Some("emails"),
+        ijson!([
+            { "value": "babs@jensen.org", "type": "home" },
+            { "value": "barbara@example.org", "type": "other", "primary": true }
+        ]),
+    )
+    .unwrap();
+    assert_eq!(
+        resource["emails"],
+        ijson!([
+            { "value": "bjensen@example.com", "type": "work", "primary": false },
+            { "value": "babs@jensen.org", "type": "home" },
+            { "value": "barbara@example.org", "type": "other", "primary": true }
+        ])
+    );
+
+    let resource = apply(Op::Add, Some("name"), ijson!({ "givenName": "Babs" })).unwrap();
+    assert_eq!(
+        resource["name"],
+        ijson!({ "givenName": "Babs", "familyName": "Jensen" })
+    );
+
+    let resource = apply(Op::Add, Some("title"), ijson!("Tour Guide")).unwrap();
+    assert_eq!(resource["title"], ijson!("Tour Guide"));
+}
+
+#[test]
+fn test_add_without_path() {
+    let resource = apply(
+        Op::Add,
+        None,
+        ijson!({
+            "title": "Tour Guide",
+            "emails": [{ "value": "barbara@example.org" }],
+            ENTERPRISE: { "employeeNumber": "701984" }
+        }),
+    )
+    .unwrap();
+    assert_eq!(resource["title"], ijson!("Tour Guide"));
+    assert_eq!(resource["emails"].as_array().unwrap().len(), 3);
+    assert_eq!(resource[ENTERPRISE], ijson!({ "employeeNumber": "701984" }));
+}
+
+#[test]
+fn test_replace() {
+    let resource = apply(
+        Op::Replace,
+        Some("emails"),
+        ijson!([{ "value": "barbara@example.org" }]),
+    )
+    .unwrap();
+    assert_eq!(
+        resource["emails"],
+        ijson!([{ "value": "barbara@example.org" }])
+    );
+
+    let resource = apply(
+        Op::Replace,
+        Some("emails[type eq \"work\"].value"),
+        ijson!("barbara@example.com"),
+    )
+    .unwrap();
+    assert_eq!(
+        resource["emails"][0],
+        ijson!({ "value": "barbara@example.com", "type": "work", "primary": true })
+    );
+    assert_eq!(resource["emails"][1]["value"], ijson!("babs@jensen.org"));
+
+    let resource = apply(
+        Op::Replace,
+        Some("emails[type eq \"home\"]"),
+        ijson!({ "value": "barbara@jensen.org", "type": "home" }),
+    )
+    .unwrap();
+    assert_eq!(
+        resource["emails"][1],
+        ijson!({ "value": "barbara@jensen.org", "type": "home" })
+    );
+
+    let resource = apply(Op::Replace, Some("emails.type"), ijson!("other")).unwrap();
+    assert!(resource["emails"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|email| email["type"] == ijson!("other")));
+
+    let resource = apply(Op::Replace, Some("name.familyName"), ijson!("Smith")).unwrap();
+    assert_eq!(
+        resource["name"],
+        ijson!({ "givenName": "Barbara", "familyName": "Smith" })
+    );
+}
+
+#[test]
+fn test_no_target() {
+    let cases = [
+        (Op::Replace, Some("emails[type eq \"other\"].value")),
+        (Op::Add, Some("emails[type eq \"other\"]")),
+        (Op::Remove, Some("emails[type eq \"other\"]")),
+        (Op::Remove, None),
+    ];
+    for (op, path) in cases {
+        let err = apply(op, path, ijson!("barbara@example.org")).unwrap_err();
+        assert!(
+            matches!(err.scim_type, Some(ErrorType::NoTarget)),
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn test_remove() {
+    let resource = apply(Op::Remove, Some("emails[type eq \"work\"]"), IValue::NULL).unwrap();
+    assert_eq!(
+        resource["emails"],
+        ijson!([{ "value": "babs@jensen.org", "type": "home" }])
+    );
+
+    let resource = apply(
+        Op::Remove,
+        Some("emails[type eq \"work\"].primary"),
+        IValue::NULL,
+    )
+    .unwrap();
+    assert_eq!(
+        resource["emails"][0],
+        ijson!({ "value": "bjensen@example.com", "type": "work" })
+    );
+
+    let resource = apply(
+        Op::Remove,
+        Some("emails"),
+        ijson!([{ "value": "bjensen@example.com" }, { "value": "babs@jensen.org" }]),
+    )
+    .unwrap();
+    assert!(!resource.contains_key("emails"));
+
+    let resource = apply(Op::Remove, Some("name.givenName"), IValue::NULL).unwrap();
+    assert_eq!(resource["name"], ijson!({ "familyName": "Jensen" }));
+
+    let resource = apply(Op::Remove, Some("title"), IValue::NULL).unwrap();
+    assert_eq!(resource, self::resource());
+}
+
+#[test]
+fn test_extension() {
+    let path = format!("{}:manager.displayName", ENTERPRISE);
+    let resource = apply(Op::Add, Some(&path), ijson!("John Smith")).unwrap();
+    assert_eq!(
+        resource[ENTERPRISE],
+        ijson!({ "manager": { "displayName": "John Smith" } })
+    );
+
+    // Removing the last attribute of an extension removes the extension.
+    let scope = Bump::new();
+    let path = format!("{}:employeeNumber", ENTERPRISE);
+    let path = scope.alloc(parse_value_path(&path).unwrap()).as_ref(&scope);
+    let value = ijson!("701984");
+    let mut resource = self::resource();
+    apply_update(
+        &mut resource,
+        &[
+            UpdateResourceItem {
+                path: Some(path),
+                op: UpdateOp::Add(&value),
+            },
+            UpdateResourceItem {
+                path: Some(path),
+                op: UpdateOp::Remove(&IValue::NULL),
+            },
+        ],
+        &test_registry(),
+    )
+    .unwrap();
+    assert_eq!(resource, self::resource());
+}
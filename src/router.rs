@@ -10,14 +10,26 @@ use axum::{
 use crate::{error::Error, state::Cream};
 
 mod args;
+mod bulk;
+mod etag;
+mod me;
 mod meta;
+mod mutability;
+mod pagination;
+mod paths;
 mod retrieve;
+#[cfg(test)]
+mod testing;
 mod update;
+mod validate;
 
 impl Cream {
     /// Build an Axum router for the `Cream` instance.
     pub fn router(&self) -> Router {
-        let mut router = meta::router().route("/.search", post(retrieve::search_root));
+        let mut router = meta::router()
+            .merge(me::router())
+            .route("/.search", post(retrieve::search_root))
+            .route("/Bulk", post(bulk::bulk));
 
         for s in self.0.resource_types.values() {
             router = router.nest(
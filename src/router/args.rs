@@ -21,8 +21,9 @@ pub(crate) struct ListResourcesArgs {
     #[serde(default)]
     pub(crate) sort_order: SortOrder,
     // Pagination
-    pub(crate) start_index: Option<usize>,
-    pub(crate) count: Option<usize>,
+    pub(crate) start_index: Option<i64>,
+    pub(crate) count: Option<i64>,
+    pub(crate) cursor: Option<String>,
     // Selection
     #[serde(default, deserialize_with = "deserialize_multistring")]
     pub(crate) attributes: Vec<String>,
@@ -30,7 +31,7 @@ pub(crate) struct ListResourcesArgs {
     pub(crate) excluded_attributes: Vec<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct GetResourcesArgs {
     // Selection
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::State,
+    http::{request::Parts, StatusCode},
+    response::IntoResponse,
+};
+use bytes::Bytes;
+use cream_core::Reference;
+use ijson::{DestructuredMut, IObject, IValue};
+
+use crate::{
+    bulk::{BulkMethod, BulkOperation, BulkOperationResponse, BulkRequest, BulkResponse},
+    error::ErrorType,
+    json::Json,
+    state::ResourceTypeState,
+    Cream, Error,
+};
+
+use super::{
+    args::{GetResourcesArgs, PatchResourceArgs},
+    etag::{resource_version, EntityTags},
+    update::{
+        create_resource_inner, delete_resource_inner, patch_resource_inner, put_resource_inner,
+    },
+};
+
+const BULK_ID_PREFIX: &str = "bulkId:";
+
+// Collects every `bulkId:` reference in the path or data of an operation.
+fn referenced_bulk_ids(operation: &BulkOperation) -> Vec<&str> {
+    fn visit<'a>(value: &'a IValue, result: &mut Vec<&'a str>) {
+        if let Some(s) = value.as_string() {
+            if let Some(bulk_id) = s.strip_prefix(BULK_ID_PREFIX) {
+                result.push(bulk_id);
+            }
+        } else if let Some(arr) = value.as_array() {
+            for item in arr {
+                visit(item, result);
+            }
+        } else if let Some(obj) = value.as_object() {
+            for item in obj.values() {
+                visit(item, result);
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    if let Some((_, bulk_id)) = operation.path.split_once(BULK_ID_PREFIX) {
+        result.push(bulk_id);
+    }
+    if let Some(data) = &operation.data {
+        visit(data, &mut result);
+    }
+    result
+}
+
+// Replaces every `bulkId:` reference in a value with the ID of the resource it refers to.
+fn resolve_bulk_ids(value: &mut IValue, resolved: &HashMap<String, String>) {
+    match value.destructure_mut() {
+        DestructuredMut::String(s) => {
+            if let Some(id) = s
+                .strip_prefix(BULK_ID_PREFIX)
+                .and_then(|bulk_id| resolved.get(bulk_id))
+            {
+                *s = id.as_str().into();
+            }
+        }
+        DestructuredMut::Array(arr) => {
+            for item in arr.iter_mut() {
+                resolve_bulk_ids(item, resolved);
+            }
+        }
+        DestructuredMut::Object(obj) => {
+            for item in obj.values_mut() {
+                resolve_bulk_ids(item, resolved);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn resolve_path(path: &str, resolved: &HashMap<String, String>) -> String {
+    match path.split_once(BULK_ID_PREFIX) {
+        Some((prefix, bulk_id)) => match resolved.get(bulk_id) {
+            Some(id) => format!("{}{}", prefix, id),
+            None => path.to_string(),
+        },
+        None => path.to_string(),
+    }
+}
+
+// Determines the order in which operations should be processed, such that operations referring
+// to a `bulkId` are processed after the operation which creates that resource. Operations which
+// can never be resolved (due to a circular reference) are placed at the end.
+fn schedule_operations(operations: &[BulkOperation]) -> Vec<usize> {
+    let mut created_by: HashMap<&str, usize> = HashMap::new();
+    for (i, operation) in operations.iter().enumerate() {
+        if let (BulkMethod::Post, Some(bulk_id)) = (operation.method, &operation.bulk_id) {
+            created_by.entry(bulk_id).or_insert(i);
+        }
+    }
+
+    let mut order = Vec::new();
+    let mut done = vec![false; operations.len()];
+    let mut pending: Vec<usize> = (0..operations.len()).collect();
+    loop {
+        let mut remaining = Vec::new();
+        for &i in &pending {
+            // References to unknown bulkIds cannot be satisfied by waiting, so
+            // only wait on bulkIds which are created by another operation.
+            let ready = referenced_bulk_ids(&operations[i])
+                .into_iter()
+                .filter_map(|bulk_id| created_by.get(bulk_id))
+                .all(|&j| j != i && done[j]);
+            if ready {
+                done[i] = true;
+                order.push(i);
+            } else {
+                remaining.push(i);
+            }
+        }
+        if remaining.len() == pending.len() {
+            order.extend(remaining);
+            return order;
+        }
+        pending = remaining;
+    }
+}
+
+fn find_resource_type<'a>(
+    state: &'a Cream,
+    path: &str,
+) -> Result<(&'a ResourceTypeState, Option<String>), Error> {
+    for rts in state.0.resource_types.values() {
+        if let Some(rest) = path.strip_prefix(rts.resource_type.endpoint.as_str()) {
+            if rest.is_empty() {
+                return Ok((rts, None));
+            } else if let Some(id) = rest.strip_prefix('/') {
+                if !id.is_empty() && !id.contains('/') {
+                    return Ok((rts, Some(id.to_string())));
+                }
+            }
+        }
+    }
+    Err(Error::new(
+        StatusCode::BAD_REQUEST,
+        Some(ErrorType::InvalidPath),
+        format!("Invalid bulk operation path: {:?}", path),
+    ))
+}
+
+fn expect_object(data: Option<IValue>) -> Result<IObject, Error> {
+    data.and_then(|data| data.into_object().ok())
+        .ok_or_else(|| Error::expected("object in bulk operation data"))
+}
+
+fn expect_id(id: Option<String>) -> Result<String, Error> {
+    id.ok_or_else(|| {
+        Error::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::InvalidPath),
+            "Bulk operation path must refer to a resource".to_string(),
+        )
+    })
+}
+
+struct OperationResult {
+    status: StatusCode,
+    id: Option<String>,
+    resource: Option<IObject>,
+}
+
+async fn process_operation<'a>(
+    state: &'a Cream,
+    parts: &Parts,
+    operation: BulkOperation,
+    resolved: &HashMap<String, String>,
+) -> Result<(&'a str, OperationResult), Error> {
+    if let Some(bulk_id) = referenced_bulk_ids(&operation)
+        .into_iter()
+        .find(|bulk_id| !resolved.contains_key(*bulk_id))
+    {
+        return Err(Error::new(
+            StatusCode::CONFLICT,
+            Some(ErrorType::InvalidValue),
+            format!("Unable to resolve reference to bulkId {:?}", bulk_id),
+        ));
+    }
+
+    let path = resolve_path(&operation.path, resolved);
+    let mut data = operation.data;
+    if let Some(data) = &mut data {
+        resolve_bulk_ids(data, resolved);
+    }
+
+    let (rts, id) = find_resource_type(state, &path)?;
+    let endpoint = rts.resource_type.endpoint.as_str();
+    let args = GetResourcesArgs::default();
+    // The version of a bulk operation acts like an `If-Match` header.
+    let if_match = operation
+        .version
+        .filter(|_| state.0.config.etag.supported)
+        .map(|version| EntityTags::Tags(vec![version]));
+
+    let result = match operation.method {
+        BulkMethod::Post => {
+            if id.is_some() {
+                return Err(Error::new(
+                    StatusCode::BAD_REQUEST,
+                    Some(ErrorType::InvalidPath),
+                    "Bulk POST operation must refer to a resource endpoint".to_string(),
+                ));
+            }
+            if operation.bulk_id.is_none() {
+                return Err(Error::new(
+                    StatusCode::BAD_REQUEST,
+                    Some(ErrorType::InvalidValue),
+                    "Bulk POST operation must have a bulkId".to_string(),
+                ));
+            }
+            let resource =
+                create_resource_inner(state, parts, rts, &args, expect_object(data)?).await?;
+            OperationResult {
+                status: StatusCode::CREATED,
+                id: resource
+                    .get("id")
+                    .and_then(|id| id.as_string())
+                    .map(|id| id.to_string()),
+                resource: Some(resource),
+            }
+        }
+        BulkMethod::Put => {
+            let id = expect_id(id)?;
+            let resource = put_resource_inner(
+                state,
+                parts,
+                rts,
+                &args,
+                id.clone(),
+                expect_object(data)?,
+                if_match.as_ref(),
+            )
+            .await?;
+            OperationResult {
+                status: StatusCode::OK,
+                id: Some(id),
+                resource: Some(resource),
+            }
+        }
+        BulkMethod::Patch => {
+            let id = expect_id(id)?;
+            let body: PatchResourceArgs =
+                ijson::from_value(&data.unwrap_or_default()).map_err(|e| {
+                    Error::new(
+                        StatusCode::BAD_REQUEST,
+                        Some(ErrorType::InvalidSyntax),
+                        e.to_string(),
+                    )
+                })?;
+            let resource = patch_resource_inner(
+                state,
+                parts,
+                rts,
+                &args,
+                id.clone(),
+                body,
+                if_match.as_ref(),
+            )
+            .await?;
+            OperationResult {
+                status: StatusCode::OK,
+                id: Some(id),
+                resource: Some(resource),
+            }
+        }
+        BulkMethod::Delete => {
+            let id = expect_id(id)?;
+            delete_resource_inner(parts, rts, &id, if_match.as_ref()).await?;
+            OperationResult {
+                status: StatusCode::NO_CONTENT,
+                id: Some(id),
+                resource: None,
+            }
+        }
+    };
+    Ok((endpoint, result))
+}
+
+pub(crate) async fn bulk(
+    State(state): State<Cream>,
+    parts: Parts,
+    body: Bytes,
+) -> Result<impl IntoResponse, Error> {
+    let config = &state.0.config.bulk;
+    if !config.supported {
+        return Err(Error::new(
+            StatusCode::NOT_IMPLEMENTED,
+            None,
+            "Bulk operations are not supported".to_string(),
+        ));
+    }
+    if body.len() > usize::try_from(config.max_payload_size).unwrap_or_default() {
+        return Err(Error::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            None,
+            format!(
+                "The size of the bulk operation exceeds the maxPayloadSize ({})",
+                config.max_payload_size
+            ),
+        ));
+    }
+
+    let request: BulkRequest = serde_json::from_slice(&body).map_err(|e| {
+        Error::new(
+            StatusCode::BAD_REQUEST,
+            Some(ErrorType::InvalidSyntax),
+            e.to_string(),
+        )
+    })?;
+    if request.operations.len() > usize::try_from(config.max_operations).unwrap_or_default() {
+        return Err(Error::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            None,
+            format!(
+                "The number of bulk operations exceeds the maxOperations ({})",
+                config.max_operations
+            ),
+        ));
+    }
+
+    let order = schedule_operations(&request.operations);
+    let mut operations: Vec<_> = request.operations.into_iter().map(Some).collect();
+    let mut resolved = HashMap::new();
+    let mut responses = Vec::new();
+    let mut errors = 0;
+
+    for i in order {
+        if request
+            .fail_on_errors
+            .is_some_and(|fail_on_errors| errors >= fail_on_errors)
+        {
+            break;
+        }
+        let operation = operations[i]
+            .take()
+            .expect("Each operation is processed once");
+        let method = operation.method;
+        let bulk_id = operation.bulk_id.clone();
+
+        let result = process_operation(&state, &parts, operation, &resolved).await;
+        responses.push(match result {
+            Ok((endpoint, result)) => {
+                if let (Some(bulk_id), Some(id)) = (&bulk_id, &result.id) {
+                    if method == BulkMethod::Post {
+                        resolved.insert(bulk_id.clone(), id.clone());
+                    }
+                }
+                BulkOperationResponse {
+                    method,
+                    bulk_id,
+                    version: result
+                        .resource
+                        .as_ref()
+                        .and_then(resource_version)
+                        .map(str::to_string),
+                    location: result
+                        .id
+                        .map(|id| Reference::new_relative(&format!("{}/{}", endpoint, id))),
+                    status: result.status,
+                    response: None,
+                }
+            }
+            Err(error) => {
+                errors += 1;
+                BulkOperationResponse {
+                    method,
+                    bulk_id,
+                    version: None,
+                    location: None,
+                    status: error.status,
+                    response: Some(error),
+                }
+            }
+        });
+    }
+
+    Ok(Json(BulkResponse {
+        schemas: Default::default(),
+        operations: responses,
+    }))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use ijson::ijson;
+use serde_json::{json, Value};
+
+use crate::{
+    bulk::BulkRequest,
+    router::testing::{config, request, send, Users},
+    CreamBuilder,
+};
+
+use super::{referenced_bulk_ids, resolve_bulk_ids, resolve_path, schedule_operations};
+
+fn parse_request(value: serde_json::Value) -> BulkRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_referenced_bulk_ids() {
+    let request = parse_request(serde_json::json!({
+        "Operations": [{
+            "method": "PATCH",
+            "path": "/Groups/bulkId:group",
+            "data": {
+                "Operations": [{
+                    "op": "add",
+                    "path": "members",
+                    "value": [{ "value": "bulkId:alice" }, { "value": "123" }]
+                }]
+            }
+        }]
+    }));
+
+    assert_eq!(
+        referenced_bulk_ids(&request.operations[0]),
+        vec!["group", "alice"]
+    );
+}
+
+#[test]
+fn test_resolve_bulk_ids() {
+    let resolved = HashMap::from([("alice".to_string(), "1".to_string())]);
+
+    let mut value = ijson!({
+        "displayName": "Admins",
+        "members": [{ "value": "bulkId:alice" }, { "value": "bulkId:bob" }]
+    });
+    resolve_bulk_ids(&mut value, &resolved);
+    assert_eq!(
+        value,
+        ijson!({
+            "displayName": "Admins",
+            "members": [{ "value": "1" }, { "value": "bulkId:bob" }]
+        })
+    );
+
+    assert_eq!(resolve_path("/Users/bulkId:alice", &resolved), "/Users/1");
+    assert_eq!(
+        resolve_path("/Users/bulkId:bob", &resolved),
+        "/Users/bulkId:bob"
+    );
+}
+
+#[test]
+fn test_schedule_operations() {
+    // The group is created before the user it references, so must be deferred.
+    let request = parse_request(serde_json::json!({
+        "Operations": [
+            {
+                "method": "POST",
+                "path": "/Groups",
+                "bulkId": "group",
+                "data": { "members": [{ "value": "bulkId:alice" }] }
+            },
+            {
+                "method": "POST",
+                "path": "/Users",
+                "bulkId": "alice",
+                "data": { "userName": "alice" }
+            },
+            {
+                "method": "DELETE",
+                "path": "/Users/bulkId:unknown"
+            }
+        ]
+    }));
+    assert_eq!(schedule_operations(&request.operations), vec![1, 2, 0]);
+
+    // Circular references can never be resolved, and are processed last.
+    let request = parse_request(serde_json::json!({
+        "Operations": [
+            {
+                "method": "POST",
+                "path": "/Groups",
+                "bulkId": "a",
+                "data": { "members": [{ "value": "bulkId:b" }] }
+            },
+            {
+                "method": "POST",
+                "path": "/Groups",
+                "bulkId": "b",
+                "data": { "members": [{ "value": "bulkId:a" }] }
+            },
+            {
+                "method": "POST",
+                "path": "/Users",
+                "data": { "userName": "alice" }
+            }
+        ]
+    }));
+    assert_eq!(schedule_operations(&request.operations), vec![2, 0, 1]);
+}
+
+async fn bulk(builder: CreamBuilder, body: Value) -> (StatusCode, Value) {
+    let (status, _, body) = send(builder, request("POST", "/Bulk", Some(body))).await;
+    (status, body)
+}
+
+// The method, bulkId and status of each operation in a bulk response.
+fn statuses(body: &Value) -> Vec<(&str, Option<&str>, &str)> {
+    body["Operations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|operation| {
+            (
+                operation["method"].as_str().unwrap(),
+                operation["bulkId"].as_str(),
+                operation["status"].as_str().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_bulk() {
+    let users = Users::new();
+    let (status, body) = bulk(
+        users.builder(),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+            "Operations": [
+                {
+                    "method": "PATCH",
+                    "path": "/Users/bulkId:alice",
+                    "data": {
+                        "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                        "Operations": [{ "op": "add", "path": "displayName", "value": "Alice" }]
+                    }
+                },
+                {
+                    "method": "POST",
+                    "path": "/Users",
+                    "bulkId": "alice",
+                    "data": { "userName": "alice" }
+                }
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        statuses(&body),
+        [("POST", Some("alice"), "201"), ("PATCH", None, "200")]
+    );
+    let location = body["Operations"][0]["location"].as_str().unwrap();
+    assert_eq!(body["Operations"][1]["location"], location);
+
+    let id = location.rsplit('/').next().unwrap();
+    let (_, _, user) = send(
+        users.builder(),
+        request("GET", &format!("/Users/{}", id), None),
+    )
+    .await;
+    assert_eq!(user["displayName"], "Alice");
+}
+
+#[tokio::test]
+async fn test_bulk_fail_on_errors() {
+    let operations = json!([
+        {
+            "method": "POST",
+            "path": "/Users",
+            "bulkId": "nobody",
+            "data": { "displayName": "No userName" }
+        },
+        { "method": "DELETE", "path": "/Users/missing" },
+        { "method": "POST", "path": "/Users", "bulkId": "alice", "data": { "userName": "alice" } }
+    ]);
+    let (status, body) = bulk(
+        Users::new().builder(),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+            "failOnErrors": 2,
+            "Operations": operations,
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        statuses(&body),
+        [("POST", Some("nobody"), "400"), ("DELETE", None, "404")]
+    );
+    assert_eq!(
+        body["Operations"][0]["response"]["scimType"],
+        "invalidValue"
+    );
+
+    // Without `failOnErrors`, every operation is attempted.
+    let (_, body) = bulk(
+        Users::new().builder(),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+            "Operations": operations,
+        }),
+    )
+    .await;
+    assert_eq!(
+        statuses(&body),
+        [
+            ("POST", Some("nobody"), "400"),
+            ("DELETE", None, "404"),
+            ("POST", Some("alice"), "201")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_bulk_post_without_bulk_id() {
+    let (status, body) = bulk(
+        Users::new().builder(),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+            "Operations": [
+                { "method": "POST", "path": "/Users", "data": { "userName": "alice" } }
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(statuses(&body), [("POST", None, "400")]);
+    let response = &body["Operations"][0]["response"];
+    assert_eq!(response["scimType"], "invalidValue");
+    assert_eq!(response["detail"], "Bulk POST operation must have a bulkId");
+}
+
+#[tokio::test]
+async fn test_bulk_unresolved_bulk_id() {
+    let (status, body) = bulk(
+        Users::new().builder(),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+            "Operations": [
+                {
+                    "method": "POST",
+                    "path": "/Users",
+                    "bulkId": "bob",
+                    "data": { "userName": "bob", "displayName": "bulkId:carol" }
+                },
+                { "method": "DELETE", "path": "/Users/bulkId:dave" }
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        statuses(&body),
+        [("POST", Some("bob"), "409"), ("DELETE", None, "409")]
+    );
+    assert_eq!(
+        body["Operations"][1]["response"]["detail"],
+        r#"Unable to resolve reference to bulkId "dave""#
+    );
+}
+
+#[tokio::test]
+async fn test_bulk_not_supported() {
+    let mut config = config();
+    config.bulk.supported = false;
+    let (status, body) = bulk(
+        Users::new().builder_with(config),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:BulkRequest"],
+            "Operations": []
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(body["status"], "501");
+}
@@ -0,0 +1,188 @@
+use axum::{
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{AppendHeaders, IntoResponse},
+};
+use ijson::IObject;
+
+use crate::{json::Json, manager, state::ResourceTypeState, Cream, Error};
+
+/// The parsed value of an `If-Match` or `If-None-Match` header.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum EntityTags {
+    /// Matches any version of an existing resource.
+    Any,
+    /// Matches any of the listed versions.
+    Tags(Vec<String>),
+}
+
+// Splits an entity tag into whether it is weak and its opaque value. Unquoted versions are
+// accepted, since managers may report them that way.
+fn opaque_tag(tag: &str) -> (bool, &str) {
+    let tag = tag.trim();
+    let (weak, tag) = match tag.strip_prefix("W/") {
+        Some(tag) => (true, tag),
+        None => (false, tag),
+    };
+    let tag = tag
+        .strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .unwrap_or(tag);
+    (weak, tag)
+}
+
+// Splits the next entity tag from a comma-separated list, returning the tag and the rest of
+// the list. A quoted tag may itself contain commas.
+fn next_tag(list: &str) -> Option<(&str, &str)> {
+    let quoted = list.strip_prefix("W/").unwrap_or(list);
+    let end = match quoted.strip_prefix('"') {
+        Some(rest) => list.len() - rest.len() + rest.find('"')? + 1,
+        None => list.find(',').unwrap_or(list.len()),
+    };
+    let (tag, rest) = list.split_at(end);
+    let rest = rest.trim_start();
+    match rest.strip_prefix(',') {
+        Some(rest) => Some((tag.trim_end(), rest)),
+        None if rest.is_empty() => Some((tag.trim_end(), rest)),
+        None => None,
+    }
+}
+
+impl EntityTags {
+    /// Parses the `If-Match` or `If-None-Match` header, if ETags are supported.
+    pub(crate) fn from_headers(
+        state: &Cream,
+        headers: &HeaderMap,
+        name: HeaderName,
+    ) -> Result<Option<Self>, Error> {
+        if !state.0.config.etag.supported {
+            return Ok(None);
+        }
+        Self::parse(headers, name)
+    }
+
+    fn parse(headers: &HeaderMap, name: HeaderName) -> Result<Option<Self>, Error> {
+        let invalid = || {
+            Error::new(
+                StatusCode::BAD_REQUEST,
+                None,
+                format!("Invalid `{}` header", name),
+            )
+        };
+        let mut tags = Vec::new();
+        for value in headers.get_all(&name) {
+            let mut list = value.to_str().map_err(|_| invalid())?.trim();
+            while !list.is_empty() {
+                let (tag, rest) = next_tag(list).ok_or_else(invalid)?;
+                if tag == "*" {
+                    return Ok(Some(Self::Any));
+                } else if !tag.is_empty() {
+                    tags.push(tag.to_string());
+                }
+                list = rest.trim_start();
+            }
+        }
+        Ok((!tags.is_empty()).then_some(Self::Tags(tags)))
+    }
+
+    /// Whether the version matches using the strong comparison function from RFC 7232, as
+    /// `If-Match` requires. Weak tags never match.
+    pub(crate) fn matches_strong(&self, version: Option<&str>) -> bool {
+        self.matches(version, |(tag_weak, tag), (version_weak, version)| {
+            !tag_weak && !version_weak && tag == version
+        })
+    }
+
+    /// Whether the version matches using the weak comparison function from RFC 7232, as
+    /// `If-None-Match` requires.
+    pub(crate) fn matches_weak(&self, version: Option<&str>) -> bool {
+        self.matches(version, |(_, tag), (_, version)| tag == version)
+    }
+
+    fn matches(
+        &self,
+        version: Option<&str>,
+        compare: impl Fn((bool, &str), (bool, &str)) -> bool,
+    ) -> bool {
+        match (self, version) {
+            (_, None) => false,
+            (Self::Any, Some(_)) => true,
+            (Self::Tags(tags), Some(version)) => tags
+                .iter()
+                .any(|tag| compare(opaque_tag(tag), opaque_tag(version))),
+        }
+    }
+}
+
+pub(crate) fn resource_version(resource: &IObject) -> Option<&str> {
+    resource
+        .get("meta")
+        .and_then(|meta| meta.get("version"))
+        .and_then(|version| version.as_string())
+        .map(|version| version.as_str())
+}
+
+/// Checks an `If-Match` precondition against the current state of a resource. Returns the
+/// version which the manager should expect when applying the change.
+pub(crate) fn check_version(
+    current: &IObject,
+    if_match: &EntityTags,
+) -> Result<Option<String>, Error> {
+    let version = resource_version(current);
+    if !if_match.matches_strong(version) {
+        return Err(Error::precondition_failed());
+    }
+    Ok(match if_match {
+        EntityTags::Any => None,
+        EntityTags::Tags(_) => version.map(str::to_string),
+    })
+}
+
+/// Checks an `If-Match` precondition against the current version of a resource. Returns the
+/// version which the manager should expect when applying the change.
+pub(crate) async fn check_if_match(
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    id: &str,
+    if_match: Option<&EntityTags>,
+) -> Result<Option<String>, Error> {
+    let Some(if_match) = if_match else {
+        return Ok(None);
+    };
+    let current = rts
+        .manager
+        .get(
+            parts,
+            manager::GetResourceArgs {
+                id: id.to_string(),
+                optional_attributes: &[],
+            },
+        )
+        .await?;
+    check_version(&current, if_match)
+}
+
+/// The `ETag` header for a resource. Versions which are not already entity tags are quoted.
+pub(crate) fn etag_header(
+    state: &Cream,
+    resource: &IObject,
+) -> AppendHeaders<Option<(HeaderName, HeaderValue)>> {
+    let etag = resource_version(resource)
+        .filter(|_| state.0.config.etag.supported)
+        .and_then(|version| {
+            let quoted = version.strip_prefix("W/").unwrap_or(version);
+            if quoted.len() >= 2 && quoted.starts_with('"') && quoted.ends_with('"') {
+                HeaderValue::from_str(version).ok()
+            } else {
+                HeaderValue::from_str(&format!("\"{}\"", version)).ok()
+            }
+        });
+    AppendHeaders(etag.map(|etag| (header::ETAG, etag)))
+}
+
+/// Serializes a resource, including its version in the `ETag` header.
+pub(crate) fn with_etag(state: &Cream, resource: IObject) -> impl IntoResponse {
+    (etag_header(state, &resource), Json(resource))
+}
+
+#[cfg(test)]
+mod tests;
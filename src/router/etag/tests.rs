@@ -0,0 +1,190 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::AppendHeaders,
+};
+use serde_json::json;
+
+use crate::router::testing::{config, request, send, Users};
+
+use super::{etag_header, EntityTags};
+
+fn parse(values: &[&[u8]]) -> Result<Option<EntityTags>, String> {
+    let mut headers = HeaderMap::new();
+    for value in values {
+        headers.append(header::IF_MATCH, HeaderValue::from_bytes(value).unwrap());
+    }
+    EntityTags::parse(&headers, header::IF_MATCH).map_err(|e| e.detail)
+}
+
+fn tags(tags: &[&str]) -> Result<Option<EntityTags>, String> {
+    Ok(Some(EntityTags::Tags(
+        tags.iter().map(|tag| tag.to_string()).collect(),
+    )))
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse(&[]), Ok(None));
+    assert_eq!(parse(&[b""]), Ok(None));
+    assert_eq!(parse(&[b"*"]), Ok(Some(EntityTags::Any)));
+    assert_eq!(parse(&[br#"W/"1", *"#]), Ok(Some(EntityTags::Any)));
+    assert_eq!(parse(&[br#"W/"1""#]), tags(&[r#"W/"1""#]));
+    assert_eq!(
+        parse(&[br#" "1" ,W/"2","#, br#""3""#]),
+        tags(&[r#""1""#, r#"W/"2""#, r#""3""#])
+    );
+    // Commas are allowed inside a quoted tag.
+    assert_eq!(
+        parse(&[br#"W/"a,b", "c""#]),
+        tags(&[r#"W/"a,b""#, r#""c""#])
+    );
+    // Unquoted tags are accepted for lenience.
+    assert_eq!(parse(&[b"1, 2"]), tags(&["1", "2"]));
+
+    let invalid = Err("Invalid `if-match` header".to_string());
+    assert_eq!(parse(&[br#""1"#]), invalid);
+    assert_eq!(parse(&[br#""1" "2""#]), invalid);
+    assert_eq!(parse(&["\"caf\u{e9}\"".as_bytes()]), invalid);
+}
+
+#[test]
+fn test_matches() {
+    let strong = EntityTags::Tags(vec![r#""1""#.to_string()]);
+    let weak = EntityTags::Tags(vec![r#"W/"1""#.to_string(), r#"W/"2""#.to_string()]);
+
+    // Under the weak comparison function, a weak and a strong tag with the same opaque value
+    // match.
+    assert!(strong.matches_weak(Some(r#""1""#)));
+    assert!(strong.matches_weak(Some(r#"W/"1""#)));
+    assert!(weak.matches_weak(Some(r#""1""#)));
+    assert!(weak.matches_weak(Some(r#"W/"2""#)));
+    assert!(!strong.matches_weak(Some(r#"W/"2""#)));
+    assert!(!weak.matches_weak(Some(r#""3""#)));
+
+    // Under the strong comparison function, weak tags never match.
+    assert!(strong.matches_strong(Some(r#""1""#)));
+    assert!(strong.matches_strong(Some("1")));
+    assert!(!strong.matches_strong(Some(r#"W/"1""#)));
+    assert!(!weak.matches_strong(Some(r#""1""#)));
+    assert!(!weak.matches_strong(Some(r#"W/"1""#)));
+
+    // A resource without a version never matches, not even `*`.
+    assert!(EntityTags::Any.matches_strong(Some(r#"W/"1""#)));
+    assert!(!EntityTags::Any.matches_strong(None));
+    assert!(!EntityTags::Any.matches_weak(None));
+    assert!(!strong.matches_weak(None));
+}
+
+#[test]
+fn test_etag_header() {
+    let state = Users::new().builder().build();
+    let etag = |version: &str| {
+        let resource = json!({ "meta": { "version": version } });
+        let AppendHeaders(header) = etag_header(
+            &state,
+            &ijson::to_value(resource).unwrap().into_object().unwrap(),
+        );
+        header.map(|(_, value)| value)
+    };
+    assert_eq!(etag(r#""1""#).unwrap(), r#""1""#);
+    assert_eq!(etag(r#"W/"1""#).unwrap(), r#"W/"1""#);
+    // Bare versions are quoted, so that the header is a valid entity tag.
+    assert_eq!(etag("3").unwrap(), r#""3""#);
+    assert_eq!(etag("W/3").unwrap(), r#""W/3""#);
+}
+
+#[tokio::test]
+async fn test_preconditions() {
+    let users = Users::new();
+    let (status, headers, body) = send(
+        users.builder(),
+        request("POST", "/Users", Some(json!({ "userName": "bjensen" }))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let etag = headers[header::ETAG].clone();
+    assert_eq!(body["meta"]["version"], etag.to_str().unwrap());
+    let uri = format!("/Users/{}", body["id"].as_str().unwrap());
+
+    let mut get = request("GET", &uri, None);
+    get.headers_mut()
+        .insert(header::IF_NONE_MATCH, etag.clone());
+    let (status, headers, body) = send(users.builder(), get).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(headers[header::ETAG], etag);
+    assert_eq!(body, serde_json::Value::Null);
+
+    let mut get = request("GET", &uri, None);
+    get.headers_mut()
+        .insert(header::IF_NONE_MATCH, HeaderValue::from_static(r#"W/"0""#));
+    let (status, _, _) = send(users.builder(), get).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let body = json!({ "userName": "bjensen", "displayName": "Babs" });
+    let mut put = request("PUT", &uri, Some(body.clone()));
+    put.headers_mut()
+        .insert(header::IF_MATCH, HeaderValue::from_static(r#"W/"0""#));
+    let (status, _, _) = send(users.builder(), put).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    // `If-Match` uses the strong comparison function, so a weak tag never satisfies it.
+    let weak = format!("W/{}", etag.to_str().unwrap());
+    let mut put = request("PUT", &uri, Some(body.clone()));
+    put.headers_mut()
+        .insert(header::IF_MATCH, HeaderValue::from_str(&weak).unwrap());
+    let (status, _, _) = send(users.builder(), put).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    // `If-None-Match` uses the weak comparison function.
+    let mut get = request("GET", &uri, None);
+    get.headers_mut()
+        .insert(header::IF_NONE_MATCH, HeaderValue::from_str(&weak).unwrap());
+    let (status, _, _) = send(users.builder(), get).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    let mut put = request("PUT", &uri, Some(body.clone()));
+    put.headers_mut().insert(
+        header::IF_MATCH,
+        HeaderValue::from_bytes(b"\"\xff\"").unwrap(),
+    );
+    let (status, _, _) = send(users.builder(), put).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let mut put = request("PUT", &uri, Some(body));
+    put.headers_mut().insert(header::IF_MATCH, etag.clone());
+    let (status, headers, body) = send(users.builder(), put).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["displayName"], "Babs");
+    assert_ne!(headers[header::ETAG], etag);
+
+    // The stale version no longer matches.
+    let mut delete = request("DELETE", &uri, None);
+    delete.headers_mut().insert(header::IF_MATCH, etag);
+    let (status, _, _) = send(users.builder(), delete).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn test_not_supported() {
+    let mut config = config();
+    config.etag.supported = false;
+    let users = Users::new();
+    let (_, headers, body) = send(
+        users.builder_with(config.clone()),
+        request("POST", "/Users", Some(json!({ "userName": "bjensen" }))),
+    )
+    .await;
+    assert!(!headers.contains_key(header::ETAG));
+
+    // Preconditions are ignored, even when they are invalid.
+    let mut delete = request(
+        "DELETE",
+        &format!("/Users/{}", body["id"].as_str().unwrap()),
+        None,
+    );
+    delete
+        .headers_mut()
+        .insert(header::IF_MATCH, HeaderValue::from_static(r#""0"#));
+    let (status, _, _) = send(users.builder_with(config), delete).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
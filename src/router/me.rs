@@ -0,0 +1,110 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{AppendHeaders, IntoResponse},
+    routing::{delete, get, patch, put},
+    Router,
+};
+use ijson::IObject;
+
+use crate::{json::Json, state::ResourceTypeState, Cream, Error};
+
+use super::{
+    args::{GetResourcesArgs, PatchResourceArgs},
+    etag::{with_etag, EntityTags},
+    retrieve::get_resource_response,
+    update::{delete_resource_inner, patch_resource_inner, put_resource_inner},
+};
+
+pub(crate) fn router() -> Router<Cream> {
+    Router::new()
+        .route("/Me", get(get_me))
+        .route("/Me", put(put_me))
+        .route("/Me", patch(patch_me))
+        .route("/Me", delete(delete_me))
+}
+
+// Resolves the resource which the request is authenticated as.
+async fn resolve<'a>(
+    state: &'a Cream,
+    parts: &Parts,
+) -> Result<(&'a ResourceTypeState, String), Error> {
+    let resolver = state.0.subject_resolver.as_ref().ok_or_else(|| {
+        Error::new(
+            StatusCode::NOT_IMPLEMENTED,
+            None,
+            "The /Me endpoint is not supported".to_string(),
+        )
+    })?;
+    let subject = resolver.resolve(parts).await?;
+    let rts = state
+        .0
+        .resource_types
+        .get(&subject.resource_type)
+        .ok_or_else(Error::not_found)?;
+    Ok((rts, subject.id))
+}
+
+// Responses from `/Me` include the location of the actual resource.
+fn location_header(
+    state: &Cream,
+    rts: &ResourceTypeState,
+    id: &str,
+) -> AppendHeaders<Option<(header::HeaderName, HeaderValue)>> {
+    let location = format!("{}{}/{}", state.0.base_url, rts.resource_type.endpoint, id);
+    AppendHeaders(
+        HeaderValue::from_str(&location)
+            .ok()
+            .map(|location| (header::LOCATION, location)),
+    )
+}
+
+async fn get_me(
+    State(state): State<Cream>,
+    Query(args): Query<GetResourcesArgs>,
+    parts: Parts,
+) -> Result<impl IntoResponse, Error> {
+    let (rts, id) = resolve(&state, &parts).await?;
+    let location = location_header(&state, rts, &id);
+    let response = get_resource_response(&state, &parts, rts, &args, id).await?;
+    Ok((location, response))
+}
+
+async fn put_me(
+    State(state): State<Cream>,
+    Query(args): Query<GetResourcesArgs>,
+    parts: Parts,
+    Json(body): Json<IObject>,
+) -> Result<impl IntoResponse, Error> {
+    let (rts, id) = resolve(&state, &parts).await?;
+    let location = location_header(&state, rts, &id);
+    let if_match = EntityTags::from_headers(&state, &parts.headers, header::IF_MATCH)?;
+    let result =
+        put_resource_inner(&state, &parts, rts, &args, id, body, if_match.as_ref()).await?;
+    Ok((location, with_etag(&state, result)))
+}
+
+async fn patch_me(
+    State(state): State<Cream>,
+    Query(args): Query<GetResourcesArgs>,
+    parts: Parts,
+    Json(body): Json<PatchResourceArgs>,
+) -> Result<impl IntoResponse, Error> {
+    let (rts, id) = resolve(&state, &parts).await?;
+    let location = location_header(&state, rts, &id);
+    let if_match = EntityTags::from_headers(&state, &parts.headers, header::IF_MATCH)?;
+    let result =
+        patch_resource_inner(&state, &parts, rts, &args, id, body, if_match.as_ref()).await?;
+    Ok((location, with_etag(&state, result)))
+}
+
+async fn delete_me(State(state): State<Cream>, parts: Parts) -> Result<impl IntoResponse, Error> {
+    let (rts, id) = resolve(&state, &parts).await?;
+    let location = location_header(&state, rts, &id);
+    let if_match = EntityTags::from_headers(&state, &parts.headers, header::IF_MATCH)?;
+    delete_resource_inner(&parts, rts, &id, if_match.as_ref()).await?;
+    Ok((StatusCode::NO_CONTENT, location))
+}
+
+#[cfg(test)]
+mod tests;
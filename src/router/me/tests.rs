@@ -0,0 +1,126 @@
+use axum::{
+    body::Body,
+    http::{header, request::Parts, Request, StatusCode},
+};
+use serde_json::{json, Value};
+
+use crate::{
+    router::testing::{request, send, Users},
+    Error, Subject, SubjectResolver,
+};
+
+// Authenticates requests as the user whose ID is given in the `x-user-id` header.
+#[derive(Debug)]
+struct HeaderResolver;
+
+#[async_trait::async_trait]
+impl SubjectResolver for HeaderResolver {
+    async fn resolve(&self, parts: &'async_trait Parts) -> Result<Subject, Error> {
+        let id = parts
+            .headers
+            .get("x-user-id")
+            .and_then(|id| id.to_str().ok())
+            .ok_or_else(|| {
+                Error::new(StatusCode::UNAUTHORIZED, None, "Unauthorized".to_string())
+            })?;
+        Ok(Subject {
+            resource_type: "User".to_string(),
+            id: id.to_string(),
+        })
+    }
+}
+
+fn me(method: &str, id: &str, body: Option<Value>) -> Request<Body> {
+    let mut request = request(method, "/Me", body);
+    request
+        .headers_mut()
+        .insert("x-user-id", id.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn test_me() {
+    let users = Users::new();
+    let builder = || users.builder().subject_resolver(HeaderResolver);
+    let (_, _, body) = send(
+        builder(),
+        request("POST", "/Users", Some(json!({ "userName": "bjensen" }))),
+    )
+    .await;
+    let id = body["id"].as_str().unwrap();
+    let location = format!("http://localhost/Users/{}", id);
+
+    let (status, headers, body) = send(builder(), me("GET", id, None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::LOCATION], location.as_str());
+    assert!(headers.contains_key(header::ETAG));
+    assert_eq!(body["userName"], "bjensen");
+
+    let (status, headers, body) = send(
+        builder(),
+        me(
+            "PUT",
+            id,
+            Some(json!({ "userName": "bjensen", "displayName": "Babs" })),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::LOCATION], location.as_str());
+    assert_eq!(body["displayName"], "Babs");
+
+    let (status, headers, body) = send(
+        builder(),
+        me(
+            "PATCH",
+            id,
+            Some(json!({
+                "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                "Operations": [{ "op": "replace", "path": "displayName", "value": "Barbara" }]
+            })),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::LOCATION], location.as_str());
+    assert_eq!(body["displayName"], "Barbara");
+
+    let (status, headers, _) = send(builder(), me("DELETE", id, None)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(headers[header::LOCATION], location.as_str());
+
+    let (status, _, _) = send(builder(), me("GET", id, None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_me_unauthenticated() {
+    let builder = Users::new().builder().subject_resolver(HeaderResolver);
+    let (status, headers, _) = send(builder, request("GET", "/Me", None)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(!headers.contains_key(header::LOCATION));
+}
+
+#[tokio::test]
+async fn test_me_not_supported() {
+    let users = Users::new();
+    let requests = [
+        request("GET", "/Me", None),
+        request("PUT", "/Me", Some(json!({ "userName": "bjensen" }))),
+        request(
+            "PATCH",
+            "/Me",
+            Some(json!({
+                "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                "Operations": []
+            })),
+        ),
+        request("DELETE", "/Me", None),
+    ];
+    for request in requests {
+        let method = request.method().clone();
+        let (status, _, body) = send(users.builder(), request).await;
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED, "{}", method);
+        assert_eq!(body["detail"], "The /Me endpoint is not supported");
+    }
+}
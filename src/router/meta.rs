@@ -5,8 +5,12 @@ use axum::{
     Router,
 };
 
-use crate::{json::Json, list::ListResponse, Cream, Error};
+use crate::{
+    filter::FILTER_CAPABILITIES_SCHEMA, json::Json, list::ListResponse, state::ResourceTypeState,
+    Cream, Error,
+};
 use cream_core::Reference;
+use ijson::{IObject, IValue};
 
 pub(crate) fn router() -> Router<Cream> {
     Router::new()
@@ -50,16 +54,34 @@ async fn get_schema(
     Ok(Json(resource))
 }
 
+// Renders a resource type for discovery, including the filters its manager supports, if it
+// declares them.
+fn resource_type_resource(rts: &ResourceTypeState) -> IObject {
+    let mut resource_type = rts.resource_type.clone();
+    resource_type.locate();
+    let mut resource = ijson::to_value(&resource_type)
+        .expect("Infallible serialization")
+        .into_object()
+        .expect("Resource types serialize as objects");
+    if let Some(capabilities) = &rts.filter_capabilities {
+        if let Some(schemas) = resource.get_mut("schemas").and_then(IValue::as_array_mut) {
+            schemas.push(FILTER_CAPABILITIES_SCHEMA);
+        }
+        resource.insert(
+            FILTER_CAPABILITIES_SCHEMA,
+            ijson::to_value(capabilities.to_extension()).expect("Infallible serialization"),
+        );
+    }
+    resource
+}
+
 async fn list_resource_types(State(state): State<Cream>) -> impl IntoResponse {
-    let mut resources: Vec<_> = state
+    let resources: Vec<_> = state
         .0
         .resource_types
         .values()
-        .map(|r| r.resource_type.clone())
+        .map(resource_type_resource)
         .collect();
-    for resource in &mut resources {
-        resource.locate();
-    }
     Json(ListResponse {
         total_results: resources.len(),
         items_per_page: resources.len() + 1,
@@ -72,13 +94,10 @@ async fn get_resource_type(
     State(state): State<Cream>,
     Path(name): Path<String>,
 ) -> Result<impl IntoResponse, Error> {
-    let mut resource = state
+    let rts = state
         .0
         .resource_types
         .get(&name)
-        .ok_or_else(Error::not_found)?
-        .resource_type
-        .clone();
-    resource.locate();
-    Ok(Json(resource))
+        .ok_or_else(Error::not_found)?;
+    Ok(Json(resource_type_resource(rts)))
 }
@@ -0,0 +1,290 @@
+//! Enforcement of attribute mutability (RFC 7643 Section 2.2).
+//!
+//! - `readOnly` attributes are ignored on create and replace. PATCH operations which target
+//!   them are rejected.
+//! - `readOnly` sub-attributes nested inside complex values are always ignored, since clients
+//!   commonly echo them back (eg. `manager.displayName`).
+//! - `immutable` attributes may be set on create, or if they do not yet have a value, but may
+//!   not be changed afterwards.
+use axum::http::request::Parts;
+use cream_core::{Attribute, Mutability, Type};
+use ijson::{IObject, IValue};
+
+use crate::{
+    filter::{AttrPathRef, ValuePathRef},
+    json::get_ignore_case,
+    manager::{GetResourceArgs, UpdateResourceItem},
+    schema::SchemaRegistry,
+    state::ResourceTypeState,
+    Error,
+};
+
+use super::etag::{check_version, EntityTags};
+
+fn is_read_only(attr: &Attribute) -> bool {
+    matches!(attr.mutability, Mutability::ReadOnly)
+}
+
+fn is_immutable(attr: &Attribute) -> bool {
+    matches!(attr.mutability, Mutability::Immutable)
+}
+
+fn has_value(value: Option<&IValue>) -> bool {
+    value.is_some_and(|value| !value.is_null())
+}
+
+// Looks up the current value of an attribute within a resource.
+fn current_value<'a>(resource: &'a IObject, path: AttrPathRef) -> Option<&'a IValue> {
+    let obj = match path.urn {
+        Some(urn) => get_ignore_case(resource, urn)?.as_object()?,
+        None => resource,
+    };
+    let value = get_ignore_case(obj, path.name)?;
+    match path.sub_attr {
+        None => Some(value),
+        Some(sub_attr) => match value.as_array() {
+            Some(arr) => arr
+                .iter()
+                .filter_map(|item| get_ignore_case(item.as_object()?, sub_attr))
+                .find(|value| !value.is_null()),
+            None => get_ignore_case(value.as_object()?, sub_attr),
+        },
+    }
+}
+
+// Removes read-only sub-attributes from a complex value.
+fn strip_read_only_sub_attributes(attr: &Attribute, value: &mut IValue) {
+    if !matches!(attr.type_, Type::Complex) {
+        return;
+    }
+    let Some(sub_attributes) = &attr.sub_attributes else {
+        return;
+    };
+    let strip = |obj: &mut IObject| {
+        obj.retain(|key, _| {
+            !sub_attributes
+                .iter()
+                .any(|sub_attr| is_read_only(sub_attr) && sub_attr.name.eq_ignore_ascii_case(key))
+        })
+    };
+    if let Some(arr) = value.as_array_mut() {
+        for item in arr.iter_mut() {
+            if let Some(obj) = item.as_object_mut() {
+                strip(obj);
+            }
+        }
+    } else if let Some(obj) = value.as_object_mut() {
+        strip(obj);
+    }
+}
+
+// Visits each attribute of a resource body, including those within extension objects. The
+// callback returns whether the attribute should be kept.
+fn retain_attributes(
+    schemas: &SchemaRegistry,
+    body: &mut IObject,
+    urn: Option<&str>,
+    f: &mut impl FnMut(AttrPathRef, &Attribute, &mut IValue) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    let mut removed = Vec::new();
+    for (key, value) in body.iter_mut() {
+        if urn.is_none() {
+            if let Some(ext) = schemas.extension(key) {
+                if let Some(obj) = value.as_object_mut() {
+                    retain_attributes(schemas, obj, Some(&ext.id), f)?;
+                    continue;
+                }
+            }
+        }
+        let path = AttrPathRef {
+            urn,
+            name: key,
+            sub_attr: None,
+        };
+        if let Some(attr) = schemas.attribute(path) {
+            if !f(path, attr, value)? {
+                removed.push(key.clone());
+            }
+        }
+    }
+    for key in removed {
+        body.remove(&key);
+    }
+    Ok(())
+}
+
+/// Removes read-only attributes from the body of a create request.
+pub(crate) fn prepare_create(schemas: &SchemaRegistry, body: &mut IObject) {
+    retain_attributes(schemas, body, None, &mut |_, attr, value| {
+        strip_read_only_sub_attributes(attr, value);
+        Ok(!is_read_only(attr))
+    })
+    .expect("Infallible");
+}
+
+/// Lists the attributes of a replace request whose current values must be known in order to
+/// check it.
+pub(crate) fn replace_dependencies<'a>(
+    schemas: &'a SchemaRegistry,
+    body: &IObject,
+) -> Vec<AttrPathRef<'a>> {
+    let mut result = Vec::new();
+    let mut push = |urn: Option<&'a str>, key: &str| {
+        let path = AttrPathRef {
+            urn,
+            name: key,
+            sub_attr: None,
+        };
+        if let Some(attr) = schemas.attribute(path) {
+            if is_immutable(attr) {
+                result.push(AttrPathRef {
+                    urn,
+                    name: &attr.name,
+                    sub_attr: None,
+                });
+            }
+        }
+    };
+    for (key, value) in body {
+        match (schemas.extension(key), value.as_object()) {
+            (Some(ext), Some(obj)) => {
+                for key in obj.keys() {
+                    push(Some(&ext.id), key);
+                }
+            }
+            _ => push(None, key),
+        }
+    }
+    result
+}
+
+/// Retrieves the current state of a resource, including the given attributes, and checks it
+/// against the `If-Match` precondition. Returns the resource, or `None` if it was not needed,
+/// and the version which the manager should expect when applying the change.
+pub(crate) async fn fetch_current(
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    id: &str,
+    attributes: &[AttrPathRef<'_>],
+    if_match: Option<&EntityTags>,
+) -> Result<(Option<IObject>, Option<String>), Error> {
+    if attributes.is_empty() && if_match.is_none() {
+        return Ok((None, None));
+    }
+    let args = GetResourceArgs {
+        id: id.to_string(),
+        optional_attributes: attributes,
+    };
+    let current = rts.manager.get(parts, args).await?;
+    let version = match if_match {
+        Some(if_match) => check_version(&current, if_match)?,
+        None => None,
+    };
+    Ok((Some(current), version))
+}
+
+/// Checks the body of a replace request against the current resource, removing read-only
+/// attributes.
+pub(crate) fn prepare_replace(
+    schemas: &SchemaRegistry,
+    body: &mut IObject,
+    current: Option<&IObject>,
+) -> Result<(), Error> {
+    retain_attributes(schemas, body, None, &mut |path, attr, value| {
+        if is_read_only(attr) {
+            return Ok(false);
+        }
+        let current_value = current.and_then(|current| current_value(current, path));
+        if is_immutable(attr) && has_value(current_value) && current_value != Some(value) {
+            return Err(Error::mutability(&path.to_string()));
+        }
+        strip_read_only_sub_attributes(attr, value);
+        Ok(true)
+    })
+}
+
+/// Removes read-only sub-attributes from the value of a PATCH operation.
+pub(crate) fn prepare_patch_value(
+    schemas: &SchemaRegistry,
+    path: Option<ValuePathRef>,
+    value: &mut IValue,
+) {
+    match path {
+        Some(ValuePathRef::Attr(path) | ValuePathRef::Filtered(path, _)) => {
+            if path.sub_attr.is_none() {
+                if let Some(attr) = schemas.attribute(path) {
+                    strip_read_only_sub_attributes(attr, value);
+                }
+            }
+        }
+        None => {
+            if let Some(obj) = value.as_object_mut() {
+                retain_attributes(schemas, obj, None, &mut |_, attr, value| {
+                    strip_read_only_sub_attributes(attr, value);
+                    Ok(true)
+                })
+                .expect("Infallible");
+            }
+        }
+    }
+}
+
+// Finds the definitions of the attribute targeted by an update, and its parent attribute.
+fn target_attributes<'a, 's>(
+    schemas: &'s SchemaRegistry,
+    item: &UpdateResourceItem<'a>,
+) -> Option<(AttrPathRef<'a>, Vec<&'s Attribute>)> {
+    let (Some(ValuePathRef::Attr(path)) | Some(ValuePathRef::Filtered(path, _))) = item.path else {
+        return None;
+    };
+    let mut attrs: Vec<_> = schemas.attribute(path).into_iter().collect();
+    if path.sub_attr.is_some() {
+        attrs.extend(schemas.attribute(AttrPathRef {
+            sub_attr: None,
+            ..path
+        }));
+    }
+    Some((path, attrs))
+}
+
+/// Lists the attributes targeted by a PATCH request whose current values must be known in order
+/// to check it.
+pub(crate) fn update_dependencies<'a>(
+    schemas: &SchemaRegistry,
+    items: &[UpdateResourceItem<'a>],
+) -> Vec<AttrPathRef<'a>> {
+    items
+        .iter()
+        .filter_map(|item| target_attributes(schemas, item))
+        .filter(|(_, attrs)| attrs.iter().any(|attr| is_immutable(attr)))
+        .map(|(path, _)| AttrPathRef {
+            sub_attr: None,
+            ..path
+        })
+        .collect()
+}
+
+/// Checks that a PATCH request does not modify any read-only or immutable attributes.
+pub(crate) fn check_update(
+    schemas: &SchemaRegistry,
+    items: &[UpdateResourceItem],
+    current: Option<&IObject>,
+) -> Result<(), Error> {
+    for item in items {
+        let Some((path, attrs)) = target_attributes(schemas, item) else {
+            continue;
+        };
+        if attrs.iter().any(|attr| is_read_only(attr)) {
+            return Err(Error::mutability(&path.to_string()));
+        }
+        if attrs.iter().any(|attr| is_immutable(attr))
+            && has_value(current.and_then(|current| current_value(current, path)))
+        {
+            return Err(Error::mutability(&path.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,201 @@
+use axum::http::{header, StatusCode};
+use bumpalo::Bump;
+use ijson::{ijson, IObject, IValue};
+use serde_json::json;
+
+use crate::{
+    filter::{parse_value_path, AttrPathRef},
+    manager::{UpdateOp, UpdateResourceItem},
+    router::testing::{request, send, Users},
+    schema::test_registry,
+};
+
+use super::{
+    check_update, prepare_create, prepare_replace, replace_dependencies, update_dependencies,
+};
+
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+fn object(value: IValue) -> IObject {
+    value.into_object().unwrap()
+}
+
+#[test]
+fn test_prepare_create() {
+    let mut body = object(ijson!({
+        "id": "123",
+        "displayName": "foo",
+        "loginCount": 3,
+        "meta": { "version": "W/\"1\"" },
+        ENTERPRISE: {
+            "employeeNumber": "abc",
+            "manager": { "value": "1", "displayName": "Alice" }
+        }
+    }));
+    prepare_create(&test_registry(), &mut body);
+
+    assert_eq!(
+        body,
+        object(ijson!({
+            "displayName": "foo",
+            ENTERPRISE: {
+                "employeeNumber": "abc",
+                "manager": { "value": "1" }
+            }
+        }))
+    );
+}
+
+#[test]
+fn test_prepare_replace() {
+    let registry = test_registry();
+    let current = object(ijson!({
+        "id": "123",
+        "displayName": "foo",
+        "loginCount": 3,
+        ENTERPRISE: { "employeeNumber": "abc" }
+    }));
+
+    let mut body = object(ijson!({
+        "id": "123",
+        "displayName": "bar",
+        "loginCount": 3,
+        "meta": { "version": "W/\"1\"" },
+        ENTERPRISE: { "employeeNumber": "abc" }
+    }));
+    prepare_replace(&registry, &mut body, Some(&current)).unwrap();
+    assert_eq!(
+        body,
+        object(ijson!({
+            "displayName": "bar",
+            ENTERPRISE: { "employeeNumber": "abc" }
+        }))
+    );
+
+    let mut body = object(ijson!({
+        "displayName": "bar",
+        ENTERPRISE: { "employeeNumber": "def" }
+    }));
+    assert!(prepare_replace(&registry, &mut body, Some(&current)).is_err());
+
+    // Read-only attributes are ignored, even if they differ from the current value.
+    let mut body = object(ijson!({ "id": "456", "displayName": "bar", "loginCount": 4 }));
+    prepare_replace(&registry, &mut body, Some(&current)).unwrap();
+    assert_eq!(body, object(ijson!({ "displayName": "bar" })));
+
+    // Omitting an immutable attribute is not a change.
+    let mut body = object(ijson!({ "displayName": "bar" }));
+    assert!(prepare_replace(&registry, &mut body, Some(&current)).is_ok());
+}
+
+#[test]
+fn test_replace_dependencies() {
+    let registry = test_registry();
+    let body = object(ijson!({
+        "id": "123",
+        "displayName": "bar",
+        "loginCount": 3,
+        ENTERPRISE: { "employeeNumber": "abc", "costCenter": "x" }
+    }));
+    // Only immutable attributes need to be compared with their current values.
+    assert_eq!(
+        replace_dependencies(&registry, &body),
+        [AttrPathRef {
+            urn: Some(ENTERPRISE),
+            name: "employeeNumber",
+            sub_attr: None,
+        }]
+    );
+    let body = object(ijson!({ "id": "123", "displayName": "bar" }));
+    assert_eq!(replace_dependencies(&registry, &body), []);
+}
+
+#[test]
+fn test_check_update() {
+    let registry = test_registry();
+    let scope = Bump::new();
+    let value = IValue::NULL;
+    let item = |path: &str| UpdateResourceItem {
+        path: Some(scope.alloc(parse_value_path(path).unwrap()).as_ref(&scope)),
+        op: UpdateOp::Replace(&value),
+    };
+    let check = |path: &str, current: Option<&IObject>| {
+        check_update(&registry, &[item(path)], current).map_err(|e| e.detail)
+    };
+    let current = object(ijson!({ ENTERPRISE: { "employeeNumber": "abc" } }));
+    let employee_number = format!("{}:employeeNumber", ENTERPRISE);
+
+    assert_eq!(check("displayName", None), Ok(()));
+    assert_eq!(check("manager.value", None), Ok(()));
+    assert_eq!(
+        check("loginCount", None),
+        Err("Attribute `loginCount` cannot be modified".to_string())
+    );
+    assert_eq!(
+        check("meta.lastModified", None),
+        Err("Attribute `meta.lastModified` cannot be modified".to_string())
+    );
+    // A read-only sub-attribute of a writable attribute.
+    assert_eq!(
+        check(&format!("{}:manager.displayName", ENTERPRISE), None),
+        Err(format!(
+            "Attribute `{}:manager.displayName` cannot be modified",
+            ENTERPRISE
+        ))
+    );
+
+    // An immutable attribute may only be set if it has no value.
+    assert_eq!(check(&employee_number, Some(&object(ijson!({})))), Ok(()));
+    assert_eq!(
+        check(&employee_number, Some(&current)),
+        Err(format!(
+            "Attribute `{}` cannot be modified",
+            employee_number
+        ))
+    );
+
+    let items = [item("displayName"), item(&employee_number)];
+    assert_eq!(
+        update_dependencies(&registry, &items),
+        [AttrPathRef {
+            urn: Some(ENTERPRISE),
+            name: "employeeNumber",
+            sub_attr: None,
+        }]
+    );
+    assert_eq!(update_dependencies(&registry, &items[..1]), []);
+}
+
+#[tokio::test]
+async fn test_fetch_current() {
+    let users = Users::new();
+    let (_, _, body) = send(
+        users.builder(),
+        request("POST", "/Users", Some(json!({ "userName": "bjensen" }))),
+    )
+    .await;
+    let uri = format!("/Users/{}", body["id"].as_str().unwrap());
+    users.take_gets();
+
+    // Echoing back read-only attributes does not require the current resource, so the only
+    // `get` is for the response.
+    let (status, headers, body) = send(users.builder(), request("PUT", &uri, Some(body))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users.take_gets(), 1);
+
+    // The resource fetched to check the precondition is reused.
+    let mut put = request("PUT", &uri, Some(body));
+    put.headers_mut()
+        .insert(header::IF_MATCH, headers[header::ETAG].clone());
+    let (status, _, _) = send(users.builder(), put).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users.take_gets(), 2);
+
+    let patch = json!({
+        "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+        "Operations": [{ "op": "replace", "path": "displayName", "value": "Babs" }]
+    });
+    let (status, _, _) = send(users.builder(), request("PATCH", &uri, Some(patch))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(users.take_gets(), 1);
+}
@@ -0,0 +1,104 @@
+//! Index-based (RFC 7644 Section 3.4.2.4) and cursor-based (RFC 9865) pagination.
+use axum::http::StatusCode;
+
+use crate::{
+    config::{PaginationMethod, ServiceProviderConfig},
+    Error, ErrorType,
+};
+
+use super::args::ListResourcesArgs;
+
+/// The page of results requested by the client, after applying defaults and limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Page<'a> {
+    /// One-based index of the first result. Always one when using a cursor.
+    pub(crate) start_index: usize,
+    /// Maximum number of results to return.
+    pub(crate) count: usize,
+    /// The cursor identifying the page, if using cursor-based pagination. Empty for the
+    /// first page.
+    pub(crate) cursor: Option<&'a str>,
+}
+
+impl<'a> Page<'a> {
+    /// Interprets the `startIndex`, `count` and `cursor` parameters. A `startIndex` less than
+    /// one is treated as one, and a negative `count` is treated as zero. The count defaults to
+    /// `defaultPageSize` from the service provider config, or `default_count` if that is not
+    /// set, and is capped at `max_count` and at the limits from the service provider config.
+    pub(crate) fn new(
+        config: &ServiceProviderConfig,
+        args: &'a ListResourcesArgs,
+        default_count: usize,
+        max_count: usize,
+    ) -> Result<Self, Error> {
+        let pagination = config.pagination.as_ref();
+        let cursor_supported = pagination.is_some_and(|pagination| pagination.cursor);
+        let index_supported = pagination.is_none_or(|pagination| pagination.index);
+        let cursor = match (&args.cursor, args.start_index) {
+            (Some(_), Some(_)) => {
+                return Err(Error::new(
+                    StatusCode::BAD_REQUEST,
+                    Some(ErrorType::InvalidValue),
+                    "Cannot specify both `startIndex` and `cursor`".to_string(),
+                ))
+            }
+            (Some(_), None) if !cursor_supported => return Err(Error::invalid_cursor()),
+            (Some(cursor), None) => Some(cursor.as_str()),
+            (None, Some(_)) if !index_supported => {
+                return Err(Error::new(
+                    StatusCode::BAD_REQUEST,
+                    Some(ErrorType::InvalidValue),
+                    "Index-based pagination is not supported".to_string(),
+                ))
+            }
+            (None, Some(_)) => None,
+            (None, None) => pagination
+                .filter(|pagination| {
+                    pagination.cursor
+                        && (!pagination.index
+                            || pagination.default_pagination_method
+                                == Some(PaginationMethod::Cursor))
+                })
+                .map(|_| ""),
+        };
+        let default_count = pagination
+            .and_then(|pagination| pagination.default_page_size)
+            .and_then(|size| usize::try_from(size).ok())
+            .filter(|size| *size > 0)
+            .unwrap_or(default_count);
+
+        let limits = [
+            Some(config.filter.max_results),
+            pagination.and_then(|pagination| pagination.max_page_size),
+        ];
+        let max_count = limits
+            .into_iter()
+            .flatten()
+            .filter_map(|limit| usize::try_from(limit).ok())
+            .filter(|limit| *limit > 0)
+            .fold(max_count, usize::min);
+
+        let start_index = match cursor {
+            Some(_) => 1,
+            None => args
+                .start_index
+                .map_or(1, |start_index| start_index.max(1) as usize),
+        };
+        let count = args
+            .count
+            .map_or(default_count, |count| count.max(0) as usize);
+        Ok(Self {
+            start_index,
+            count: count.min(max_count),
+            cursor,
+        })
+    }
+
+    /// Zero-based offset of the first result.
+    pub(crate) fn offset(&self) -> usize {
+        self.start_index - 1
+    }
+}
+
+#[cfg(test)]
+mod tests;
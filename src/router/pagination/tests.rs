@@ -0,0 +1,129 @@
+use crate::{config::ServiceProviderConfig, router::args::ListResourcesArgs};
+
+use super::Page;
+
+fn config(pagination: serde_json::Value) -> ServiceProviderConfig {
+    serde_json::from_value(serde_json::json!({
+        "patch": { "supported": true },
+        "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+        "filter": { "supported": true, "maxResults": 200 },
+        "changePassword": { "supported": false },
+        "sort": { "supported": true },
+        "etag": { "supported": false },
+        "pagination": pagination,
+        "authenticationSchemes": []
+    }))
+    .unwrap()
+}
+
+fn args(value: serde_json::Value) -> ListResourcesArgs {
+    serde_json::from_value(value).unwrap()
+}
+
+fn page(
+    config: &ServiceProviderConfig,
+    value: serde_json::Value,
+) -> Result<(usize, usize, Option<String>), String> {
+    let args = args(value);
+    Page::new(config, &args, 50, 100)
+        .map(|page| {
+            (
+                page.start_index,
+                page.count,
+                page.cursor.map(str::to_string),
+            )
+        })
+        .map_err(|e| e.detail)
+}
+
+#[test]
+fn test_index() {
+    let config = config(serde_json::Value::Null);
+    let cases = [
+        (serde_json::json!({}), (1, 50)),
+        (
+            serde_json::json!({ "startIndex": 11, "count": 10 }),
+            (11, 10),
+        ),
+        (serde_json::json!({ "startIndex": 0 }), (1, 50)),
+        (serde_json::json!({ "startIndex": -5 }), (1, 50)),
+        (serde_json::json!({ "count": -1 }), (1, 0)),
+        (serde_json::json!({ "count": 0 }), (1, 0)),
+        (serde_json::json!({ "count": 1000 }), (1, 100)),
+    ];
+    for (value, (start_index, count)) in cases {
+        assert_eq!(page(&config, value), Ok((start_index, count, None)));
+    }
+}
+
+#[test]
+fn test_cursor() {
+    let unsupported = config(serde_json::json!({ "cursor": false, "index": true }));
+    assert_eq!(
+        page(&unsupported, serde_json::json!({ "cursor": "abc" })),
+        Err("Invalid Cursor".to_string())
+    );
+
+    let supported = config(serde_json::json!({
+        "cursor": true,
+        "index": true,
+        "defaultPaginationMethod": "cursor",
+        "maxPageSize": 20
+    }));
+    assert_eq!(
+        page(&supported, serde_json::json!({})),
+        Ok((1, 20, Some(String::new())))
+    );
+    assert_eq!(
+        page(
+            &supported,
+            serde_json::json!({ "cursor": "abc", "count": 5 })
+        ),
+        Ok((1, 5, Some("abc".to_string())))
+    );
+    assert_eq!(
+        page(&supported, serde_json::json!({ "startIndex": 3 })),
+        Ok((3, 20, None))
+    );
+    assert!(page(
+        &supported,
+        serde_json::json!({ "startIndex": 3, "cursor": "abc" })
+    )
+    .is_err());
+}
+
+#[test]
+fn test_defaults() {
+    let default_size = config(serde_json::json!({
+        "cursor": true,
+        "index": true,
+        "defaultPaginationMethod": "index",
+        "defaultPageSize": 10
+    }));
+    assert_eq!(
+        page(&default_size, serde_json::json!({})),
+        Ok((1, 10, None))
+    );
+    assert_eq!(
+        page(&default_size, serde_json::json!({ "count": 70 })),
+        Ok((1, 70, None))
+    );
+
+    // The default page size is still capped.
+    let large_size = config(serde_json::json!({
+        "cursor": false,
+        "index": true,
+        "defaultPageSize": 1000
+    }));
+    assert_eq!(page(&large_size, serde_json::json!({})), Ok((1, 100, None)));
+
+    let cursor_only = config(serde_json::json!({ "cursor": true, "index": false }));
+    assert_eq!(
+        page(&cursor_only, serde_json::json!({})),
+        Ok((1, 50, Some(String::new())))
+    );
+    assert_eq!(
+        page(&cursor_only, serde_json::json!({ "startIndex": 1 })),
+        Err("Index-based pagination is not supported".to_string())
+    );
+}
@@ -0,0 +1,149 @@
+//! Validation of the attribute paths in filters, `sortBy` and PATCH operations against the
+//! schemas of a resource type, before they are passed to the manager.
+//!
+//! Paths are validated after their casing has been fixed, so that a typo is reported to the
+//! client rather than silently matching nothing.
+use axum::http::StatusCode;
+use cream_core::{Attribute, Type};
+
+use crate::{
+    error::ErrorType,
+    filter::{AttrPathRef, CompValueRef, CompareOp, FilterRef, ValuePathRef},
+    schema::{describe_type, SchemaRegistry},
+    Error,
+};
+
+struct PathValidator<'a> {
+    schemas: &'a SchemaRegistry,
+    scim_type: ErrorType,
+}
+
+impl<'a> PathValidator<'a> {
+    fn error(&self, detail: String) -> Error {
+        Error::new(
+            StatusCode::BAD_REQUEST,
+            Some(self.scim_type.clone()),
+            detail,
+        )
+    }
+
+    // Returns `None` for the `schemas` attribute, which every resource has but no schema defines.
+    fn attribute(&self, path: AttrPathRef) -> Result<Option<&'a Attribute>, Error> {
+        if path.urn.is_none()
+            && path.sub_attr.is_none()
+            && path.name.eq_ignore_ascii_case("schemas")
+        {
+            return Ok(None);
+        }
+        self.schemas
+            .attribute(path)
+            .map(Some)
+            .ok_or_else(|| self.error(format!("Unknown attribute `{}`", path)))
+    }
+
+    fn complex_attribute(&self, path: AttrPathRef) -> Result<(), Error> {
+        match self.attribute(path)? {
+            Some(attr) if matches!(attr.type_, Type::Complex) => Ok(()),
+            _ => Err(self.error(format!(
+                "Value filters can only be applied to complex attributes, but `{}` is not complex",
+                path
+            ))),
+        }
+    }
+
+    fn compare(&self, path: AttrPathRef, op: CompareOp, value: CompValueRef) -> Result<(), Error> {
+        if self.attribute(path)?.is_none() {
+            return Ok(());
+        }
+        // Comparing with `null` tests whether the attribute is present, regardless of its type
+        let is_equality = matches!(op, CompareOp::Equal | CompareOp::NotEqual);
+        if is_equality && matches!(value, CompValueRef::Null) {
+            return Ok(());
+        }
+        let (_, attr) = self.schemas.comparison_attribute(path).ok_or_else(|| {
+            self.error(format!(
+                "`{}` is a complex attribute, so a sub-attribute must be specified",
+                path
+            ))
+        })?;
+        let supported = match attr.type_ {
+            Type::String | Type::Reference => true,
+            Type::Integer | Type::Decimal | Type::DateTime => !matches!(
+                op,
+                CompareOp::Contains | CompareOp::StartsWith | CompareOp::EndsWith
+            ),
+            Type::Boolean | Type::Binary | Type::Complex => is_equality,
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(self.error(format!(
+                "The `{}` operator cannot be used with `{}`, which is {}",
+                op,
+                path,
+                describe_type(&attr.type_)
+            )))
+        }
+    }
+
+    fn filter(&self, filter: &FilterRef) -> Result<(), Error> {
+        match *filter {
+            FilterRef::Present(path) => self.attribute(path).map(drop),
+            FilterRef::Compare(path, op, value) => self.compare(path, op, value),
+            FilterRef::Has(path, filter) => {
+                self.complex_attribute(path)?;
+                self.filter(filter)
+            }
+            FilterRef::And(filters) | FilterRef::Or(filters) => {
+                filters.iter().try_for_each(|filter| self.filter(filter))
+            }
+            FilterRef::Not(filter) => self.filter(filter),
+        }
+    }
+}
+
+/// Checks that every attribute in a filter is defined, and that every comparison is supported
+/// by the type of its attribute.
+pub(crate) fn validate_filter(schemas: &SchemaRegistry, filter: &FilterRef) -> Result<(), Error> {
+    PathValidator {
+        schemas,
+        scim_type: ErrorType::InvalidFilter,
+    }
+    .filter(filter)
+}
+
+/// Checks that an attribute path, such as the `sortBy` parameter, refers to a defined attribute.
+pub(crate) fn validate_attr_path(schemas: &SchemaRegistry, path: AttrPathRef) -> Result<(), Error> {
+    PathValidator {
+        schemas,
+        scim_type: ErrorType::InvalidPath,
+    }
+    .attribute(path)
+    .map(drop)
+}
+
+/// Checks the path of a PATCH operation, including any value filter it contains. All errors
+/// have the `invalidPath` type.
+pub(crate) fn validate_value_path(
+    schemas: &SchemaRegistry,
+    path: ValuePathRef,
+) -> Result<(), Error> {
+    let validator = PathValidator {
+        schemas,
+        scim_type: ErrorType::InvalidPath,
+    };
+    match path {
+        ValuePathRef::Attr(path) => validator.attribute(path).map(drop),
+        ValuePathRef::Filtered(path, filter) => {
+            validator.complex_attribute(AttrPathRef {
+                sub_attr: None,
+                ..path
+            })?;
+            validator.attribute(path)?;
+            validator.filter(&filter)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,120 @@
+use crate::{
+    error::ErrorType,
+    filter::{parse_attr_path, parse_filter, parse_value_path},
+    schema::test_registry,
+};
+
+use super::{validate_attr_path, validate_filter, validate_value_path};
+
+fn check_filter(input: &str) -> Result<(), String> {
+    let scope = bumpalo::Bump::new();
+    let filter = parse_filter(input).unwrap();
+    validate_filter(&test_registry(), &filter.as_ref(&scope)).map_err(|e| {
+        assert!(matches!(e.scim_type, Some(ErrorType::InvalidFilter)));
+        e.detail
+    })
+}
+
+fn check_value_path(input: &str) -> Result<(), String> {
+    let scope = bumpalo::Bump::new();
+    let path = parse_value_path(input).unwrap();
+    validate_value_path(&test_registry(), path.as_ref(&scope)).map_err(|e| {
+        assert!(matches!(e.scim_type, Some(ErrorType::InvalidPath)));
+        e.detail
+    })
+}
+
+#[test]
+fn test_valid_filters() {
+    let cases = [
+        r#"userName eq "bjensen" and active eq true"#,
+        "loginCount gt 5 and not (loginCount le 1)",
+        r#"emails co "example.com" or emails[value ew ".org" and primary eq true]"#,
+        r#"name.givenName sw "B" and name pr"#,
+        "active eq null and name ne null",
+        r#"lastLogin gt "2024-01-01T00:00:00Z" and urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value eq "1""#,
+        r#"id eq "1" and meta.lastModified ge "2024-01-01T00:00:00Z""#,
+        r#"schemas eq "urn:ietf:params:scim:schemas:core:2.0:User""#,
+    ];
+    for input in cases {
+        assert_eq!(check_filter(input), Ok(()), "{}", input);
+    }
+}
+
+#[test]
+fn test_invalid_filters() {
+    let cases = [
+        (r#"userNmae eq "x""#, "Unknown attribute `userNmae`"),
+        ("name.middleName pr", "Unknown attribute `name.middleName`"),
+        ("userName.value pr", "Unknown attribute `userName.value`"),
+        (
+            r#"urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:userName eq "x""#,
+            "Unknown attribute `urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:userName`",
+        ),
+        (
+            r#"name eq "Barbara""#,
+            "`name` is a complex attribute, so a sub-attribute must be specified",
+        ),
+        (
+            "active gt true",
+            "The `gt` operator cannot be used with `active`, which is a boolean",
+        ),
+        (
+            r#"loginCount co "1""#,
+            "The `co` operator cannot be used with `loginCount`, which is an integer",
+        ),
+        (
+            "emails[primary lt true]",
+            "The `lt` operator cannot be used with `emails.primary`, which is a boolean",
+        ),
+        (
+            r#"userName[value eq "x"]"#,
+            "Value filters can only be applied to complex attributes, but `userName` is not complex",
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(check_filter(input), Err(expected.to_string()), "{}", input);
+    }
+}
+
+#[test]
+fn test_sort_by() {
+    let registry = test_registry();
+    let check = |input: &str| {
+        validate_attr_path(&registry, parse_attr_path(input).unwrap().as_ref()).map_err(|e| {
+            assert!(matches!(e.scim_type, Some(ErrorType::InvalidPath)));
+            e.detail
+        })
+    };
+    assert_eq!(check("name.givenName"), Ok(()));
+    assert_eq!(check("meta.created"), Ok(()));
+    assert_eq!(
+        check("name.givenNmae"),
+        Err("Unknown attribute `name.givenNmae`".to_string())
+    );
+}
+
+#[test]
+fn test_patch_paths() {
+    assert_eq!(check_value_path("name.givenName"), Ok(()));
+    assert_eq!(check_value_path(r#"emails[primary eq true].value"#), Ok(()));
+    assert_eq!(
+        check_value_path("titel"),
+        Err("Unknown attribute `titel`".to_string())
+    );
+    assert_eq!(
+        check_value_path(r#"emails[kind eq "work"]"#),
+        Err("Unknown attribute `emails.kind`".to_string())
+    );
+    assert_eq!(
+        check_value_path(r#"emails[primary eq true].label"#),
+        Err("Unknown attribute `emails.label`".to_string())
+    );
+    assert_eq!(
+        check_value_path("active[value eq true]"),
+        Err(
+            "Value filters can only be applied to complex attributes, but `active` is not complex"
+                .to_string()
+        )
+    );
+}
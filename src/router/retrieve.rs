@@ -1,64 +1,79 @@
 use axum::{
     extract::{Path, Query, State},
-    http::request::Parts,
-    response::IntoResponse,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
     Extension,
 };
 use bumpalo::Bump;
+use ijson::IObject;
 
 use crate::{
-    filter::{self, Visitor as _},
+    filter::{self, Filter, FilterRef, Visitor as _},
     json::Json,
     list::ListResponse,
     manager,
     state::ResourceTypeState,
-    Cream, Error,
+    Cream, Error, ErrorType,
 };
 
 use super::{
     args::{
         list_optional_attributes, FixAttributeCasingVisitor, GetResourcesArgs, ListResourcesArgs,
     },
-    ResourceTypeName,
+    etag::{etag_header, resource_version, with_etag, EntityTags},
+    pagination::Page,
+    paths, ResourceTypeName,
 };
 
 async fn list_resources_inner(
     state: &Cream,
     parts: &Parts,
-    resource_type: &str,
+    rts: &ResourceTypeState,
+    filter: Option<Filter>,
     args: ListResourcesArgs,
-) -> Result<impl IntoResponse, Error> {
-    let rts = state
-        .0
-        .resource_types
-        .get(resource_type)
-        .ok_or_else(Error::not_found)?;
-
+) -> Result<Json<ListResponse<IObject>>, Error> {
     let scope = Bump::new();
     let mut translated_args = manager::ListResourceArgs::default();
     let mut fixer = FixAttributeCasingVisitor::new(&rts.resource_type, state);
+    let mut residual = None;
 
-    if let Some(filter) = args.filter {
-        let filter = scope.alloc(filter::parse_filter(&filter)?);
+    if let Some(mut filter) = filter {
         // Fix the casing and URNs on any filters
-        fixer.visit_filter(filter);
-
-        translated_args.filter = Some(filter.as_ref(&scope));
+        fixer.visit_filter(&mut filter);
+        // Convert values to the types of the attributes they are compared with
+        filter.coerce(&rts.schemas)?;
+        // Simplify the filter, now that equivalent paths are spelled the same way
+        let filter = scope.alloc(filter.normalize()).as_ref(&scope);
+        paths::validate_filter(&rts.schemas, &filter)?;
+        // Only the part of the filter passed to the manager needs to be supported by it
+        let split = filter.split(&scope, |filter| rts.manager.can_push_down(filter));
+        if let (Some(capabilities), Some(pushdown)) = (&rts.filter_capabilities, &split.pushdown) {
+            capabilities.check(&rts.schemas, pushdown)?;
+        }
+
+        translated_args.filter = split.pushdown;
+        residual = split.residual;
     }
 
-    if let Some(sort_by) = args.sort_by {
-        let sort_by = scope.alloc(filter::parse_attr_path(&sort_by)?);
+    if let Some(sort_by) = &args.sort_by {
+        let sort_by = scope.alloc(filter::parse_attr_path(sort_by)?);
         // Fix the casing and URNs on any filters
         fixer.visit_attr_path(sort_by);
+        paths::validate_attr_path(&rts.schemas, sort_by.as_ref())?;
 
         translated_args.sort_by = Some(sort_by.as_ref());
     }
     translated_args.sort_order = args.sort_order;
 
-    translated_args.count = args
-        .count
-        .unwrap_or_else(|| rts.manager.default_page_size());
-    translated_args.start_index = args.start_index.unwrap_or(1) - 1;
+    let page = Page::new(
+        &state.0.config,
+        &args,
+        rts.manager.default_page_size(),
+        rts.manager.max_page_size(),
+    )?;
+    translated_args.count = page.count;
+    translated_args.start_index = page.offset();
+    translated_args.cursor = page.cursor;
 
     let optional_attributes = list_optional_attributes(
         &args.attributes,
@@ -69,23 +84,117 @@ async fn list_resources_inner(
 
     translated_args.optional_attributes = &optional_attributes;
 
-    let result = rts.manager.list(parts, translated_args).await?;
+    let mut result = match residual {
+        Some(residual) => list_with_residual(state, parts, rts, translated_args, &residual).await?,
+        None => rts.manager.list(parts, translated_args).await?,
+    };
+    result.resources.truncate(page.count);
     Ok(Json(ListResponse {
-        start_index: args.start_index.unwrap_or(1),
-        total_results: result.resources.len(),
-        items_per_page: result.items_per_page,
+        start_index: page.cursor.is_none().then_some(page.start_index),
+        total_results: result.total_count,
+        items_per_page: result.resources.len(),
+        next_cursor: result.next_cursor,
+        previous_cursor: result.previous_cursor,
         resources: result.resources,
         ..Default::default()
     }))
 }
 
+// Lists every resource matching the pushed down part of the filter, and then applies the
+// residual part and pagination to them. Cursors are the offset of the first result on the page.
+async fn list_with_residual(
+    state: &Cream,
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    args: manager::ListResourceArgs<'_>,
+    residual: &FilterRef<'_>,
+) -> Result<manager::ListResourceResult<IObject>, Error> {
+    let offset = match args.cursor {
+        Some("") => 0,
+        Some(cursor) => cursor.parse().map_err(|_| Error::invalid_cursor())?,
+        None => args.start_index,
+    };
+    let page_size = rts.manager.max_page_size();
+    let mut matching = Vec::new();
+    let mut fetched = 0;
+    loop {
+        // Fetch one more than the maximum, to tell whether it is exceeded
+        let count = page_size.min(state.0.max_residual_scan + 1 - fetched);
+        let result = rts
+            .manager
+            .list(
+                parts,
+                manager::ListResourceArgs {
+                    start_index: fetched,
+                    count,
+                    cursor: None,
+                    ..args.clone()
+                },
+            )
+            .await?;
+        let page_len = result.resources.len();
+        if page_len == 0 {
+            break;
+        }
+        fetched += page_len;
+        if fetched > state.0.max_residual_scan {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                Some(ErrorType::TooMany),
+                format!(
+                    "Evaluating the filter requires scanning more than the maximum of {} resources",
+                    state.0.max_residual_scan
+                ),
+            ));
+        }
+        matching.extend(
+            result
+                .resources
+                .into_iter()
+                .filter(|resource| residual.matches(resource, &rts.schemas)),
+        );
+        // Managers may not report an accurate total, so only a short page marks the end
+        if page_len < count {
+            break;
+        }
+    }
+
+    let total_count = matching.len();
+    let resources = matching.into_iter().skip(offset).take(args.count).collect();
+    let (next_cursor, previous_cursor) = match args.cursor {
+        Some(_) => (
+            (offset + args.count < total_count).then(|| (offset + args.count).to_string()),
+            (offset > 0).then(|| offset.saturating_sub(args.count).to_string()),
+        ),
+        None => (None, None),
+    };
+    Ok(manager::ListResourceResult {
+        resources,
+        items_per_page: args.count,
+        total_count,
+        next_cursor,
+        previous_cursor,
+    })
+}
+
 pub(crate) async fn list_resources(
     State(state): State<Cream>,
     Extension(ResourceTypeName(resource_type)): Extension<ResourceTypeName>,
     Query(args): Query<ListResourcesArgs>,
     parts: Parts,
 ) -> Result<impl IntoResponse, Error> {
-    list_resources_inner(&state, &parts, &resource_type, args).await
+    let rts = state
+        .0
+        .resource_types
+        .get(&resource_type)
+        .ok_or_else(Error::not_found)?;
+    let filter = args
+        .filter
+        .as_deref()
+        .map(|filter| state.0.filter_limits.parse(filter))
+        .transpose()?;
+
+    list_resources_inner(&state, &parts, rts, filter, args).await
 }
 
 pub(crate) async fn search_resources(
@@ -94,7 +203,18 @@ pub(crate) async fn search_resources(
     parts: Parts,
     Json(args): Json<ListResourcesArgs>,
 ) -> Result<impl IntoResponse, Error> {
-    list_resources_inner(&state, &parts, &resource_type, args).await
+    let rts = state
+        .0
+        .resource_types
+        .get(&resource_type)
+        .ok_or_else(Error::not_found)?;
+    let filter = args
+        .filter
+        .as_deref()
+        .map(|filter| state.0.filter_limits.parse(filter))
+        .transpose()?;
+
+    list_resources_inner(&state, &parts, rts, filter, args).await
 }
 
 pub(crate) async fn search_root(
@@ -102,13 +222,13 @@ pub(crate) async fn search_root(
     parts: Parts,
     Json(args): Json<ListResourcesArgs>,
 ) -> Result<impl IntoResponse, Error> {
-    let scope = Bump::new();
-    let mut translated_args = manager::ListResourceArgs::default();
-
     // Must have a resource type filter
-    let filter = args.filter.ok_or_else(Error::invalid_filter)?;
+    let filter = args.filter.as_deref().ok_or_else(Error::invalid_filter)?;
 
-    let (parsed_filter, resource_type) = filter::parse_filter(&filter)?
+    let (filter, resource_type) = state
+        .0
+        .filter_limits
+        .parse(filter)?
         .take_resource_type_filter()
         .map_err(|_| Error::invalid_filter())?;
 
@@ -118,57 +238,16 @@ pub(crate) async fn search_root(
         .get(&resource_type)
         .ok_or_else(Error::not_found)?;
 
-    let mut fixer = FixAttributeCasingVisitor::new(&rts.resource_type, &state);
-
-    if let Some(filter) = parsed_filter {
-        let filter = scope.alloc(filter);
-        // Fix the casing and URNs on any filters
-        fixer.visit_filter(filter);
-
-        translated_args.filter = Some(filter.as_ref(&scope));
-    }
-
-    if let Some(sort_by) = args.sort_by {
-        let sort_by = scope.alloc(filter::parse_attr_path(&sort_by)?);
-        // Fix the casing and URNs on any filters
-        fixer.visit_attr_path(sort_by);
-
-        translated_args.sort_by = Some(sort_by.as_ref());
-    }
-    translated_args.sort_order = args.sort_order;
-
-    translated_args.count = args
-        .count
-        .unwrap_or_else(|| rts.manager.default_page_size());
-    translated_args.start_index = args.start_index.unwrap_or(1) - 1;
-
-    let optional_attributes = list_optional_attributes(
-        &args.attributes,
-        &args.excluded_attributes,
-        &mut fixer,
-        &scope,
-    )?;
-
-    translated_args.optional_attributes = &optional_attributes;
-
-    let result = rts.manager.list(&parts, translated_args).await?;
-
-    Ok(Json(ListResponse {
-        start_index: args.start_index.unwrap_or(1),
-        total_results: result.resources.len(),
-        items_per_page: result.items_per_page,
-        resources: result.resources,
-        ..Default::default()
-    }))
+    list_resources_inner(&state, &parts, rts, filter, args).await
 }
 
 pub(crate) async fn get_resource_inner(
     state: &Cream,
     parts: &Parts,
     rts: &ResourceTypeState,
-    args: GetResourcesArgs,
+    args: &GetResourcesArgs,
     id: String,
-) -> Result<impl IntoResponse, Error> {
+) -> Result<IObject, Error> {
     let scope = Bump::new();
     let mut translated_args = manager::GetResourceArgs {
         id,
@@ -185,7 +264,24 @@ pub(crate) async fn get_resource_inner(
 
     translated_args.optional_attributes = &optional_attributes;
 
-    Ok(Json(rts.manager.get(parts, translated_args).await?))
+    rts.manager.get(parts, translated_args).await
+}
+
+// Gets a resource, responding with `304 Not Modified` if the client already has the
+// current version.
+pub(crate) async fn get_resource_response(
+    state: &Cream,
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    args: &GetResourcesArgs,
+    id: String,
+) -> Result<Response, Error> {
+    let if_none_match = EntityTags::from_headers(state, &parts.headers, header::IF_NONE_MATCH)?;
+    let result = get_resource_inner(state, parts, rts, args, id).await?;
+    if if_none_match.is_some_and(|tags| tags.matches_weak(resource_version(&result))) {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header(state, &result)).into_response());
+    }
+    Ok(with_etag(state, result).into_response())
 }
 
 pub(crate) async fn get_resource(
@@ -194,13 +290,12 @@ pub(crate) async fn get_resource(
     Path(id): Path<String>,
     Query(args): Query<GetResourcesArgs>,
     parts: Parts,
-) -> Result<impl IntoResponse, Error> {
+) -> Result<Response, Error> {
     let rts = state
         .0
         .resource_types
         .get(&resource_type)
         .ok_or_else(Error::not_found)?;
 
-    let result = get_resource_inner(&state, &parts, rts, args, id).await?;
-    Ok(result)
+    get_resource_response(&state, &parts, rts, &args, id).await
 }
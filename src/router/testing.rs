@@ -0,0 +1,143 @@
+//! Helpers for sending requests through the router in tests.
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{request::Parts, HeaderMap, Request, StatusCode},
+};
+use cream_core::{load_static_json, ResourceType, Schema};
+use ijson::IObject;
+use serde_json::Value;
+use tower::ServiceExt;
+
+use crate::{
+    manager::{
+        GenericResourceManager, GetResourceArgs, ListResourceArgs, ListResourceResult,
+        UpdateResourceArgs,
+    },
+    memory::MemoryStore,
+    CreamBuilder, Error, ServiceProviderConfig,
+};
+
+/// The example service provider configuration.
+pub(crate) fn config() -> ServiceProviderConfig {
+    load_static_json!("../../examples/smoke_config.json")
+}
+
+/// A store of users with the example schema, which counts the calls to `get`.
+#[derive(Debug, Clone)]
+pub(crate) struct Users {
+    store: Arc<MemoryStore>,
+    gets: Arc<AtomicUsize>,
+}
+
+impl Users {
+    pub(crate) fn new() -> Self {
+        Self {
+            store: Arc::new(MemoryStore::new(
+                load_static_json!("../../examples/user_type.json"),
+                [load_static_json!("../../examples/user_schema.json")],
+            )),
+            gets: Arc::default(),
+        }
+    }
+
+    /// The number of calls to `get` since the last call to this method.
+    pub(crate) fn take_gets(&self) -> usize {
+        self.gets.swap(0, Ordering::SeqCst)
+    }
+
+    /// Constructs a builder with the example configuration, which manages these users.
+    pub(crate) fn builder(&self) -> CreamBuilder {
+        self.builder_with(config())
+    }
+
+    /// Constructs a builder with the given configuration, which manages these users.
+    pub(crate) fn builder_with(&self, config: ServiceProviderConfig) -> CreamBuilder {
+        CreamBuilder::new("http://localhost", config).resource_type(self.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl GenericResourceManager for Users {
+    async fn list(
+        &self,
+        parts: &'async_trait Parts,
+        args: ListResourceArgs<'async_trait>,
+    ) -> Result<ListResourceResult<IObject>, Error> {
+        self.store.list(parts, args).await
+    }
+    async fn get(
+        &self,
+        parts: &'async_trait Parts,
+        args: GetResourceArgs<'async_trait>,
+    ) -> Result<IObject, Error> {
+        self.gets.fetch_add(1, Ordering::SeqCst);
+        self.store.get(parts, args).await
+    }
+    async fn create(&self, parts: &'async_trait Parts, resource: IObject) -> Result<String, Error> {
+        self.store.create(parts, resource).await
+    }
+    async fn update(
+        &self,
+        parts: &'async_trait Parts,
+        args: UpdateResourceArgs<'async_trait>,
+    ) -> Result<(), Error> {
+        self.store.update(parts, args).await
+    }
+    async fn replace(
+        &self,
+        parts: &'async_trait Parts,
+        id: &str,
+        resource: IObject,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        self.store.replace(parts, id, resource, version).await
+    }
+    async fn delete(
+        &self,
+        parts: &'async_trait Parts,
+        id: &str,
+        version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        self.store.delete(parts, id, version).await
+    }
+    fn load_resource_type(&self) -> ResourceType {
+        self.store.load_resource_type()
+    }
+    fn load_schema(&self, id: &str) -> Schema {
+        self.store.load_schema(id)
+    }
+}
+
+/// Builds a request, with a SCIM body if one is given.
+pub(crate) fn request(method: &str, uri: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder().method(method).uri(uri);
+    match body {
+        Some(body) => builder
+            .header("content-type", "application/scim+json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap()
+}
+
+/// Sends a request, returning the status, headers and body of the response. An empty body is
+/// returned as `null`.
+pub(crate) async fn send(
+    builder: CreamBuilder,
+    request: Request<Body>,
+) -> (StatusCode, HeaderMap, Value) {
+    let response = builder.build().router().oneshot(request).await.unwrap();
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.unwrap();
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&body).unwrap()
+    };
+    (parts.status, parts.headers, body)
+}
@@ -1,6 +1,6 @@
 use axum::{
     extract::{Path, Query, State},
-    http::{request::Parts, StatusCode},
+    http::{header, request::Parts, StatusCode},
     response::IntoResponse,
     Extension,
 };
@@ -12,13 +12,16 @@ use crate::{
     filter::{self, AttrPathRef, Visitor},
     json::Json,
     manager::{self, UpdateResourceItem},
+    state::ResourceTypeState,
     Cream, Error,
 };
 
 use super::{
     args::{FixAttributeCasingVisitor, GetResourcesArgs, PatchOperationType, PatchResourceArgs},
+    etag::{check_if_match, with_etag, EntityTags},
+    mutability, paths,
     retrieve::get_resource_inner,
-    ResourceTypeName,
+    validate, ResourceTypeName,
 };
 
 fn normalize_update<'a>(
@@ -49,6 +52,20 @@ fn normalize_update<'a>(
     items
 }
 
+pub(crate) async fn create_resource_inner(
+    state: &Cream,
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    args: &GetResourcesArgs,
+    mut body: IObject,
+) -> Result<IObject, Error> {
+    mutability::prepare_create(&rts.schemas, &mut body);
+    validate::validate_resource(&rts.schemas, &body)?;
+    let id = rts.manager.create(parts, body).await?;
+
+    get_resource_inner(state, parts, rts, args, id).await
+}
+
 pub(crate) async fn create_resource(
     State(state): State<Cream>,
     Extension(ResourceTypeName(resource_type)): Extension<ResourceTypeName>,
@@ -61,39 +78,43 @@ pub(crate) async fn create_resource(
         .resource_types
         .get(&resource_type)
         .ok_or_else(Error::not_found)?;
-    let id = rts.manager.create(&parts, body).await?;
 
-    get_resource_inner(&state, &parts, rts, args, id)
+    create_resource_inner(&state, &parts, rts, &args, body)
         .await
-        .map(|r| (StatusCode::CREATED, r))
+        .map(|r| (StatusCode::CREATED, with_etag(&state, r)))
 }
 
-pub(crate) async fn patch_resource(
-    State(state): State<Cream>,
-    Extension(ResourceTypeName(resource_type)): Extension<ResourceTypeName>,
-    Path(id): Path<String>,
-    Query(args): Query<GetResourcesArgs>,
-    parts: Parts,
-    Json(body): Json<PatchResourceArgs>,
-) -> Result<impl IntoResponse, Error> {
+pub(crate) async fn patch_resource_inner(
+    state: &Cream,
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    args: &GetResourcesArgs,
+    id: String,
+    body: PatchResourceArgs,
+    if_match: Option<&EntityTags>,
+) -> Result<IObject, Error> {
     let scope = Bump::new();
-    let rts = state
-        .0
-        .resource_types
-        .get(&resource_type)
-        .ok_or_else(Error::not_found)?;
-    let mut fixer = FixAttributeCasingVisitor::new(&rts.resource_type, &state);
+    let mut fixer = FixAttributeCasingVisitor::new(&rts.resource_type, state);
 
-    let mut items = Vec::new();
-    for operation in &body.operations {
+    let mut body = body;
+    let mut paths = Vec::with_capacity(body.operations.len());
+    for operation in &mut body.operations {
         let path = if operation.path.is_empty() {
             None
         } else {
-            let value_path = scope.alloc(filter::parse_value_path(&operation.path)?);
+            let value_path = scope.alloc(state.0.filter_limits.parse_value_path(&operation.path)?);
             fixer.visit_value_path(value_path);
-            Some(value_path.as_ref(&scope))
+            value_path.coerce(&rts.schemas)?;
+            let value_path = value_path.as_ref(&scope);
+            paths::validate_value_path(&rts.schemas, value_path)?;
+            Some(value_path)
         };
+        mutability::prepare_patch_value(&rts.schemas, path, &mut operation.value);
+        paths.push(path);
+    }
 
+    let mut items = Vec::new();
+    for (operation, path) in body.operations.iter().zip(paths) {
         // An add or replace at the top level operates field-wise. Translate it to a series of
         // individual updates to make life easier for the manager.
         if path.is_none()
@@ -103,12 +124,19 @@ pub(crate) async fn patch_resource(
             )
         {
             if let Some(obj) = operation.value.as_object() {
-                items.extend(normalize_update(
+                let normalized = normalize_update(
                     obj,
                     &rts.resource_type.schema,
                     &rts.resource_type.schema_extensions,
                     true,
-                ));
+                );
+                // Reject unknown keys just as an operation with an unknown path would be
+                for item in &normalized {
+                    if let Some(filter::ValuePathRef::Attr(path)) = item.path {
+                        paths::validate_attr_path(&rts.schemas, path)?;
+                    }
+                }
+                items.extend(normalized);
                 continue;
             }
         }
@@ -121,14 +149,62 @@ pub(crate) async fn patch_resource(
         items.push(UpdateResourceItem { path, op })
     }
 
+    let dependencies = mutability::update_dependencies(&rts.schemas, &items);
+    let (current, version) =
+        mutability::fetch_current(parts, rts, &id, &dependencies, if_match).await?;
+    mutability::check_update(&rts.schemas, &items, current.as_ref())?;
+
     let translated_args = manager::UpdateResourceArgs {
         id: &id,
         items: &items,
+        version: version.as_deref(),
     };
 
-    rts.manager.update(&parts, translated_args).await?;
+    rts.manager.update(parts, translated_args).await?;
+
+    get_resource_inner(state, parts, rts, args, id).await
+}
+
+pub(crate) async fn patch_resource(
+    State(state): State<Cream>,
+    Extension(ResourceTypeName(resource_type)): Extension<ResourceTypeName>,
+    Path(id): Path<String>,
+    Query(args): Query<GetResourcesArgs>,
+    parts: Parts,
+    Json(body): Json<PatchResourceArgs>,
+) -> Result<impl IntoResponse, Error> {
+    let rts = state
+        .0
+        .resource_types
+        .get(&resource_type)
+        .ok_or_else(Error::not_found)?;
 
-    get_resource_inner(&state, &parts, rts, args, id).await
+    let if_match = EntityTags::from_headers(&state, &parts.headers, header::IF_MATCH)?;
+    patch_resource_inner(&state, &parts, rts, &args, id, body, if_match.as_ref())
+        .await
+        .map(|r| with_etag(&state, r))
+}
+
+pub(crate) async fn put_resource_inner(
+    state: &Cream,
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    args: &GetResourcesArgs,
+    id: String,
+    mut body: IObject,
+    if_match: Option<&EntityTags>,
+) -> Result<IObject, Error> {
+    let dependencies = mutability::replace_dependencies(&rts.schemas, &body);
+    let (current, version) =
+        mutability::fetch_current(parts, rts, &id, &dependencies, if_match).await?;
+    mutability::prepare_replace(&rts.schemas, &mut body, current.as_ref())?;
+    validate::validate_resource(&rts.schemas, &body)?;
+
+    rts.manager
+        .replace(parts, &id, body, version.as_deref())
+        .await?;
+
+    get_resource_inner(state, parts, rts, args, id).await
 }
 
 pub(crate) async fn put_resource(
@@ -144,9 +220,21 @@ pub(crate) async fn put_resource(
         .resource_types
         .get(&resource_type)
         .ok_or_else(Error::not_found)?;
-    rts.manager.replace(&parts, &id, body).await?;
 
-    get_resource_inner(&state, &parts, rts, args, id).await
+    let if_match = EntityTags::from_headers(&state, &parts.headers, header::IF_MATCH)?;
+    put_resource_inner(&state, &parts, rts, &args, id, body, if_match.as_ref())
+        .await
+        .map(|r| with_etag(&state, r))
+}
+
+pub(crate) async fn delete_resource_inner(
+    parts: &Parts,
+    rts: &ResourceTypeState,
+    id: &str,
+    if_match: Option<&EntityTags>,
+) -> Result<(), Error> {
+    let version = check_if_match(parts, rts, id, if_match).await?;
+    rts.manager.delete(parts, id, version.as_deref()).await
 }
 
 pub(crate) async fn delete_resource(
@@ -160,6 +248,10 @@ pub(crate) async fn delete_resource(
         .resource_types
         .get(&resource_type)
         .ok_or_else(Error::not_found)?;
-    rts.manager.delete(&parts, &id).await?;
+    let if_match = EntityTags::from_headers(&state, &parts.headers, header::IF_MATCH)?;
+    delete_resource_inner(&parts, rts, &id, if_match.as_ref()).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,66 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use crate::router::testing::{request, send, Users};
+
+fn patch(operation: Value) -> Value {
+    json!({
+        "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+        "Operations": [operation]
+    })
+}
+
+#[tokio::test]
+async fn test_patch_unknown_attribute() {
+    let users = Users::new();
+    let (_, _, body) = send(
+        users.builder(),
+        request("POST", "/Users", Some(json!({ "userName": "bjensen" }))),
+    )
+    .await;
+    let uri = format!("/Users/{}", body["id"].as_str().unwrap());
+
+    // An unknown attribute is rejected whether it is given as the path or as a key of the value.
+    let cases = [
+        (
+            json!({ "op": "replace", "path": "bogus", "value": 1 }),
+            "Unknown attribute `bogus`",
+        ),
+        (
+            json!({ "op": "replace", "value": { "displayName": "Babs", "bogus": 1 } }),
+            "Unknown attribute `bogus`",
+        ),
+        (
+            json!({ "op": "add", "value": { "urn:example:unknown": { "bogus": 1 } } }),
+            "Unknown attribute `urn:example:unknown`",
+        ),
+    ];
+    for (operation, detail) in cases {
+        let (status, _, body) = send(
+            users.builder(),
+            request("PATCH", &uri, Some(patch(operation.clone()))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", operation);
+        assert_eq!(body["scimType"], "invalidPath", "{}", operation);
+        assert_eq!(body["detail"], detail, "{}", operation);
+    }
+
+    let (status, _, body) = send(users.builder(), request("GET", &uri, None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("displayName").is_none());
+
+    let (status, _, body) = send(
+        users.builder(),
+        request(
+            "PATCH",
+            &uri,
+            Some(patch(
+                json!({ "op": "replace", "value": { "displayName": "Babs" } }),
+            )),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["displayName"], "Babs");
+}
@@ -0,0 +1,133 @@
+//! Validation of resource bodies against their schemas, before they are passed to the manager.
+//!
+//! Attributes which are not defined by any schema are left for the manager to deal with, and
+//! `null` is treated as equivalent to an omitted value. Read-only attributes are removed from
+//! the body before validation, so they are never required.
+use cream_core::{Attribute, Mutability, Type};
+use ijson::{IObject, IValue};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    filter::AttrPathRef,
+    json::get_ignore_case,
+    schema::{describe_type, SchemaRegistry},
+    Error,
+};
+
+fn is_base64(value: &str) -> bool {
+    let data = value.trim_end_matches('=');
+    value.len().is_multiple_of(4)
+        && value.len() - data.len() <= 2
+        && data
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+}
+
+fn is_reference(value: &str) -> bool {
+    !value.is_empty() && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn is_valid_scalar(type_: &Type, value: &IValue) -> bool {
+    match type_ {
+        Type::String => value.is_string(),
+        Type::Boolean => value.is_bool(),
+        Type::Decimal => value.is_number(),
+        Type::Integer => value
+            .as_number()
+            .is_some_and(|number| !number.has_decimal_point() && number.to_i64().is_some()),
+        Type::DateTime => value
+            .as_string()
+            .is_some_and(|s| OffsetDateTime::parse(s, &Rfc3339).is_ok()),
+        Type::Binary => value.as_string().is_some_and(|s| is_base64(s)),
+        Type::Reference => value.as_string().is_some_and(|s| is_reference(s)),
+        Type::Complex => value.is_object(),
+    }
+}
+
+// Paths are built by appending attribute names to a prefix, eg. `emails[1].` or the URN of an
+// extension schema followed by `:`.
+fn check_required(attributes: &[Attribute], obj: &IObject, prefix: &str) -> Result<(), Error> {
+    for attr in attributes {
+        if attr.required
+            && !matches!(attr.mutability, Mutability::ReadOnly)
+            && get_ignore_case(obj, &attr.name).is_none_or(IValue::is_null)
+        {
+            return Err(Error::invalid_value(
+                &format!("{}{}", prefix, attr.name),
+                "a value",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_single(attr: &Attribute, value: &IValue, path: &str) -> Result<(), Error> {
+    if !is_valid_scalar(&attr.type_, value) {
+        return Err(Error::invalid_value(path, describe_type(&attr.type_)));
+    }
+    if let (Some(obj), Some(sub_attributes)) = (value.as_object(), &attr.sub_attributes) {
+        validate_attributes(sub_attributes, obj, &format!("{}.", path))?;
+    }
+    Ok(())
+}
+
+fn validate_attribute(attr: &Attribute, value: &IValue, path: &str) -> Result<(), Error> {
+    if value.is_null() {
+        return Ok(());
+    }
+    if attr.multi_valued {
+        let arr = value
+            .as_array()
+            .ok_or_else(|| Error::invalid_value(path, "an array"))?;
+        for (i, item) in arr.iter().enumerate() {
+            validate_single(attr, item, &format!("{}[{}]", path, i))?;
+        }
+        Ok(())
+    } else if value.is_array() {
+        Err(Error::invalid_value(path, describe_type(&attr.type_)))
+    } else {
+        validate_single(attr, value, path)
+    }
+}
+
+fn validate_attributes(attributes: &[Attribute], obj: &IObject, prefix: &str) -> Result<(), Error> {
+    check_required(attributes, obj, prefix)?;
+    for (key, value) in obj {
+        if let Some(attr) = attributes
+            .iter()
+            .find(|attr| attr.name.eq_ignore_ascii_case(key))
+        {
+            validate_attribute(attr, value, &format!("{}{}", prefix, key.as_str()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Validates a resource body against the schemas of its resource type.
+pub(crate) fn validate_resource(schemas: &SchemaRegistry, body: &IObject) -> Result<(), Error> {
+    let core = schemas.attributes(None).unwrap_or_default();
+    check_required(core, body, "")?;
+    for (key, value) in body {
+        if let Some(ext) = schemas.extension(key) {
+            let obj = value
+                .as_object()
+                .ok_or_else(|| Error::invalid_value(key, "an object"))?;
+            validate_attributes(&ext.attributes, obj, &format!("{}:", ext.id))?;
+        } else if let Some(attr) = schemas.attribute(AttrPathRef {
+            urn: None,
+            name: key,
+            sub_attr: None,
+        }) {
+            validate_attribute(attr, value, key)?;
+        }
+    }
+    for (ext, required) in schemas.extensions() {
+        if required && get_ignore_case(body, &ext.id).is_none_or(IValue::is_null) {
+            return Err(Error::invalid_value(&ext.id, "an object"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,78 @@
+use ijson::{ijson, IObject, IValue};
+
+use crate::schema::test_registry;
+
+use super::validate_resource;
+
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+fn validate(value: IValue) -> Result<(), String> {
+    let body: IObject = value.into_object().unwrap();
+    validate_resource(&test_registry(), &body).map_err(|e| e.detail)
+}
+
+#[test]
+fn test_valid() {
+    assert_eq!(
+        validate(ijson!({
+            "userName": "alice",
+            "active": true,
+            "lastLogin": "2024-01-01T00:00:00Z",
+            "emails": [{ "value": "alice@example.com", "primary": true }],
+            "x509Certificates": [{ "value": "aGVsbG8=" }],
+            "externalId": "a1",
+            "unknown": 42,
+            ENTERPRISE: { "manager": { "value": "1" } }
+        })),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_invalid() {
+    let cases = [
+        (
+            ijson!({ ENTERPRISE: {} }),
+            "Invalid value for `userName`: expected a value",
+        ),
+        (
+            ijson!({ "userName": "alice" }),
+            "Invalid value for `urn:ietf:params:scim:schemas:extension:enterprise:2.0:User`: expected an object",
+        ),
+        (
+            ijson!({ "userName": "alice", "active": "yes", ENTERPRISE: {} }),
+            "Invalid value for `active`: expected a boolean",
+        ),
+        (
+            ijson!({
+                "userName": "alice",
+                "x509Certificates": [{ "value": "not base64!" }],
+                ENTERPRISE: {}
+            }),
+            "Invalid value for `x509Certificates[0].value`: expected a base64-encoded string",
+        ),
+        (
+            ijson!({ "userName": "alice", "emails": { "value": "x" }, ENTERPRISE: {} }),
+            "Invalid value for `emails`: expected an array",
+        ),
+        (
+            ijson!({
+                "userName": "alice",
+                "emails": [{ "value": "x" }, { "value": 1 }],
+                ENTERPRISE: {}
+            }),
+            "Invalid value for `emails[1].value`: expected a string",
+        ),
+        (
+            ijson!({ "userName": "alice", "lastLogin": "yesterday", ENTERPRISE: {} }),
+            "Invalid value for `lastLogin`: expected an RFC 3339 date and time",
+        ),
+        (
+            ijson!({ "userName": "alice", ENTERPRISE: { "manager": { "value": 1 } } }),
+            "Invalid value for `urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value`: expected a string",
+        ),
+    ];
+    for (body, expected) in cases {
+        assert_eq!(validate(body), Err(expected.to_string()));
+    }
+}
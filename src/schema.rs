@@ -1,6 +1,159 @@
-use cream_core::{Attribute, Returned, Schema};
+use cream_core::{Attribute, ResourceType, Returned, Schema, Type};
 
-use crate::filter::{AttrPath, AttrPathRef};
+use crate::{
+    filter::{AttrPath, AttrPathRef},
+    manager::GenericResourceManager,
+};
+
+/// The schemas which make up a resource type, used to look up attribute definitions.
+///
+/// In addition to the attributes defined by the core and extension schemas, the registry knows
+/// about the common attributes (`id`, `externalId` and `meta`) which are part of every resource.
+#[derive(Debug, Clone)]
+pub struct SchemaRegistry {
+    core: Schema,
+    extensions: Vec<(Schema, bool)>,
+    common: Vec<Attribute>,
+}
+
+// Describes the values of an attribute type, as in "expected a boolean".
+pub(crate) fn describe_type(type_: &Type) -> &'static str {
+    match type_ {
+        Type::String => "a string",
+        Type::Boolean => "a boolean",
+        Type::Decimal => "a number",
+        Type::Integer => "an integer",
+        Type::DateTime => "an RFC 3339 date and time",
+        Type::Binary => "a base64-encoded string",
+        Type::Reference => "a URI reference",
+        Type::Complex => "an object",
+    }
+}
+
+impl SchemaRegistry {
+    /// Construct a registry for the given resource type, using `load_schema` to look up each
+    /// of the schemas it references.
+    pub fn new<'a>(
+        resource_type: &ResourceType,
+        mut load_schema: impl FnMut(&str) -> &'a Schema,
+    ) -> Self {
+        Self {
+            core: load_schema(&resource_type.schema).clone(),
+            extensions: resource_type
+                .schema_extensions
+                .iter()
+                .map(|ext| (load_schema(&ext.schema).clone(), ext.required))
+                .collect(),
+            common: Attribute::common(),
+        }
+    }
+
+    /// Construct a registry for the resource type handled by a manager.
+    pub fn from_manager(manager: &(impl GenericResourceManager + ?Sized)) -> Self {
+        let resource_type = manager.load_resource_type();
+        Self {
+            core: manager.load_schema(&resource_type.schema),
+            extensions: resource_type
+                .schema_extensions
+                .iter()
+                .map(|ext| (manager.load_schema(&ext.schema), ext.required))
+                .collect(),
+            common: Attribute::common(),
+        }
+    }
+
+    /// The core schema.
+    pub fn core(&self) -> &Schema {
+        &self.core
+    }
+
+    /// Iterates over the extension schemas, and whether they are required.
+    pub fn extensions(&self) -> impl Iterator<Item = (&Schema, bool)> {
+        self.extensions
+            .iter()
+            .map(|(schema, required)| (schema, *required))
+    }
+
+    /// Finds the extension schema with the given ID.
+    pub fn extension(&self, urn: &str) -> Option<&Schema> {
+        self.extensions()
+            .map(|(schema, _)| schema)
+            .find(|schema| schema.id.eq_ignore_ascii_case(urn))
+    }
+
+    /// Finds the top-level attributes defined by the schema with the given ID, or by the core
+    /// schema if no ID is given.
+    pub fn attributes(&self, urn: Option<&str>) -> Option<&[Attribute]> {
+        match urn {
+            Some(urn) if !urn.eq_ignore_ascii_case(&self.core.id) => self
+                .extension(urn)
+                .map(|schema| schema.attributes.as_slice()),
+            _ => Some(&self.core.attributes),
+        }
+    }
+
+    /// Finds the definition of an attribute, or a sub-attribute if the path has one.
+    pub fn attribute(&self, path: AttrPathRef) -> Option<&Attribute> {
+        let is_core = path
+            .urn
+            .is_none_or(|urn| urn.eq_ignore_ascii_case(&self.core.id));
+        let attr = Attribute::find(self.attributes(path.urn)?, path.name).or_else(|| {
+            if is_core {
+                Attribute::find(&self.common, path.name)
+            } else {
+                None
+            }
+        })?;
+        match path.sub_attr {
+            Some(sub_attr) => attr.sub_attribute(sub_attr),
+            None => Some(attr),
+        }
+    }
+
+    /// Finds the attribute which a filter compares when it compares the given path with a
+    /// value, along with its path. A complex attribute is compared using its `value`
+    /// sub-attribute, so `emails` resolves to `emails.value`.
+    pub fn comparison_attribute<'a>(
+        &self,
+        path: AttrPathRef<'a>,
+    ) -> Option<(AttrPathRef<'a>, &Attribute)> {
+        let attr = self.attribute(path)?;
+        let comparison = attr.comparison_attribute()?;
+        let path = if std::ptr::eq(attr, comparison) {
+            path
+        } else {
+            AttrPathRef {
+                sub_attr: Some("value"),
+                ..path
+            }
+        };
+        Some((path, comparison))
+    }
+}
+
+/// The registry for the example `User` resource type, extended with the enterprise schema.
+///
+/// The schemas are test fixtures rather than the examples: they add the non-standard
+/// `loginCount` and `lastLogin` attributes, and make `employeeNumber` immutable.
+#[cfg(test)]
+pub(crate) fn test_registry() -> SchemaRegistry {
+    let core: Schema = serde_json::from_str(include_str!("testdata/user_schema.json")).unwrap();
+    let enterprise: Schema =
+        serde_json::from_str(include_str!("testdata/enterprise_user_schema.json")).unwrap();
+    let mut resource_type: ResourceType =
+        serde_json::from_str(include_str!("../examples/user_type.json")).unwrap();
+    resource_type.schema_extensions = vec![cream_core::SchemaExtension {
+        schema: enterprise.id.clone(),
+        required: true,
+    }];
+    SchemaRegistry::new(&resource_type, |id| {
+        if id == enterprise.id {
+            &enterprise
+        } else {
+            &core
+        }
+    })
+}
 
 fn fix_attribute_casing_inner(schema: &Schema, name: &mut String, parent_name: Option<&str>) {
     if let Some(parent_name) = parent_name {
@@ -0,0 +1,370 @@
+//! Translation of SCIM filters and sort orders into SQL.
+//!
+//! Resource managers backed by a relational database can use [`SqlTranslator`] to turn the
+//! filter passed to [`GenericResourceManager::list`](crate::GenericResourceManager::list)
+//! into a parameterised `WHERE` clause, instead of walking the [`FilterRef`] tree by hand.
+
+use std::fmt::Write;
+
+use cream_core::{Attribute, DateTime, Type};
+
+use crate::{
+    filter::{AttrPathRef, CompValueRef, CompareOp, FilterRef},
+    manager::SortOrder,
+    schema::SchemaRegistry,
+    Error,
+};
+
+/// The SQL representation of an attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlColumn {
+    /// An expression which can be used directly in the query, such as `users.user_name`.
+    Expr(String),
+    /// A multi-valued attribute which is stored in a child table, with one row per value.
+    /// `join` is a condition relating rows of the child table to the parent row, such as
+    /// `user_emails.user_id = users.id`.
+    ///
+    /// The sub-attributes of the attribute must be mapped to columns of the child table. A
+    /// comparison against the attribute itself uses its `value` sub-attribute.
+    Table {
+        /// The name of the child table.
+        table: String,
+        /// The condition joining the child table to the parent.
+        join: String,
+    },
+}
+
+/// A value bound to a parameter of a SQL fragment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    Int(i64),
+    /// A floating point number.
+    Float(f64),
+    /// A string.
+    Text(String),
+    /// A date and time. Databases which store dates as text should bind it as an RFC 3339
+    /// string in UTC, so that dates compare in order.
+    DateTime(DateTime),
+}
+
+/// A fragment of SQL along with the values of its parameters, in order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SqlFragment {
+    /// The SQL text, containing a placeholder for each parameter.
+    pub sql: String,
+    /// The values to bind to the parameters.
+    pub params: Vec<SqlValue>,
+}
+
+/// Translates filters and sort orders into SQL, using a mapping from attributes to columns.
+///
+/// Attributes are mapped by a function which is called with each attribute path found in the
+/// filter. Filters referring to attributes which are not mapped are rejected with an
+/// `invalidFilter` error. String comparisons are case-insensitive unless the attribute is
+/// `caseExact`, in which case the database's collation applies. Note that `LIKE` ignores case
+/// by default in some databases, including SQLite and MySQL.
+pub struct SqlTranslator<'a> {
+    schemas: &'a SchemaRegistry,
+    columns: Box<dyn Fn(AttrPathRef) -> Option<SqlColumn> + 'a>,
+    numbered: bool,
+}
+
+// The resolved column for an attribute path.
+struct Column<'a> {
+    expr: String,
+    table: Option<(String, String)>,
+    attr: Option<&'a Attribute>,
+}
+
+impl Column<'_> {
+    // Whether string comparisons against this column ignore case.
+    fn ignore_case(&self) -> bool {
+        self.attr.is_none_or(|attr| {
+            !attr.case_exact && matches!(attr.type_, Type::String | Type::Reference)
+        })
+    }
+    // The expression to compare against a literal, or to sort by.
+    fn lhs(&self, literal: Option<CompValueRef>) -> String {
+        match literal {
+            Some(CompValueRef::Str(_)) | None if self.ignore_case() => {
+                format!("LOWER({})", self.expr)
+            }
+            _ => self.expr.clone(),
+        }
+    }
+}
+
+// Escapes the special characters of a `LIKE` pattern, using `!` as the escape character.
+fn escape_like(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '!' | '%' | '_') {
+            result.push('!');
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn same_attribute(a: AttrPathRef, b: AttrPathRef) -> bool {
+    a.name.eq_ignore_ascii_case(b.name)
+        && match (a.urn, b.urn) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (None, None) => true,
+            _ => false,
+        }
+}
+
+struct Writer {
+    fragment: SqlFragment,
+    numbered: bool,
+}
+
+impl Writer {
+    fn push(&mut self, sql: &str) {
+        self.fragment.sql.push_str(sql);
+    }
+    fn param(&mut self, value: SqlValue) {
+        self.fragment.params.push(value);
+        if self.numbered {
+            write!(self.fragment.sql, "${}", self.fragment.params.len()).expect("Infallible");
+        } else {
+            self.fragment.sql.push('?');
+        }
+    }
+}
+
+impl<'a> SqlTranslator<'a> {
+    /// Construct a translator for resources with the given schemas. `columns` returns the
+    /// SQL representation of an attribute, or `None` if it cannot be filtered on.
+    ///
+    /// Parameters are written as `?` placeholders.
+    pub fn new(
+        schemas: &'a SchemaRegistry,
+        columns: impl Fn(AttrPathRef) -> Option<SqlColumn> + 'a,
+    ) -> Self {
+        Self {
+            schemas,
+            columns: Box::new(columns),
+            numbered: false,
+        }
+    }
+
+    /// Write parameters as numbered placeholders (`$1`, `$2`, ...), as used by PostgreSQL.
+    pub fn numbered_params(mut self) -> Self {
+        self.numbered = true;
+        self
+    }
+
+    fn resolve(&self, path: AttrPathRef, scope: Option<AttrPathRef>) -> Result<Column<'a>, Error> {
+        let parent = AttrPathRef {
+            sub_attr: None,
+            ..path
+        };
+        let table = match (self.columns)(parent) {
+            Some(SqlColumn::Table { table, join }) => Some((table, join)),
+            _ => None,
+        };
+        // Multi-valued attributes stored in a table are compared using their `value` column.
+        let path = match self.schemas.comparison_attribute(path) {
+            Some((path, _)) => path,
+            None => path,
+        };
+        let path = if path.sub_attr.is_none() && table.is_some() {
+            AttrPathRef {
+                sub_attr: Some("value"),
+                ..path
+            }
+        } else {
+            path
+        };
+        let Some(SqlColumn::Expr(expr)) = (self.columns)(path) else {
+            return Err(Error::invalid_filter());
+        };
+        Ok(Column {
+            expr,
+            // Within a value filter on the same attribute, the child table is already in scope.
+            table: table.filter(|_| scope.is_none_or(|scope| !same_attribute(scope, parent))),
+            attr: self.schemas.attribute(path),
+        })
+    }
+
+    // Writes the condition for a comparison against a single row.
+    fn write_compare(
+        &self,
+        w: &mut Writer,
+        column: &Column,
+        op: CompareOp,
+        literal: CompValueRef,
+    ) -> Result<(), Error> {
+        let value = match literal {
+            CompValueRef::Null => {
+                let sql = match op {
+                    CompareOp::Equal => "IS NULL",
+                    CompareOp::NotEqual => "IS NOT NULL",
+                    _ => return Err(Error::invalid_filter()),
+                };
+                w.push(&format!("{} {}", column.expr, sql));
+                return Ok(());
+            }
+            CompValueRef::Bool(b) => SqlValue::Bool(b),
+            CompValueRef::Num(n) => match n.to_i64() {
+                Some(n) => SqlValue::Int(n),
+                None => SqlValue::Float(n.to_f64_lossy()),
+            },
+            CompValueRef::Str(s) if column.ignore_case() => SqlValue::Text(s.to_lowercase()),
+            CompValueRef::Str(s) => SqlValue::Text(s.to_string()),
+            CompValueRef::DateTime(dt) => SqlValue::DateTime(dt),
+        };
+        let lhs = column.lhs(Some(literal));
+        let sql_op = match op {
+            CompareOp::Equal => "=",
+            CompareOp::NotEqual => {
+                w.push(&format!("({} IS NULL OR {} <> ", column.expr, lhs));
+                w.param(value);
+                w.push(")");
+                return Ok(());
+            }
+            CompareOp::Contains | CompareOp::StartsWith | CompareOp::EndsWith => {
+                let SqlValue::Text(s) = value else {
+                    return Err(Error::invalid_filter());
+                };
+                let pattern = match op {
+                    CompareOp::Contains => format!("%{}%", escape_like(&s)),
+                    CompareOp::StartsWith => format!("{}%", escape_like(&s)),
+                    _ => format!("%{}", escape_like(&s)),
+                };
+                w.push(&format!("{} LIKE ", lhs));
+                w.param(SqlValue::Text(pattern));
+                w.push(" ESCAPE '!'");
+                return Ok(());
+            }
+            _ if matches!(value, SqlValue::Bool(_)) => return Err(Error::invalid_filter()),
+            CompareOp::GreaterThan => ">",
+            CompareOp::GreaterThanOrEqual => ">=",
+            CompareOp::LessThan => "<",
+            CompareOp::LessThanOrEqual => "<=",
+        };
+        w.push(&format!("{} {} ", lhs, sql_op));
+        w.param(value);
+        Ok(())
+    }
+
+    fn write_filter(
+        &self,
+        w: &mut Writer,
+        filter: &FilterRef,
+        scope: Option<AttrPathRef>,
+    ) -> Result<(), Error> {
+        match *filter {
+            FilterRef::Present(path) => {
+                let column = self.resolve(path, scope)?;
+                if let Some((table, join)) = &column.table {
+                    w.push(&format!(
+                        "EXISTS (SELECT 1 FROM {} WHERE {} AND ",
+                        table, join
+                    ));
+                    w.push(&format!("{} IS NOT NULL)", column.expr));
+                } else {
+                    w.push(&format!("{} IS NOT NULL", column.expr));
+                }
+            }
+            FilterRef::Compare(path, op, literal) => {
+                let column = self.resolve(path, scope)?;
+                let Some((table, join)) = &column.table else {
+                    return self.write_compare(w, &column, op, literal);
+                };
+                // A multi-valued attribute matches if any of its values match. Conversely,
+                // `ne` matches if none of the values are equal.
+                let (exists, op, literal) = match (op, literal) {
+                    (CompareOp::Equal, CompValueRef::Null) => {
+                        ("NOT EXISTS", CompareOp::NotEqual, literal)
+                    }
+                    (CompareOp::NotEqual, CompValueRef::Null) => ("EXISTS", op, literal),
+                    (CompareOp::NotEqual, _) => ("NOT EXISTS", CompareOp::Equal, literal),
+                    _ => ("EXISTS", op, literal),
+                };
+                w.push(&format!(
+                    "{} (SELECT 1 FROM {} WHERE {} AND ",
+                    exists, table, join
+                ));
+                self.write_compare(w, &column, op, literal)?;
+                w.push(")");
+            }
+            FilterRef::Has(path, inner) => match (self.columns)(path) {
+                Some(SqlColumn::Table { table, join })
+                    if scope.is_none_or(|scope| !same_attribute(scope, path)) =>
+                {
+                    w.push(&format!(
+                        "EXISTS (SELECT 1 FROM {} WHERE {} AND ",
+                        table, join
+                    ));
+                    self.write_filter(w, inner, Some(path))?;
+                    w.push(")");
+                }
+                _ => self.write_filter(w, inner, scope)?,
+            },
+            FilterRef::And(filters) | FilterRef::Or(filters) => {
+                let (sep, empty) = match filter {
+                    FilterRef::And(_) => (" AND ", "1 = 1"),
+                    _ => (" OR ", "1 = 0"),
+                };
+                if filters.is_empty() {
+                    w.push(empty);
+                    return Ok(());
+                }
+                w.push("(");
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        w.push(sep);
+                    }
+                    self.write_filter(w, filter, scope)?;
+                }
+                w.push(")");
+            }
+            // Comparisons against NULL columns are neither true nor false in SQL, but an
+            // attribute which is not present does not match a filter.
+            FilterRef::Not(inner) => {
+                w.push("NOT COALESCE(");
+                self.write_filter(w, inner, scope)?;
+                w.push(", FALSE)");
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate a filter into a condition suitable for a `WHERE` clause.
+    pub fn where_clause(&self, filter: &FilterRef) -> Result<SqlFragment, Error> {
+        let mut w = Writer {
+            fragment: SqlFragment::default(),
+            numbered: self.numbered,
+        };
+        self.write_filter(&mut w, filter, None)?;
+        Ok(w.fragment)
+    }
+
+    /// Translate a sort order into the expressions of an `ORDER BY` clause. Resources which
+    /// do not have the attribute are sorted last. Multi-valued attributes are sorted by
+    /// their smallest value.
+    pub fn order_by(&self, sort_by: AttrPathRef, sort_order: SortOrder) -> Result<String, Error> {
+        let column = self
+            .resolve(sort_by, None)
+            .map_err(|_| Error::invalid_path())?;
+        let lhs = column.lhs(None);
+        let expr = match &column.table {
+            Some((table, join)) => format!("(SELECT MIN({}) FROM {} WHERE {})", lhs, table, join),
+            None => lhs,
+        };
+        let direction = match sort_order {
+            SortOrder::Ascending => "ASC",
+            SortOrder::Descending => "DESC",
+        };
+        Ok(format!("{} IS NULL, {} {}", expr, expr, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,219 @@
+use bumpalo::Bump;
+use rusqlite::{params_from_iter, types::Value, Connection};
+use time::{format_description::well_known::Rfc3339, UtcOffset};
+
+use crate::{
+    filter::{parse_filter, AttrPathRef},
+    manager::SortOrder,
+    schema::test_registry,
+};
+
+use super::{SqlColumn, SqlTranslator, SqlValue};
+
+const ENTERPRISE: &str = "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User";
+
+fn columns(path: AttrPathRef) -> Option<SqlColumn> {
+    let expr = |expr: &str| Some(SqlColumn::Expr(expr.to_string()));
+    match (path.urn, path.name, path.sub_attr) {
+        (None, "userName", None) => expr("users.user_name"),
+        (None, "externalId", None) => expr("users.external_id"),
+        (None, "title", None) => expr("users.title"),
+        (None, "active", None) => expr("users.active"),
+        (None, "loginCount", None) => expr("users.login_count"),
+        (None, "lastLogin", None) => expr("users.last_login"),
+        (None, "name", Some("familyName")) => expr("users.family_name"),
+        (None, "emails", None) => Some(SqlColumn::Table {
+            table: "user_emails".to_string(),
+            join: "user_emails.user_id = users.id".to_string(),
+        }),
+        (None, "emails", Some("value")) => expr("user_emails.value"),
+        (None, "emails", Some("type")) => expr("user_emails.type"),
+        (Some(ENTERPRISE), "employeeNumber", None) => expr("users.employee_number"),
+        _ => None,
+    }
+}
+
+fn connection() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        PRAGMA case_sensitive_like = ON;
+        CREATE TABLE users (
+            id INTEGER PRIMARY KEY,
+            user_name TEXT NOT NULL,
+            external_id TEXT,
+            title TEXT,
+            active BOOLEAN,
+            login_count INTEGER,
+            last_login TEXT,
+            family_name TEXT,
+            employee_number TEXT
+        );
+        CREATE TABLE user_emails (user_id INTEGER, value TEXT, type TEXT);
+        INSERT INTO users VALUES
+            (1, 'Alice', 'Al', 'Engineer', TRUE, 12, '2020-03-01T09:00:00Z', 'Smith', '100%'),
+            (2, 'bob', NULL, NULL, FALSE, 3, '2021-06-15T17:30:00Z', 'Jones', '701984'),
+            (3, 'Carol', 'carol_1', 'Manager', TRUE, NULL, NULL, NULL, NULL);
+        INSERT INTO user_emails VALUES
+            (1, 'alice@example.com', 'work'),
+            (1, 'alice@home.org', 'home'),
+            (2, 'bob@example.com', 'home');
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+fn query(filter: &str) -> Vec<String> {
+    let scope = Bump::new();
+    let mut filter = parse_filter(filter).unwrap();
+    let registry = test_registry();
+    filter.coerce(&registry).unwrap();
+    let fragment = SqlTranslator::new(&registry, columns)
+        .where_clause(&filter.as_ref(&scope))
+        .unwrap();
+    let params = fragment.params.into_iter().map(|param| match param {
+        SqlValue::Bool(b) => Value::Integer(b.into()),
+        SqlValue::Int(n) => Value::Integer(n),
+        SqlValue::Float(n) => Value::Real(n),
+        SqlValue::Text(s) => Value::Text(s),
+        SqlValue::DateTime(dt) => {
+            Value::Text(dt.0.to_offset(UtcOffset::UTC).format(&Rfc3339).unwrap())
+        }
+    });
+    let conn = connection();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT user_name FROM users WHERE {} ORDER BY id",
+            fragment.sql
+        ))
+        .unwrap();
+    stmt.query_map(params_from_iter(params), |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_where_clause() {
+    let cases: &[(&str, &[&str])] = &[
+        ("userName eq \"ALICE\"", &["Alice"]),
+        ("userName ne \"alice\"", &["bob", "Carol"]),
+        ("externalId eq \"al\"", &[]),
+        ("externalId eq \"Al\"", &["Alice"]),
+        ("externalId sw \"CAROL\"", &[]),
+        ("externalId co \"_\"", &["Carol"]),
+        ("userName sw \"c\"", &["Carol"]),
+        ("userName ew \"OB\"", &["bob"]),
+        ("title pr", &["Alice", "Carol"]),
+        ("title eq null", &["bob"]),
+        ("title ne \"engineer\"", &["bob", "Carol"]),
+        ("not (title eq \"Engineer\")", &["bob", "Carol"]),
+        ("active eq true", &["Alice", "Carol"]),
+        ("loginCount gt 3", &["Alice"]),
+        ("loginCount eq \"12\"", &["Alice"]),
+        ("active eq \"false\"", &["bob"]),
+        ("lastLogin gt \"2021-01-01T00:00:00Z\"", &["bob"]),
+        (
+            "lastLogin gt \"2020-03-01T10:00:00+02:00\"",
+            &["Alice", "bob"],
+        ),
+        (
+            "loginCount lt 4 or name.familyName eq \"smith\"",
+            &["Alice", "bob"],
+        ),
+        ("name.familyName pr and active eq false", &["bob"]),
+        ("emails pr", &["Alice", "bob"]),
+        ("emails co \"example.com\"", &["Alice", "bob"]),
+        ("emails.type eq \"home\"", &["Alice", "bob"]),
+        ("emails.type ne \"home\"", &["Carol"]),
+        ("emails eq null", &["Carol"]),
+        (
+            "emails[type eq \"work\" and value ew \"example.com\"]",
+            &["Alice"],
+        ),
+        (
+            "emails[type eq \"home\" and value ew \"example.com\"]",
+            &["bob"],
+        ),
+        ("not (emails[type eq \"work\"])", &["bob", "Carol"]),
+        (
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:employeeNumber co \"0%\"",
+            &["Alice"],
+        ),
+    ];
+    for (filter, expected) in cases {
+        assert_eq!(query(filter), *expected, "{}", filter);
+    }
+}
+
+#[test]
+fn test_sql() {
+    let scope = Bump::new();
+    let registry = test_registry();
+    let translator = SqlTranslator::new(&registry, columns).numbered_params();
+
+    let filter = parse_filter("emails[type eq \"work\"] and userName co \"a_b\"").unwrap();
+    let fragment = translator.where_clause(&filter.as_ref(&scope)).unwrap();
+    assert_eq!(
+        fragment.sql,
+        "(EXISTS (SELECT 1 FROM user_emails WHERE user_emails.user_id = users.id AND \
+         LOWER(user_emails.type) = $1) AND LOWER(users.user_name) LIKE $2 ESCAPE '!')"
+    );
+    assert_eq!(
+        fragment.params,
+        [
+            SqlValue::Text("work".to_string()),
+            SqlValue::Text("%a!_b%".to_string())
+        ]
+    );
+
+    let filter = parse_filter("displayName eq \"Alice\"").unwrap();
+    assert!(translator.where_clause(&filter.as_ref(&scope)).is_err());
+}
+
+#[test]
+fn test_order_by() {
+    let registry = test_registry();
+    let translator = SqlTranslator::new(&registry, columns);
+    let path = |name, sub_attr| AttrPathRef {
+        urn: None,
+        name,
+        sub_attr,
+    };
+    let order_by = translator
+        .order_by(path("userName", None), SortOrder::Ascending)
+        .unwrap();
+    assert_eq!(
+        order_by,
+        "LOWER(users.user_name) IS NULL, LOWER(users.user_name) ASC"
+    );
+
+    let conn = connection();
+    let order_by = translator
+        .order_by(path("name", Some("familyName")), SortOrder::Descending)
+        .unwrap();
+    let names: Vec<String> = conn
+        .prepare(&format!(
+            "SELECT user_name FROM users ORDER BY {}",
+            order_by
+        ))
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(names, ["Alice", "bob", "Carol"]);
+
+    let order_by = translator
+        .order_by(path("emails", None), SortOrder::Ascending)
+        .unwrap();
+    assert_eq!(
+        order_by,
+        "(SELECT MIN(LOWER(user_emails.value)) FROM user_emails WHERE user_emails.user_id = users.id) IS NULL, \
+         (SELECT MIN(LOWER(user_emails.value)) FROM user_emails WHERE user_emails.user_id = users.id) ASC"
+    );
+    assert!(translator
+        .order_by(path("displayName", None), SortOrder::Ascending)
+        .is_err());
+}
@@ -2,7 +2,13 @@ use std::{collections::BTreeMap, sync::Arc};
 
 use cream_core::{ResourceType, Schema};
 
-use crate::{config::ServiceProviderConfig, manager::GenericResourceManager};
+use crate::{
+    config::ServiceProviderConfig,
+    filter::{FilterCapabilities, FilterLimits},
+    manager::GenericResourceManager,
+    schema::SchemaRegistry,
+    subject::SubjectResolver,
+};
 
 /// The main entry point for the `cream` library.
 #[derive(Clone, Debug)]
@@ -11,7 +17,9 @@ pub struct Cream(pub(crate) Arc<InnerState>);
 #[derive(Debug)]
 pub(crate) struct ResourceTypeState {
     pub(crate) resource_type: ResourceType,
+    pub(crate) schemas: SchemaRegistry,
     pub(crate) manager: Box<dyn GenericResourceManager>,
+    pub(crate) filter_capabilities: Option<FilterCapabilities>,
 }
 
 #[derive(Debug)]
@@ -20,4 +28,7 @@ pub(crate) struct InnerState {
     pub(crate) config: ServiceProviderConfig,
     pub(crate) schemas: BTreeMap<String, Schema>,
     pub(crate) resource_types: BTreeMap<String, ResourceTypeState>,
+    pub(crate) subject_resolver: Option<Box<dyn SubjectResolver>>,
+    pub(crate) filter_limits: FilterLimits,
+    pub(crate) max_residual_scan: usize,
 }
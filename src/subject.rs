@@ -0,0 +1,24 @@
+use std::fmt::Debug;
+
+use axum::http::request::Parts;
+
+use crate::error::Error;
+
+/// Resolves the resource corresponding to the authenticated subject of a request. Required to
+/// support the `/Me` endpoint.
+#[async_trait::async_trait]
+pub trait SubjectResolver: Debug + Send + Sync + 'static {
+    /// Determine which resource the client is authenticated as. This should typically return
+    /// an error with a `401` or `403` status if the request is not authenticated as a resource
+    /// managed by cream.
+    async fn resolve(&self, parts: &'async_trait Parts) -> Result<Subject, Error>;
+}
+
+/// A resource which a request is authenticated as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subject {
+    /// The name of the resource type, eg. `User`.
+    pub resource_type: String,
+    /// The ID of the resource.
+    pub id: String,
+}
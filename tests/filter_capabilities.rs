@@ -0,0 +1,239 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+};
+use cream::{
+    declare_resource,
+    filter::{FilterRef, FILTER_CAPABILITIES_SCHEMA},
+    hidden::axum::http::request::Parts,
+    load_static_json, CreamBuilder, Error, GetResourceArgs, ListResourceArgs, ListResourceResult,
+    UpdateResourceArgs,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+declare_resource!("examples/user_type.json" as User [
+    "examples/user_schema.json",
+] filter {
+    "userName": eq, sw;
+    "emails.type": eq;
+    "emails": pr, co;
+    "active": eq;
+    "meta.lastModified": gt;
+});
+
+#[derive(Debug, Default, Clone)]
+struct Manager {
+    clauses: Arc<Mutex<Vec<String>>>,
+}
+
+fn collect_clauses(filter: &FilterRef, clauses: &mut Vec<String>) {
+    match *filter {
+        FilterRef::And(filters) | FilterRef::Or(filters) => {
+            for filter in filters {
+                collect_clauses(filter, clauses);
+            }
+        }
+        FilterRef::Has(_, filter) | FilterRef::Not(filter) => collect_clauses(filter, clauses),
+        _ => {
+            let clause = UserFilterClause::from_filter(filter).expect("supported clause");
+            clauses.push(match clause {
+                UserFilterClause::UserNameEq(value) => format!("userName = {}", value),
+                UserFilterClause::UserNameNe(value) => format!("userName != {}", value),
+                UserFilterClause::UserNameSw(value) => format!("userName starts {}", value),
+                UserFilterClause::EmailsTypeEq(value) => format!("emails.type = {}", value),
+                UserFilterClause::EmailsTypeNe(value) => format!("emails.type != {}", value),
+                UserFilterClause::EmailsPr => "emails present".to_string(),
+                UserFilterClause::EmailsAbsent => "emails absent".to_string(),
+                UserFilterClause::EmailsCo(value) => format!("emails contains {}", value),
+                UserFilterClause::ActiveEq(value) => format!("active = {}", value),
+                UserFilterClause::ActiveNe(value) => format!("active != {}", value),
+                UserFilterClause::MetaLastModifiedGt(value) => {
+                    format!("lastModified > {}", value.0.unix_timestamp())
+                }
+            });
+        }
+    }
+}
+
+#[cream::hidden::async_trait::async_trait]
+impl UserManager for Manager {
+    async fn list(
+        &self,
+        _parts: &'async_trait Parts,
+        args: ListResourceArgs<'async_trait>,
+    ) -> Result<ListResourceResult<User>, Error> {
+        let mut clauses = self.clauses.lock().unwrap();
+        if let Some(filter) = &args.filter {
+            collect_clauses(filter, &mut clauses);
+        }
+        Ok(ListResourceResult::default())
+    }
+    async fn get(
+        &self,
+        _parts: &'async_trait Parts,
+        _args: GetResourceArgs<'async_trait>,
+    ) -> Result<User, Error> {
+        Err(Error::not_found())
+    }
+    async fn create(
+        &self,
+        _parts: &'async_trait Parts,
+        _resource: CreateUser,
+    ) -> Result<String, Error> {
+        Err(Error::not_found())
+    }
+    async fn update(
+        &self,
+        _parts: &'async_trait Parts,
+        _args: UpdateResourceArgs<'async_trait>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    async fn replace(
+        &self,
+        _parts: &'async_trait Parts,
+        _id: &'async_trait str,
+        _resource: CreateUser,
+        _version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    async fn delete(
+        &self,
+        _parts: &'async_trait Parts,
+        _id: &'async_trait str,
+        _version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+}
+
+async fn send(manager: &Manager, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let cream = CreamBuilder::new(
+        "http://localhost",
+        load_static_json!("../examples/smoke_config.json"),
+    )
+    .resource_type(User::manage(manager.clone()))
+    .build();
+    let request = match body {
+        Some(body) => Request::post(uri)
+            .header("content-type", "application/scim+json")
+            .body(Body::from(body.to_string())),
+        None => Request::get(uri).body(Body::empty()),
+    };
+    let response = cream.router().oneshot(request.unwrap()).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn search(manager: &Manager, filter: &str) -> (StatusCode, Value) {
+    send(
+        manager,
+        "/Users/.search",
+        Some(json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:SearchRequest"],
+            "filter": filter,
+        })),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_supported_filters() {
+    let manager = Manager::default();
+    let (status, _) = search(
+        &manager,
+        r#"USERNAME sw "b" and (emails[type eq "work"] or emails pr) and not (emails eq null)"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = search(
+        &manager,
+        r#"active eq "true" and meta.lastModified gt "2011-05-13T04:42:34Z""#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    // A negated `eq` comparison is normalized to `ne`, which is supported along with `eq`.
+    let (status, _) = search(
+        &manager,
+        r#"not (userName eq "x") and emails[not (type eq "home")]"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        *manager.clauses.lock().unwrap(),
+        [
+            "userName starts b",
+            "emails.type = work",
+            "emails present",
+            "emails present",
+            "active = true",
+            "lastModified > 1305261754",
+            "userName != x",
+            "emails.type != home",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_unsupported_filters() {
+    let manager = Manager::default();
+    let cases = [
+        (
+            r#"displayName eq "Babs""#,
+            "Filtering on `displayName` is not supported",
+        ),
+        (
+            r#"userName co "b""#,
+            "Filtering on `userName` with `co` is not supported. Supported operators are: eq, sw",
+        ),
+        (
+            "userName pr",
+            "Filtering on `userName` with `pr` is not supported. Supported operators are: eq, sw",
+        ),
+        (
+            "active ne null",
+            "Filtering on `active` with `ne null` is not supported. Supported operators are: eq",
+        ),
+        (
+            "userName eq 5",
+            "Filtering on `userName` requires a string value, not 5",
+        ),
+    ];
+    for (filter, expected) in cases {
+        let (status, body) = search(&manager, filter).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", filter);
+        assert_eq!(body["scimType"], "invalidFilter");
+        assert_eq!(body["detail"], expected);
+    }
+    assert!(manager.clauses.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_discovery() {
+    let (status, body) = send(&Manager::default(), "/ResourceTypes/User", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["schemas"],
+        json!([
+            "urn:ietf:params:scim:schemas:core:2.0:ResourceType",
+            FILTER_CAPABILITIES_SCHEMA
+        ])
+    );
+    assert_eq!(
+        body[FILTER_CAPABILITIES_SCHEMA],
+        json!({
+            "attributes": [
+                { "path": "userName", "operators": ["eq", "sw"] },
+                { "path": "emails.type", "operators": ["eq"] },
+                { "path": "emails", "operators": ["pr", "co"] },
+                { "path": "active", "operators": ["eq"] },
+                { "path": "meta.lastModified", "operators": ["gt"] }
+            ]
+        })
+    );
+}
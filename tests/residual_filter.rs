@@ -0,0 +1,290 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+};
+use cream::{
+    filter::FilterRef, hidden::axum::http::request::Parts, load_static_json, CreamBuilder, Error,
+    GenericResourceManager, GetResourceArgs, ListResourceArgs, ListResourceResult, ResourceType,
+    Schema, SchemaRegistry, UpdateResourceArgs,
+};
+use ijson::{ijson, IObject};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+// A store which can only filter on `userName`, and returns at most two users at a time.
+#[derive(Debug, Clone)]
+struct Manager {
+    users: Vec<IObject>,
+    filters: Arc<Mutex<Vec<Option<String>>>>,
+    reports_total: bool,
+}
+
+impl Manager {
+    fn new() -> Self {
+        let users = [
+            ("alice", true),
+            ("bob", false),
+            ("bella", true),
+            ("brian", true),
+            ("carl", true),
+        ];
+        Self {
+            users: users
+                .into_iter()
+                .map(|(user_name, active)| {
+                    ijson!({
+                        "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+                        "id": user_name,
+                        "userName": user_name,
+                        "active": active,
+                    })
+                    .into_object()
+                    .unwrap()
+                })
+                .collect(),
+            filters: Arc::default(),
+            reports_total: true,
+        }
+    }
+
+    // Leaves `total_count` at zero, as managers which cannot count cheaply do.
+    fn without_total(self) -> Self {
+        Self {
+            reports_total: false,
+            ..self
+        }
+    }
+
+    fn registry(&self) -> SchemaRegistry {
+        let schema = self.load_schema("urn:ietf:params:scim:schemas:core:2.0:User");
+        SchemaRegistry::new(&self.load_resource_type(), |_| &schema)
+    }
+}
+
+#[cream::hidden::async_trait::async_trait]
+impl GenericResourceManager for Manager {
+    async fn list(
+        &self,
+        _parts: &'async_trait Parts,
+        args: ListResourceArgs<'async_trait>,
+    ) -> Result<ListResourceResult<IObject>, Error> {
+        self.filters
+            .lock()
+            .unwrap()
+            .push(args.filter.map(|filter| filter.to_string()));
+        let registry = self.registry();
+        let matching: Vec<_> = self
+            .users
+            .iter()
+            .filter(|user| {
+                args.filter
+                    .is_none_or(|filter| filter.matches(user, &registry))
+            })
+            .cloned()
+            .collect();
+        Ok(ListResourceResult {
+            total_count: if self.reports_total {
+                matching.len()
+            } else {
+                0
+            },
+            items_per_page: args.count,
+            resources: matching
+                .into_iter()
+                .skip(args.start_index)
+                .take(args.count)
+                .collect(),
+            next_cursor: None,
+            previous_cursor: None,
+        })
+    }
+    async fn get(
+        &self,
+        _parts: &'async_trait Parts,
+        _args: GetResourceArgs<'async_trait>,
+    ) -> Result<IObject, Error> {
+        Err(Error::not_found())
+    }
+    async fn create(
+        &self,
+        _parts: &'async_trait Parts,
+        _resource: IObject,
+    ) -> Result<String, Error> {
+        Err(Error::not_found())
+    }
+    async fn update(
+        &self,
+        _parts: &'async_trait Parts,
+        _args: UpdateResourceArgs<'async_trait>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    async fn replace(
+        &self,
+        _parts: &'async_trait Parts,
+        _id: &str,
+        _resource: IObject,
+        _version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    async fn delete(
+        &self,
+        _parts: &'async_trait Parts,
+        _id: &str,
+        _version: Option<&'async_trait str>,
+    ) -> Result<(), Error> {
+        Err(Error::not_found())
+    }
+    fn max_page_size(&self) -> usize {
+        2
+    }
+    fn can_push_down(&self, filter: &FilterRef) -> bool {
+        match *filter {
+            FilterRef::Present(path) | FilterRef::Compare(path, _, _) => {
+                path.name.eq_ignore_ascii_case("userName")
+            }
+            _ => false,
+        }
+    }
+    fn load_resource_type(&self) -> ResourceType {
+        load_static_json!("../examples/user_type.json")
+    }
+    fn load_schema(&self, _id: &str) -> Schema {
+        load_static_json!("../examples/user_schema.json")
+    }
+}
+
+fn builder(manager: &Manager) -> CreamBuilder {
+    CreamBuilder::new(
+        "http://localhost",
+        load_static_json!("../examples/smoke_config.json"),
+    )
+    .resource_type(manager.clone())
+}
+
+async fn send(builder: CreamBuilder, body: Value) -> (StatusCode, Value) {
+    let request = Request::post("/Users/.search")
+        .header("content-type", "application/scim+json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = builder.build().router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn search(manager: &Manager, body: Value) -> Value {
+    let (status, body) = send(builder(manager), body).await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+fn user_names(body: &Value) -> Vec<&str> {
+    body["Resources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|user| user["userName"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_residual_filter() {
+    let manager = Manager::new();
+    let body = search(
+        &manager,
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:SearchRequest"],
+            "filter": r#"userName sw "b" and active eq true"#,
+        }),
+    )
+    .await;
+    assert_eq!(body["totalResults"], 2);
+    assert_eq!(user_names(&body), ["bella", "brian"]);
+    // Every page matching the pushed down part of the filter is fetched
+    assert_eq!(
+        *manager.filters.lock().unwrap(),
+        vec![Some(r#"userName sw "b""#.to_string()); 2]
+    );
+}
+
+#[tokio::test]
+async fn test_residual_filter_pagination() {
+    let manager = Manager::new();
+    let body = search(
+        &manager,
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:SearchRequest"],
+            "filter": "active eq true",
+            "startIndex": 2,
+            "count": 2,
+        }),
+    )
+    .await;
+    assert_eq!(body["totalResults"], 4);
+    assert_eq!(body["startIndex"], 2);
+    assert_eq!(body["itemsPerPage"], 2);
+    assert_eq!(user_names(&body), ["bella", "brian"]);
+    assert_eq!(*manager.filters.lock().unwrap(), [None, None, None]);
+}
+
+#[tokio::test]
+async fn test_residual_filter_without_total() {
+    let manager = Manager::new().without_total();
+    let body = search(
+        &manager,
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:SearchRequest"],
+            "filter": "active eq true",
+            "startIndex": 3,
+        }),
+    )
+    .await;
+    assert_eq!(body["totalResults"], 4);
+    assert_eq!(user_names(&body), ["brian", "carl"]);
+    // Pages are fetched until one comes back short
+    assert_eq!(*manager.filters.lock().unwrap(), [None, None, None]);
+}
+
+#[tokio::test]
+async fn test_pushed_down_filter() {
+    let manager = Manager::new();
+    let body = search(
+        &manager,
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:SearchRequest"],
+            "filter": r#"userName sw "b" or userName eq "carl""#,
+            "count": 2,
+        }),
+    )
+    .await;
+    assert_eq!(body["totalResults"], 4);
+    assert_eq!(user_names(&body), ["bob", "bella"]);
+    assert_eq!(manager.filters.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_max_residual_scan() {
+    let manager = Manager::new();
+    let body = json!({
+        "schemas": ["urn:ietf:params:scim:api:messages:2.0:SearchRequest"],
+        "filter": "active eq true",
+    });
+
+    let (status, error) = send(builder(&manager).max_residual_scan(4), body.clone()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["scimType"], "tooMany");
+    assert_eq!(
+        error["detail"],
+        "Evaluating the filter requires scanning more than the maximum of 4 resources"
+    );
+    // No more than one resource beyond the maximum is fetched.
+    assert_eq!(*manager.filters.lock().unwrap(), [None, None, None]);
+
+    let (status, body) = send(builder(&manager).max_residual_scan(5), body).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["totalResults"], 4);
+}